@@ -0,0 +1,174 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use std::borrow::Cow;
+use std::io;
+use std::io::ErrorKind;
+
+/// How the payload of a `SetChunk` is encoded on the wire.
+///
+/// The codec is chosen per chunk, so chunks that do not compress well can be stored as is.
+#[repr(u8)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChunkCodec {
+    /// The payload holds the octets of the chunk as is.
+    #[default]
+    Stored = 0x00,
+    /// The payload is a sequence of `(run length, octet)` pairs, which suits chunks with long
+    /// runs of the same octet, e.g. zero padding.
+    RunLength = 0x01,
+    /// The payload is an LZ4 block, prefixed with the decompressed length as a little-endian
+    /// `u32`. Only available with the `compression` feature.
+    #[cfg(feature = "compression")]
+    Lz4 = 0x02,
+}
+
+/// The highest ratio between the decompressed and compressed length of an LZ4 block, used to
+/// reject a length prefix that no valid block could have before allocating for it.
+#[cfg(feature = "compression")]
+const LZ4_MAX_COMPRESSION_RATIO: usize = 255;
+
+impl TryFrom<u8> for ChunkCodec {
+    type Error = io::Error;
+
+    fn try_from(value: u8) -> io::Result<Self> {
+        match value {
+            0x00 => Ok(Self::Stored),
+            0x01 => Ok(Self::RunLength),
+            #[cfg(feature = "compression")]
+            0x02 => Ok(Self::Lz4),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unknown chunk codec {value}"),
+            )),
+        }
+    }
+}
+
+impl ChunkCodec {
+    /// Encodes the octets of a chunk with this codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `octets` - The octets of the chunk.
+    ///
+    /// # Returns
+    ///
+    /// The encoded payload, which may be larger than `octets` for data that does not compress.
+    #[must_use]
+    pub fn encode(self, octets: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Stored => octets.to_vec(),
+            Self::RunLength => {
+                let mut encoded = Vec::new();
+                let mut remaining = octets;
+                while let Some(&octet) = remaining.first() {
+                    let run_length = remaining
+                        .iter()
+                        .take(u8::MAX as usize)
+                        .take_while(|&&other| other == octet)
+                        .count();
+                    #[allow(clippy::cast_possible_truncation)]
+                    encoded.push(run_length as u8);
+                    encoded.push(octet);
+                    remaining = &remaining[run_length..];
+                }
+                encoded
+            }
+            #[cfg(feature = "compression")]
+            Self::Lz4 => lz4_flex::compress_prepend_size(octets),
+        }
+    }
+
+    /// Decodes a payload that was encoded with this codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The encoded payload.
+    /// * `max_octet_size` - The maximum number of octets that a compressed payload may decode
+    ///   to, usually the size of the chunk. Decoding stops as soon as it is exceeded, before
+    ///   more memory is allocated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::InvalidData` if the payload is not valid for the codec,
+    /// or if a compressed payload decodes to more than `max_octet_size` octets.
+    ///
+    /// # Returns
+    ///
+    /// The octets of the chunk. A stored payload is borrowed instead of copied.
+    pub fn decode(self, payload: &[u8], max_octet_size: usize) -> io::Result<Cow<'_, [u8]>> {
+        match self {
+            Self::Stored => Ok(Cow::Borrowed(payload)),
+            Self::RunLength => {
+                if !payload.len().is_multiple_of(2) {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        "run length payload must consist of (run length, octet) pairs",
+                    ));
+                }
+                let mut octets = Vec::new();
+                for pair in payload.chunks_exact(2) {
+                    if pair[0] == 0 {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidData,
+                            "run length payload contains an empty run",
+                        ));
+                    }
+                    let octet_count = octets.len() + pair[0] as usize;
+                    if octet_count > max_octet_size {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!(
+                                "run length payload decodes to more than {max_octet_size} octets"
+                            ),
+                        ));
+                    }
+                    octets.resize(octet_count, pair[1]);
+                }
+                Ok(Cow::Owned(octets))
+            }
+            #[cfg(feature = "compression")]
+            Self::Lz4 => {
+                let Some((length_prefix, block)) = payload.split_first_chunk::<4>() else {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        "lz4 payload is missing the decompressed length",
+                    ));
+                };
+                let octet_length = u32::from_le_bytes(*length_prefix) as usize;
+                if octet_length > max_octet_size {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "lz4 payload decompresses to {octet_length} octets, more than {max_octet_size}"
+                        ),
+                    ));
+                }
+                if octet_length > block.len().saturating_mul(LZ4_MAX_COMPRESSION_RATIO) {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "lz4 payload of {} octets can not decompress to {octet_length} octets",
+                            block.len()
+                        ),
+                    ));
+                }
+                let octets = lz4_flex::decompress(block, octet_length)
+                    .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))?;
+                if octets.len() != octet_length {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "lz4 payload decompressed to {} octets, expected {octet_length}",
+                            octets.len()
+                        ),
+                    ));
+                }
+                Ok(Cow::Owned(octets))
+            }
+        }
+    }
+}
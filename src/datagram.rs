@@ -0,0 +1,149 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::protocol_front::SetChunkFrontData;
+
+/// Groups `SetChunk` commands into datagrams.
+///
+/// A datagram is filled with chunks until either the octet budget or the chunk count cap
+/// would be exceeded. Capping the number of chunks in a datagram trades some overhead for
+/// resilience, since a lost datagram then loses fewer chunks.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DatagramBatcher {
+    max_octet_size: usize,
+    max_chunks_per_datagram: usize,
+}
+
+impl DatagramBatcher {
+    /// Creates a new `DatagramBatcher` that only limits the number of octets in a datagram.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_octet_size` - The maximum number of octets in a datagram, typically based on the MTU.
+    ///
+    /// # Returns
+    ///
+    /// A new `DatagramBatcher` instance.
+    #[must_use]
+    pub const fn new(max_octet_size: usize) -> Self {
+        Self {
+            max_octet_size,
+            max_chunks_per_datagram: usize::MAX,
+        }
+    }
+
+    /// Sets the maximum number of chunks in a single datagram.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_chunks_per_datagram` - The maximum number of chunks in a datagram.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `max_chunks_per_datagram` is zero.
+    ///
+    /// # Returns
+    ///
+    /// The `DatagramBatcher` with the cap applied.
+    #[must_use]
+    pub const fn with_max_chunks_per_datagram(mut self, max_chunks_per_datagram: usize) -> Self {
+        assert!(
+            max_chunks_per_datagram > 0,
+            "max_chunks_per_datagram must be greater than zero"
+        );
+        self.max_chunks_per_datagram = max_chunks_per_datagram;
+        self
+    }
+
+    /// Returns the maximum number of octets in a datagram.
+    #[must_use]
+    pub const fn max_octet_size(&self) -> usize {
+        self.max_octet_size
+    }
+
+    /// Returns the maximum number of chunks in a datagram.
+    #[must_use]
+    pub const fn max_chunks_per_datagram(&self) -> usize {
+        self.max_chunks_per_datagram
+    }
+
+    /// Groups the chunks into datagrams, keeping the order of the chunks.
+    ///
+    /// The size of each chunk is the size of its serialized `SetChunk` command, including the
+    /// command octet. A chunk that is larger than the octet budget on its own is put in a
+    /// datagram by itself, since chunks can not be split.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - The chunks to send.
+    ///
+    /// # Returns
+    ///
+    /// The chunks grouped into datagrams.
+    #[must_use]
+    pub fn batch(&self, chunks: Vec<SetChunkFrontData>) -> Vec<Vec<SetChunkFrontData>> {
+        let mut datagrams = Vec::new();
+        let mut datagram = Vec::new();
+        let mut datagram_octet_size = 0;
+
+        for chunk in chunks {
+            let octet_size = chunk.command_octet_size();
+            if self.is_full(datagram.len(), datagram_octet_size, octet_size) {
+                datagrams.push(core::mem::take(&mut datagram));
+                datagram_octet_size = 0;
+            }
+            datagram_octet_size += octet_size;
+            datagram.push(chunk);
+        }
+
+        if !datagram.is_empty() {
+            datagrams.push(datagram);
+        }
+
+        datagrams
+    }
+
+    /// Returns the number of datagrams that [`Self::batch`] would produce for chunks with the
+    /// given sizes, without needing the chunks themselves.
+    ///
+    /// # Arguments
+    ///
+    /// * `command_octet_sizes` - The sizes of the serialized `SetChunk` commands, in send order.
+    ///
+    /// # Returns
+    ///
+    /// The number of datagrams.
+    #[must_use]
+    pub fn datagram_count(&self, command_octet_sizes: impl IntoIterator<Item = usize>) -> usize {
+        let mut datagram_count = 0;
+        let mut chunk_count = 0;
+        let mut datagram_octet_size = 0;
+
+        for octet_size in command_octet_sizes {
+            if chunk_count == 0 || self.is_full(chunk_count, datagram_octet_size, octet_size) {
+                datagram_count += 1;
+                chunk_count = 0;
+                datagram_octet_size = 0;
+            }
+            chunk_count += 1;
+            datagram_octet_size += octet_size;
+        }
+
+        datagram_count
+    }
+
+    /// Checks if the current datagram, holding `chunk_count` commands of `datagram_octet_size`
+    /// octets in total, must be closed before a command of `octet_size` octets is added.
+    /// An empty datagram always takes the command, even if it is too large on its own.
+    const fn is_full(
+        &self,
+        chunk_count: usize,
+        datagram_octet_size: usize,
+        octet_size: usize,
+    ) -> bool {
+        chunk_count > 0
+            && (chunk_count >= self.max_chunks_per_datagram
+                || datagram_octet_size + octet_size > self.max_octet_size)
+    }
+}
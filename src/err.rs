@@ -7,8 +7,29 @@ pub enum BlobError {
     InvalidChunkIndex(usize, usize),
     UnexpectedChunkSize(usize, usize, usize),
     OutOfBounds,
+    PayloadExceedsBlob {
+        chunk_index: ChunkIndex,
+        end: usize,
+        blob_len: usize,
+    },
     RedundantSameContents(ChunkIndex),
     RedundantContentDiffers(ChunkIndex),
+    OverlappingChunk {
+        existing_range: Range<usize>,
+        new_range: Range<usize>,
+    },
+    InconsistentOctetCount(usize, usize),
+    OutOfWindow(ChunkIndex, Range<usize>),
+    HashMismatch,
+    ReceivedChunkBeyondSize(ChunkIndex, usize),
+    TruncateBeyondSize(usize, usize),
+    ChunkSizeTooSmall(usize, usize),
+    CheckpointMismatch(usize),
+    InvalidChunkSize(usize),
+    #[cfg(feature = "std")]
+    InvalidPayload(ChunkIndex, std::io::Error),
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
 }
 
 impl fmt::Display for BlobError {
@@ -22,33 +43,248 @@ impl fmt::Display for BlobError {
                 "unexpected chunk size. expected {expected} but encountered {found} for chunk {id}"
             ),
             Self::OutOfBounds => write!(f, "calculated slice range is out of bounds"),
+            Self::PayloadExceedsBlob { chunk_index, end, blob_len } => write!(f, "chunk {chunk_index} ends at octet {end}, past the end of the blob of {blob_len} octets"),
             Self::RedundantSameContents(chunk_index) => write!(f, "chunk {chunk_index} has already been received"),
             Self::RedundantContentDiffers(chunk_index) => write!(f, "chunk {chunk_index} has already been received, but now received different content for that chunk. this is serious"),
+            Self::OverlappingChunk { existing_range, new_range } => write!(f, "chunk octets {new_range:?} overlap previously received octets {existing_range:?} with different content"),
+            Self::OutOfWindow(chunk_index, window) => write!(f, "chunk {chunk_index} is outside of the acceptance window {window:?}"),
+            Self::ReceivedChunkBeyondSize(chunk_index, octet_count) => write!(f, "chunk {chunk_index} has already been received, but does not fit a blob of {octet_count} octets"),
+            Self::TruncateBeyondSize(octet_count, blob_len) => write!(f, "can not truncate a blob of {blob_len} octets to {octet_count} octets"),
+            Self::ChunkSizeTooSmall(chunk_size, min_chunk_size) => write!(f, "chunk size {chunk_size} is below the minimum chunk size {min_chunk_size}"),
+            Self::InvalidChunkSize(chunk_size) => write!(f, "chunk size {chunk_size} is invalid, it must be greater than zero"),
+            Self::CheckpointMismatch(chunk_count) => write!(f, "the hash of the first {chunk_count} chunks does not match the checkpoint"),
+            Self::HashMismatch => write!(f, "the hash of the complete blob does not match the expected hash"),
+            Self::InconsistentOctetCount(expected, found) => write!(f, "inconsistent blob length. expected {expected} octets but the chunks and storage add up to {found}"),
+            #[cfg(feature = "std")]
+            Self::InvalidPayload(chunk_index, err) => write!(f, "the payload of chunk {chunk_index} can not be decoded: {err}"),
+            #[cfg(feature = "std")]
+            Self::Io(err) => write!(f, "could not write to the storage: {err}"),
         }
     }
 }
 
 impl Error for BlobError {} // it implements Debug and Display
 
+#[cfg(feature = "tracing")]
+impl BlobError {
+    /// Returns the `outcome` field of the tracing events for a chunk that failed with this error.
+    pub(crate) const fn outcome(&self) -> &'static str {
+        match self {
+            Self::RedundantSameContents(_) => "duplicate",
+            Self::RedundantContentDiffers(_) => "conflict",
+            _ => "error",
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+use crate::in_logic_front::PeerId;
+#[cfg(feature = "std")]
+use crate::protocol::MAX_PAYLOAD_OCTET_SIZE;
 use crate::ChunkIndex;
+use core::error::Error;
 use core::fmt;
-use std::error::Error;
+use core::ops::Range;
+#[cfg(feature = "std")]
 use std::io;
 
+#[cfg(feature = "std")]
+impl BlobError {
+    /// Returns the kind of the `io::Error` that this error is converted to.
+    #[must_use]
+    pub fn io_error_kind(&self) -> io::ErrorKind {
+        match self {
+            // Map your custom error to an appropriate io::Error kind
+            Self::InvalidChunkIndex(_, _) | Self::OutOfWindow(_, _) => io::ErrorKind::InvalidInput,
+            Self::OutOfBounds => io::ErrorKind::UnexpectedEof,
+            Self::Io(io_err) => io_err.kind(),
+            Self::RedundantSameContents(_) => io::ErrorKind::AlreadyExists,
+            Self::RedundantContentDiffers(_)
+            | Self::UnexpectedChunkSize(_, _, _)
+            | Self::OverlappingChunk { .. }
+            | Self::PayloadExceedsBlob { .. }
+            | Self::InconsistentOctetCount(_, _)
+            | Self::HashMismatch
+            | Self::ReceivedChunkBeyondSize(_, _)
+            | Self::TruncateBeyondSize(_, _)
+            | Self::ChunkSizeTooSmall(_, _)
+            | Self::CheckpointMismatch(_)
+            | Self::InvalidChunkSize(_)
+            | Self::InvalidPayload(_, _) => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl From<BlobError> for io::Error {
     fn from(err: BlobError) -> Self {
         match err {
-            // Map your custom error to an appropriate io::Error kind
-            BlobError::InvalidChunkIndex(_, _) => {
-                Self::new(io::ErrorKind::InvalidInput, err.to_string())
-            }
-            BlobError::OutOfBounds => Self::new(io::ErrorKind::UnexpectedEof, err.to_string()),
-            BlobError::RedundantSameContents(_) => {
-                Self::new(io::ErrorKind::AlreadyExists, err.to_string())
-            }
-            BlobError::RedundantContentDiffers(_) | BlobError::UnexpectedChunkSize(_, _, _) => {
-                Self::new(io::ErrorKind::InvalidData, err.to_string())
-            }
+            BlobError::Io(io_err) => io_err,
+            _ => Self::new(err.io_error_kind(), err.to_string()),
+        }
+    }
+}
+
+/// The errors of the receiving front, [`crate::in_logic_front::FrontLogic`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum FrontError {
+    /// A command referred to a transfer that has not been started.
+    TransferNotFound(u16),
+    /// A `StartTransfer` declared a blob that exceeds the
+    /// [`crate::in_logic_front::TransferLimits`] of the front.
+    TransferTooLarge {
+        transfer_id: u16,
+        octet_count: usize,
+        chunk_count: usize,
+    },
+    /// A `StartTransfer` for a transfer in progress declared other parameters than the
+    /// transfer was started with.
+    StartMismatch {
+        transfer_id: u16,
+        octet_count: usize,
+        chunk_size: usize,
+        started_octet_count: usize,
+        started_chunk_size: usize,
+    },
+    /// A `StartTransfer` declared a chunk size above [`MAX_PAYLOAD_OCTET_SIZE`], so its
+    /// chunks could not be received.
+    ChunkSizeTooLarge { transfer_id: u16, chunk_size: usize },
+    /// A hash has a different length than the digests of the hasher of the front.
+    DigestSizeMismatch {
+        octet_count: usize,
+        digest_octet_size: usize,
+    },
+    /// A command for a transfer was received from another peer than the one that started it.
+    PeerMismatch { transfer_id: u16, peer_id: PeerId },
+    /// A `StartTransfer` for a new transfer was rejected, since the front already receives the
+    /// [`crate::in_logic_front::TransferLimits::max_transfers`].
+    TooManyTransfers {
+        transfer_id: u16,
+        max_transfers: usize,
+    },
+    /// A command was invalid or could not be applied.
+    Protocol(io::Error),
+    /// The blob of a transfer rejected a command.
+    Blob(BlobError),
+}
+
+#[cfg(feature = "std")]
+impl FrontError {
+    /// Returns the kind of the `io::Error` that this error is converted to, e.g. to handle it
+    /// like any other I/O error.
+    #[must_use]
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::TransferNotFound(_)
+            | Self::TransferTooLarge { .. }
+            | Self::StartMismatch { .. }
+            | Self::ChunkSizeTooLarge { .. }
+            | Self::DigestSizeMismatch { .. }
+            | Self::TooManyTransfers { .. } => io::ErrorKind::InvalidData,
+            Self::PeerMismatch { .. } => io::ErrorKind::PermissionDenied,
+            Self::Protocol(err) => err.kind(),
+            Self::Blob(err) => err.io_error_kind(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for FrontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TransferNotFound(transfer_id) => write!(f, "Unknown transfer_id {transfer_id}"),
+            Self::TransferTooLarge {
+                transfer_id,
+                octet_count,
+                chunk_count,
+            } => write!(
+                f,
+                "StartTransfer for transfer_id {transfer_id} declares {octet_count} octets in {chunk_count} chunks, which exceeds the limits"
+            ),
+            Self::StartMismatch {
+                transfer_id,
+                octet_count,
+                chunk_size,
+                started_octet_count,
+                started_chunk_size,
+            } => write!(
+                f,
+                "StartTransfer for transfer_id {transfer_id} declares {octet_count} octets in chunks of {chunk_size}, but the transfer was started with {started_octet_count} octets in chunks of {started_chunk_size}"
+            ),
+            Self::ChunkSizeTooLarge {
+                transfer_id,
+                chunk_size,
+            } => write!(
+                f,
+                "StartTransfer for transfer_id {transfer_id} declares chunks of {chunk_size} octets, which exceeds the maximum payload of {MAX_PAYLOAD_OCTET_SIZE} octets"
+            ),
+            Self::DigestSizeMismatch {
+                octet_count,
+                digest_octet_size,
+            } => write!(
+                f,
+                "hash length {octet_count} does not match the digest size {digest_octet_size} of the hasher"
+            ),
+            Self::PeerMismatch {
+                transfer_id,
+                peer_id,
+            } => write!(
+                f,
+                "transfer_id {transfer_id} does not belong to peer {}",
+                peer_id.0
+            ),
+            Self::TooManyTransfers {
+                transfer_id,
+                max_transfers,
+            } => write!(
+                f,
+                "can not start transfer_id {transfer_id}, already receiving the maximum of {max_transfers} transfers"
+            ),
+            Self::Protocol(err) => write!(f, "{err}"),
+            Self::Blob(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for FrontError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::TransferNotFound(_)
+            | Self::TransferTooLarge { .. }
+            | Self::StartMismatch { .. }
+            | Self::ChunkSizeTooLarge { .. }
+            | Self::DigestSizeMismatch { .. }
+            | Self::PeerMismatch { .. }
+            | Self::TooManyTransfers { .. } => None,
+            Self::Protocol(err) => Some(err),
+            Self::Blob(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for FrontError {
+    fn from(err: io::Error) -> Self {
+        Self::Protocol(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<BlobError> for FrontError {
+    fn from(err: BlobError) -> Self {
+        Self::Blob(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<FrontError> for io::Error {
+    fn from(err: FrontError) -> Self {
+        match err {
+            FrontError::Protocol(io_err) => io_err,
+            FrontError::Blob(blob_err) => blob_err.into(),
+            _ => Self::new(err.kind(), err.to_string()),
         }
     }
 }
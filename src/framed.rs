@@ -0,0 +1,105 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! Reads and writes commands over an async byte stream, e.g. a TCP socket with tokio.
+//!
+//! Each command is sent as a frame: the octet size of the command as a big-endian `u32`,
+//! followed by the command as written by `to_stream`. The length prefix lets the reader wait
+//! until a whole command has arrived, however the stream splits it up.
+use crate::protocol_front::{
+    ReceiverToSenderFrontCommands, SenderToReceiverFrontCommands, MAX_SET_CHUNKS_OCTET_SIZE,
+};
+use std::io;
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The maximum octet size of a frame read with the `read_*` functions.
+///
+/// It leaves room for the largest valid command, and a peer that declares a larger frame is
+/// rejected before allocating for it.
+pub const MAX_FRAME_OCTET_SIZE: usize = MAX_SET_CHUNKS_OCTET_SIZE + 64 * 1024;
+
+/// Writes `command` as a length-prefixed frame and flushes `writer`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`SenderToReceiverFrontCommands::to_stream`], or an `io::Error`
+/// if writing to `writer` fails.
+pub async fn write_sender_command<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    command: &SenderToReceiverFrontCommands,
+) -> io::Result<()> {
+    write_frame(writer, &command.to_bytes()?).await
+}
+
+/// Reads a command written by [`write_sender_command`], waiting until the whole frame has
+/// arrived.
+///
+/// # Errors
+///
+/// Returns an `io::Error` with `ErrorKind::UnexpectedEof` if `reader` ends before the frame is
+/// complete, or with `ErrorKind::InvalidData` if the frame is larger than
+/// [`MAX_FRAME_OCTET_SIZE`]. Also returns the same errors as
+/// [`SenderToReceiverFrontCommands::from_stream`].
+pub async fn read_sender_command<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> io::Result<SenderToReceiverFrontCommands> {
+    SenderToReceiverFrontCommands::from_bytes(&read_frame(reader).await?)
+}
+
+/// Writes `command` as a length-prefixed frame and flushes `writer`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`ReceiverToSenderFrontCommands::to_stream`], or an `io::Error`
+/// if writing to `writer` fails.
+pub async fn write_receiver_command<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    command: &ReceiverToSenderFrontCommands,
+) -> io::Result<()> {
+    write_frame(writer, &command.to_bytes()?).await
+}
+
+/// Reads a command written by [`write_receiver_command`], waiting until the whole frame has
+/// arrived.
+///
+/// # Errors
+///
+/// Returns an `io::Error` with `ErrorKind::UnexpectedEof` if `reader` ends before the frame is
+/// complete, or with `ErrorKind::InvalidData` if the frame is larger than
+/// [`MAX_FRAME_OCTET_SIZE`]. Also returns the same errors as
+/// [`ReceiverToSenderFrontCommands::from_stream`].
+pub async fn read_receiver_command<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> io::Result<ReceiverToSenderFrontCommands> {
+    ReceiverToSenderFrontCommands::from_bytes(&read_frame(reader).await?)
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, octets: &[u8]) -> io::Result<()> {
+    let octet_size = u32::try_from(octets.len()).map_err(|_| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("a frame of {} octets is too large", octets.len()),
+        )
+    })?;
+    writer.write_u32(octet_size).await?;
+    writer.write_all(octets).await?;
+    writer.flush().await
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let octet_size = reader.read_u32().await? as usize;
+    if octet_size > MAX_FRAME_OCTET_SIZE {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "frame of {octet_size} octets exceeds the maximum of {MAX_FRAME_OCTET_SIZE} octets"
+            ),
+        ));
+    }
+    let mut octets = vec![0; octet_size];
+    reader.read_exact(&mut octets).await?;
+    Ok(octets)
+}
@@ -0,0 +1,104 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+/// Computes a digest of a complete blob, so the receiver can verify that it matches the blob
+/// that the sender has.
+///
+/// Any digest size is supported, from short checksums to long cryptographic hashes.
+pub trait BlobHasher: Debug {
+    /// Returns the number of octets in the digests that [`Self::hash`] produces.
+    fn digest_octet_size(&self) -> usize;
+
+    /// Computes the digest of the complete `blob`.
+    ///
+    /// The returned digest must be [`Self::digest_octet_size`] octets long.
+    fn hash(&self, blob: &[u8]) -> Vec<u8>;
+
+    /// Starts a digest that is computed incrementally, e.g. to verify hash checkpoints without
+    /// hashing the start of the blob again for each of them.
+    ///
+    /// Feeding the octets of a blob to the returned state, in any number of pieces, must result
+    /// in the same digest as [`Self::hash`] for the whole blob.
+    fn start(&self) -> Box<dyn HashState>;
+}
+
+/// A digest that is computed incrementally, see [`BlobHasher::start`].
+pub trait HashState: Debug + Send {
+    /// Feeds the next `octets` of the blob.
+    fn update(&mut self, octets: &[u8]);
+
+    /// Returns the digest of the octets fed so far. More octets can be fed afterwards.
+    fn digest(&self) -> Vec<u8>;
+}
+
+impl<S: HashState + ?Sized> HashState for Box<S> {
+    fn update(&mut self, octets: &[u8]) {
+        (**self).update(octets);
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        (**self).digest()
+    }
+}
+
+/// The SHA-256 state that [`crate::in_stream::BlobStreamIn::blob_hash`] is computed with.
+#[cfg(feature = "sha256")]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Sha256State(sha2::Sha256);
+
+#[cfg(feature = "sha256")]
+impl HashState for Sha256State {
+    fn update(&mut self, octets: &[u8]) {
+        sha2::Digest::update(&mut self.0, octets);
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        sha2::Digest::finalize(self.0.clone()).to_vec()
+    }
+}
+
+/// Hashes the contiguous prefix of a blob as it grows, so that each octet is only fed once,
+/// e.g. to have the digest of the complete blob ready as soon as the last chunk arrives.
+#[cfg(any(feature = "std", feature = "sha256"))]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PrefixHasher<S> {
+    state: S,
+    hashed_octet_count: usize,
+}
+
+#[cfg(any(feature = "std", feature = "sha256"))]
+impl<S: HashState> PrefixHasher<S> {
+    /// Creates a prefix hasher that feeds `state`, which must not have been fed any octets.
+    #[cfg(feature = "std")]
+    pub(crate) const fn new(state: S) -> Self {
+        Self {
+            state,
+            hashed_octet_count: 0,
+        }
+    }
+
+    /// Returns the number of octets that have been fed so far.
+    #[cfg(feature = "std")]
+    pub(crate) const fn hashed_octet_count(&self) -> usize {
+        self.hashed_octet_count
+    }
+
+    /// Feeds the octets of `prefix` that have not been hashed yet. `prefix` must start with
+    /// the octets that have been fed before.
+    pub(crate) fn update(&mut self, prefix: &[u8]) {
+        if let Some(new_octets) = prefix.get(self.hashed_octet_count..) {
+            self.state.update(new_octets);
+            self.hashed_octet_count = prefix.len();
+        }
+    }
+
+    /// Returns the digest of the octets fed so far.
+    pub(crate) fn digest(&self) -> Vec<u8> {
+        self.state.digest()
+    }
+}
@@ -2,9 +2,18 @@
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
+use crate::err::BlobError;
+use crate::hash::{BlobHasher, HashState, PrefixHasher};
 use crate::in_stream::BlobStreamIn;
-use crate::protocol::{AckChunkData, SetChunkData};
-use crate::ChunkIndex;
+use crate::protocol::{
+    AckBytesData, AckChunkCreditData, AckChunkData, AckChunkMaskData, RequestChunksData,
+    SetChunkData, MAX_RECEIVE_MASK_OCTET_SIZE, MAX_REQUESTED_CHUNK_COUNT,
+};
+use crate::received_set;
+use crate::{chunk_index_from_wire, chunk_index_to_wire, ChunkIndex};
+use bit_array_rs::BitArray;
+use core::ops::{AddAssign, Range};
+use flood_rs::{ReadOctetStream, WriteOctetStream};
 use std::io;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -16,14 +25,257 @@ pub struct Info {
     pub waiting_for_chunk_index: ChunkIndex,
 }
 
+/// Counters for the chunks that a receiver has been sent, for monitoring a receiver.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct ReceiveStats {
+    /// The number of chunks that were set.
+    pub accepted_chunk_count: usize,
+    /// The number of payload octets in the chunks that were set.
+    pub accepted_octet_count: usize,
+    /// The number of chunks that had already been received with the same contents.
+    pub duplicate_chunk_count: usize,
+    /// The number of chunks that had already been received with different contents.
+    pub conflicting_chunk_count: usize,
+    /// The number of chunks that were set while an earlier chunk was still missing.
+    pub out_of_order_chunk_count: usize,
+}
+
+impl ReceiveStats {
+    /// Returns stats with all counters at zero, like [`Default::default`], but in `const`
+    /// contexts.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            accepted_chunk_count: 0,
+            accepted_octet_count: 0,
+            duplicate_chunk_count: 0,
+            conflicting_chunk_count: 0,
+            out_of_order_chunk_count: 0,
+        }
+    }
+
+    fn record_accepted(
+        &mut self,
+        chunk_index: ChunkIndex,
+        first_missing: Option<ChunkIndex>,
+        octet_count: usize,
+    ) {
+        self.accepted_chunk_count += 1;
+        self.accepted_octet_count += octet_count;
+        if first_missing.is_some_and(|first_missing| chunk_index > first_missing) {
+            self.out_of_order_chunk_count += 1;
+        }
+    }
+
+    const fn record_rejected(&mut self, err: &BlobError) {
+        match err {
+            BlobError::RedundantSameContents(_) => self.duplicate_chunk_count += 1,
+            BlobError::RedundantContentDiffers(_) => self.conflicting_chunk_count += 1,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl ReceiveStats {
+    /// Returns the `outcome` field of the tracing events, from how the counters changed for a
+    /// single chunk.
+    const fn outcome_since(&self, before: &Self) -> &'static str {
+        if self.accepted_chunk_count > before.accepted_chunk_count {
+            "accepted"
+        } else if self.duplicate_chunk_count > before.duplicate_chunk_count {
+            "duplicate"
+        } else if self.conflicting_chunk_count > before.conflicting_chunk_count {
+            "conflict"
+        } else {
+            "error"
+        }
+    }
+}
+
+/// How a single chunk was handled by [`Logic::update_with_outcome`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReceiveOutcome {
+    /// The chunk was set, but the blob is not complete yet.
+    Accepted,
+    /// The chunk had already been received with the same contents.
+    Duplicate,
+    /// The chunk was set and completed the blob.
+    Completed,
+}
+
+/// Adds the counters of `other`, e.g. to sum up the stats of several transfers.
+impl AddAssign for ReceiveStats {
+    fn add_assign(&mut self, other: Self) {
+        self.accepted_chunk_count += other.accepted_chunk_count;
+        self.accepted_octet_count += other.accepted_octet_count;
+        self.duplicate_chunk_count += other.duplicate_chunk_count;
+        self.conflicting_chunk_count += other.conflicting_chunk_count;
+        self.out_of_order_chunk_count += other.out_of_order_chunk_count;
+    }
+}
+
+/// The state of a partially received blob, e.g. to persist a receiver and resume the transfer
+/// after a restart. Created with [`Logic::snapshot`] and turned back into a receiver with
+/// [`Logic::restore`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ReceiveSnapshot {
+    pub octet_count: usize,
+    pub fixed_chunk_size: usize,
+    /// The indices of the chunks that have been received, in ascending order.
+    pub received_chunks: Vec<ChunkIndex>,
+    /// The full-length blob, with the octets of the received chunks in place.
+    pub blob: Vec<u8>,
+}
+
+impl ReceiveSnapshot {
+    /// Writes the snapshot. The received chunks are written with [`received_set::encode`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// It also returns an error with `ErrorKind::InvalidInput` if the octet count or chunk size
+    /// does not fit in an `u32`, or if the snapshot is inconsistent.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        let octet_count = u32::try_from(self.octet_count)
+            .map_err(|_| invalid_input(format!("octet count {} is too large", self.octet_count)))?;
+        let fixed_chunk_size = u32::try_from(self.fixed_chunk_size).map_err(|_| {
+            invalid_input(format!("chunk size {} is too large", self.fixed_chunk_size))
+        })?;
+        let received = self
+            .received_bit_array()
+            .map_err(|err| invalid_input(err.to_string()))?;
+        let received = received_set::encode(&received);
+        let received_octet_count = u32::try_from(received.len())
+            .map_err(|_| invalid_input("received set is too large".to_string()))?;
+
+        stream.write_u32(octet_count)?;
+        stream.write_u32(fixed_chunk_size)?;
+        stream.write_u32(received_octet_count)?;
+        stream.write(&received)?;
+        stream.write(&self.blob)
+    }
+
+    /// Reads a snapshot that was written with [`Self::to_stream`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with reading from the stream.
+    /// It also returns an error with `ErrorKind::InvalidData` if the chunk size is zero, the
+    /// received chunks do not match the octet count, or the received chunks are longer than
+    /// their packed encoding. The octets are read in pieces, so a corrupt length fails when the
+    /// stream ends instead of allocating the whole length up front.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        let octet_count = stream.read_u32()? as usize;
+        let fixed_chunk_size = stream.read_u32()? as usize;
+        if fixed_chunk_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "snapshot chunk size must be greater than zero",
+            ));
+        }
+        let chunk_count = octet_count.div_ceil(fixed_chunk_size);
+        let received_octet_count = stream.read_u32()? as usize;
+        // `received_set::encode` never uses more octets than the packed encoding.
+        let max_received_octet_count = 1 + chunk_count.div_ceil(8);
+        if received_octet_count > max_received_octet_count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("snapshot received set of {received_octet_count} octets is longer than the maximum {max_received_octet_count} for {chunk_count} chunks"),
+            ));
+        }
+        let received = read_octets(stream, received_octet_count)?;
+        let blob = read_octets(stream, octet_count)?;
+        // Decoded after the blob has been read, since it allocates a bit for every chunk.
+        let received = received_set::decode(&received, chunk_count)?;
+
+        Ok(Self {
+            octet_count,
+            fixed_chunk_size,
+            received_chunks: (0..received.bit_count())
+                .filter(|&chunk_index| received.get(chunk_index))
+                .collect(),
+            blob,
+        })
+    }
+
+    /// Checks that the snapshot is consistent and returns one bit per chunk, set if the chunk
+    /// has been received.
+    fn received_bit_array(&self) -> Result<BitArray, BlobError> {
+        if self.fixed_chunk_size == 0 {
+            return Err(BlobError::InvalidChunkSize(0));
+        }
+        if self.blob.len() != self.octet_count {
+            return Err(BlobError::InconsistentOctetCount(
+                self.octet_count,
+                self.blob.len(),
+            ));
+        }
+        let chunk_count = self.octet_count.div_ceil(self.fixed_chunk_size);
+        let mut received = BitArray::new(chunk_count);
+        for &chunk_index in &self.received_chunks {
+            if chunk_index >= chunk_count {
+                return Err(BlobError::InvalidChunkIndex(chunk_index, chunk_count));
+            }
+            received.set(chunk_index);
+        }
+        Ok(received)
+    }
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// The most octets that [`ReceiveSnapshot::from_stream`] allocates before reading them.
+const SNAPSHOT_READ_OCTET_SIZE: usize = 64 * 1024;
+
+/// Reads `octet_count` octets, allocating at most [`SNAPSHOT_READ_OCTET_SIZE`] octets ahead of
+/// the octets that have been read.
+fn read_octets(stream: &mut dyn ReadOctetStream, octet_count: usize) -> io::Result<Vec<u8>> {
+    let mut octets = Vec::new();
+    while octets.len() < octet_count {
+        let start = octets.len();
+        octets.resize(start + SNAPSHOT_READ_OCTET_SIZE.min(octet_count - start), 0);
+        stream.read(&mut octets[start..])?;
+    }
+    Ok(octets)
+}
+
 /// `Logic` handles the logic for receiving and processing chunks of data
 /// in a streaming context. It manages the internal state and interactions
 /// between the sender and receiver commands.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Logic {
     in_stream: BlobStreamIn,
+    receive_window: Option<usize>,
+    stats: ReceiveStats,
+    checkpoint_hasher: CheckpointHasher,
+}
+
+/// The running hash of the prefix that the last hash checkpoint was verified for, see
+/// [`Logic::verify_checkpoint`].
+#[derive(Debug, Default)]
+struct CheckpointHasher(Option<PrefixHasher<Box<dyn HashState>>>);
+
+/// A hash state can not be cloned, so a clone hashes the prefix again for its next checkpoint.
+impl Clone for CheckpointHasher {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
 }
 
+/// Two `Logic` are equal if they have the same stream and receive window. The
+/// [`Logic::stats`] are not compared, so that two receivers that converged on the same chunks
+/// in a different order are still equal, and neither is the running hash of the checkpoints.
+impl PartialEq for Logic {
+    fn eq(&self, other: &Self) -> bool {
+        self.in_stream == other.in_stream && self.receive_window == other.receive_window
+    }
+}
+
+impl Eq for Logic {}
+
 impl Logic {
     /// Creates a new `Logic` instance with the specified `octet_count` and `chunk_size`.
     ///
@@ -46,9 +298,98 @@ impl Logic {
     pub fn new(octet_count: usize, chunk_size: usize) -> Self {
         Self {
             in_stream: BlobStreamIn::new(octet_count, chunk_size),
+            receive_window: None,
+            stats: ReceiveStats::default(),
+            checkpoint_hasher: CheckpointHasher::default(),
+        }
+    }
+
+    /// Creates a new `Logic` instance like [`Self::new`], but returns an error instead of
+    /// panicking if `chunk_size` is zero, e.g. when the size comes from a `StartTransfer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::InvalidChunkSize` if `chunk_size` is zero.
+    pub fn try_new(octet_count: usize, chunk_size: usize) -> Result<Self, BlobError> {
+        Ok(Self {
+            in_stream: BlobStreamIn::try_new(octet_count, chunk_size)?,
+            receive_window: None,
+            stats: ReceiveStats::default(),
+            checkpoint_hasher: CheckpointHasher::default(),
+        })
+    }
+
+    /// Creates a receiver from a [`ReceiveSnapshot`], e.g. to resume a transfer after a restart.
+    ///
+    /// The received chunks are treated as already received, so setting them again is rejected
+    /// as redundant, and as a conflict if the contents differ. The [`Self::stats`] start over.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::InvalidChunkSize` if the chunk size is zero,
+    /// `BlobError::InconsistentOctetCount` if the blob length differs from the octet count, or
+    /// `BlobError::InvalidChunkIndex` if a received chunk is out of range.
+    pub fn restore(snapshot: ReceiveSnapshot) -> Result<Self, BlobError> {
+        snapshot.received_bit_array()?;
+        Ok(Self {
+            in_stream: BlobStreamIn::from_existing(
+                snapshot.blob,
+                snapshot.fixed_chunk_size,
+                &snapshot.received_chunks,
+            )?,
+            receive_window: None,
+            stats: ReceiveStats::default(),
+            checkpoint_hasher: CheckpointHasher::default(),
+        })
+    }
+
+    /// Limits how far ahead of the first missing chunk that chunks are accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `receive_window` - The number of chunks, starting with the first missing chunk,
+    ///   that are accepted.
+    ///
+    /// # Returns
+    ///
+    /// The `Logic` with the window applied.
+    #[must_use]
+    pub const fn with_receive_window(mut self, receive_window: usize) -> Self {
+        self.receive_window = Some(receive_window);
+        self
+    }
+
+    /// Captures the received chunks and octets, to be restored with [`Self::restore`].
+    #[must_use]
+    pub fn snapshot(&self) -> ReceiveSnapshot {
+        ReceiveSnapshot {
+            octet_count: self.in_stream.octet_count,
+            fixed_chunk_size: self.in_stream.fixed_chunk_size,
+            received_chunks: (0..self.in_stream.chunk_count())
+                .filter(|&chunk_index| self.in_stream.bit_array.get(chunk_index))
+                .collect(),
+            blob: self.in_stream.storage().clone(),
         }
     }
 
+    /// Returns the range of chunk indices that are currently accepted.
+    ///
+    /// The range starts at the first missing chunk and spans the receive window, or the rest
+    /// of the blob if no window is set. Chunks before the range have already been received.
+    #[must_use]
+    pub fn acceptance_window(&self) -> Range<ChunkIndex> {
+        let chunk_count = self.in_stream.chunk_count();
+        let frontier = self
+            .in_stream
+            .bit_array
+            .first_unset_bit()
+            .unwrap_or(chunk_count);
+        let end = self
+            .receive_window
+            .map_or(chunk_count, |window| (frontier + window).min(chunk_count));
+        frontier..end
+    }
+
     #[must_use]
     pub fn info(&self) -> Info {
         Info {
@@ -75,40 +416,467 @@ impl Logic {
     ///
     /// # Errors
     ///
-    /// Returns an [`io::Error`] if the chunk cannot be set due to an I/O error.
+    /// Returns `BlobError::InvalidPayload` if the payload can not be decoded,
+    /// `BlobError::OutOfWindow` if the chunk is past the [`Self::acceptance_window`], or the
+    /// errors from setting the chunk, e.g. `BlobError::RedundantSameContents` for a chunk that
+    /// has already been received.
     ///
     /// # Example
     ///
     /// ```
     /// use blob_stream::in_logic::Logic;
-    /// use blob_stream::protocol::{SetChunkData};
+    /// use blob_stream::codec::ChunkCodec;
+    /// use blob_stream::protocol::SetChunkData;
     ///
     /// let mut in_logic = Logic::new(1024, 5);
     /// let chunk_data = SetChunkData {
     ///   chunk_index: 1,
+    ///   codec: ChunkCodec::Stored,
     ///   payload: [0x8f, 0x23, 0x98, 0xfa, 0x99].into(),
     /// };
     /// in_logic.update(&chunk_data).unwrap();
     /// ```
-    #[allow(clippy::cast_possible_truncation)]
-    pub fn update(&mut self, chunk_data: &SetChunkData) -> io::Result<AckChunkData> {
-        self.in_stream
-            .set_chunk(chunk_data.chunk_index as ChunkIndex, &chunk_data.payload)?;
+    pub fn update(&mut self, chunk_data: &SetChunkData) -> Result<AckChunkData, BlobError> {
+        #[cfg(feature = "tracing")]
+        let stats_before = self.stats;
+        let result = self.apply_chunk(chunk_data);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            name: "receive_chunk",
+            chunk_index = chunk_data.chunk_index,
+            octet_count = chunk_data.payload.len(),
+            outcome = self.stats.outcome_since(&stats_before),
+        );
+        result
+    }
 
-        let waiting_for_chunk_index = self
-            .in_stream
-            .bit_array
-            .first_unset_bit()
-            .unwrap_or_else(|| self.in_stream.bit_array.bit_count());
+    /// Same as [`Self::update`], but also tells how the chunk was handled, so the caller
+    /// learns about completion without polling [`Self::is_complete`] after every chunk.
+    ///
+    /// A chunk that has already been received with the same contents is not an error here,
+    /// it is reported as [`ReceiveOutcome::Duplicate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::update`], except for duplicate chunks.
+    pub fn update_with_outcome(
+        &mut self,
+        chunk_data: &SetChunkData,
+    ) -> Result<(AckChunkData, ReceiveOutcome), BlobError> {
+        let was_complete = self.is_complete();
+        let duplicate_chunk_count = self.stats.duplicate_chunk_count;
+        match self.update(chunk_data) {
+            Ok(ack) if !was_complete && self.is_complete() => Ok((ack, ReceiveOutcome::Completed)),
+            Ok(ack) => Ok((ack, ReceiveOutcome::Accepted)),
+            Err(_) if self.stats.duplicate_chunk_count > duplicate_chunk_count => {
+                Ok((self.ack(), ReceiveOutcome::Duplicate))
+            }
+            Err(err) => Err(err),
+        }
+    }
 
-        let receive_mask = self
-            .in_stream
-            .bit_array
-            .atom_from_index(waiting_for_chunk_index + 1);
-        Ok(AckChunkData {
-            waiting_for_chunk_index: waiting_for_chunk_index as u32,
+    /// Returns the number of octets that the chunk at `chunk_index` may decode to, which is
+    /// zero for a chunk index past the end of the blob.
+    fn max_chunk_octet_size(&self, chunk_index: ChunkIndex) -> usize {
+        if chunk_index < self.in_stream.chunk_count() {
+            self.in_stream.chunk_size(chunk_index)
+        } else {
+            0
+        }
+    }
+
+    fn apply_chunk(&mut self, chunk_data: &SetChunkData) -> Result<AckChunkData, BlobError> {
+        let chunk_index = chunk_index_from_wire(chunk_data.chunk_index);
+        let window = self.acceptance_window();
+        if chunk_index >= window.end && chunk_index < self.in_stream.chunk_count() {
+            return Err(BlobError::OutOfWindow(chunk_index, window));
+        }
+
+        let payload = chunk_data
+            .decoded_payload(self.max_chunk_octet_size(chunk_index))
+            .map_err(|err| BlobError::InvalidPayload(chunk_index, err))?;
+        let first_missing = self.in_stream.first_missing();
+        if let Err(err) = self.in_stream.set_chunk(chunk_index, &payload) {
+            self.stats.record_rejected(&err);
+            return Err(err);
+        }
+        self.stats
+            .record_accepted(chunk_index, first_missing, payload.len());
+
+        debug_assert!(
+            self.assert_complete_consistency().is_ok(),
+            "the received chunks should add up to the blob"
+        );
+
+        Ok(self.ack())
+    }
+
+    /// Applies several chunks at once, e.g. from a `SetChunks` command. Either all chunks are
+    /// set, or none of them.
+    ///
+    /// All chunks are checked against the [`Self::acceptance_window`] as it is before the batch,
+    /// so a batch can not rely on its own chunks to advance the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - The chunks to set, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::update`] for the first chunk that can not be set,
+    /// including a chunk index that appears twice in the batch. Nothing is set in that case.
+    pub fn update_batch(&mut self, chunks: &[SetChunkData]) -> Result<AckChunkData, BlobError> {
+        let window = self.acceptance_window();
+        let chunk_count = self.in_stream.chunk_count();
+        let mut payloads = Vec::with_capacity(chunks.len());
+        for (position, chunk_data) in chunks.iter().enumerate() {
+            let chunk_index = chunk_index_from_wire(chunk_data.chunk_index);
+            if chunk_index >= window.end && chunk_index < chunk_count {
+                return Err(BlobError::OutOfWindow(chunk_index, window));
+            }
+            let payload = chunk_data
+                .decoded_payload(self.max_chunk_octet_size(chunk_index))
+                .map_err(|err| BlobError::InvalidPayload(chunk_index, err))?;
+            let checked = self
+                .in_stream
+                .check_chunk(chunk_index, &payload)
+                .and_then(|()| {
+                    chunks[..position]
+                        .iter()
+                        .zip(&payloads)
+                        .find(|(earlier, _)| earlier.chunk_index == chunk_data.chunk_index)
+                        .map_or(Ok(()), |(_, earlier_payload)| {
+                            Err(if *earlier_payload == payload {
+                                BlobError::RedundantSameContents(chunk_index)
+                            } else {
+                                BlobError::RedundantContentDiffers(chunk_index)
+                            })
+                        })
+                });
+            if let Err(err) = checked {
+                self.stats.record_rejected(&err);
+                return Err(err);
+            }
+            payloads.push(payload);
+        }
+
+        for (chunk_data, payload) in chunks.iter().zip(&payloads) {
+            let chunk_index = chunk_index_from_wire(chunk_data.chunk_index);
+            let first_missing = self.in_stream.first_missing();
+            self.in_stream.set_chunk(chunk_index, payload)?;
+            self.stats
+                .record_accepted(chunk_index, first_missing, payload.len());
+        }
+
+        debug_assert!(
+            self.assert_complete_consistency().is_ok(),
+            "the received chunks should add up to the blob"
+        );
+
+        Ok(self.ack())
+    }
+
+    /// Returns the received chunks, one bit per chunk, e.g. for custom retransmission logic.
+    ///
+    /// It is the same state that [`Self::ack`] and [`Self::ack_mask`] report to the sender.
+    #[must_use]
+    pub const fn receive_bitmask(&self) -> &BitArray {
+        &self.in_stream.bit_array
+    }
+
+    /// Returns the index of the first chunk that has not been received, or the chunk count if
+    /// the blob is complete. This is the `waiting_for_chunk_index` of [`Self::ack`].
+    #[must_use]
+    pub fn waiting_for_chunk_index(&self) -> u64 {
+        chunk_index_to_wire(
+            self.in_stream
+                .bit_array
+                .first_unset_bit()
+                .unwrap_or_else(|| self.in_stream.bit_array.bit_count()),
+        )
+    }
+
+    /// Creates an acknowledgement of the chunks that have been received so far.
+    #[must_use]
+    pub fn ack(&self) -> AckChunkData {
+        AckChunkData::from_bit_array(&self.in_stream.bit_array)
+    }
+
+    /// Creates an acknowledgement of the chunks that have been received so far, with a receive
+    /// mask that covers all chunks up to the last received one.
+    ///
+    /// Unlike [`Self::ack`], chunks that are more than 64 chunks after the first missing chunk
+    /// are also reported, up to the [`MAX_RECEIVE_MASK_OCTET_SIZE`] of the mask. Chunks that
+    /// are even further ahead are reported as not received.
+    #[must_use]
+    pub fn ack_mask(&self) -> AckChunkMaskData {
+        let bit_array = &self.in_stream.bit_array;
+        let chunk_count = bit_array.bit_count();
+        let waiting_for_chunk_index = bit_array.first_unset_bit().unwrap_or(chunk_count);
+        let mask_end = chunk_count.min(
+            waiting_for_chunk_index
+                .saturating_add(1)
+                .saturating_add(MAX_RECEIVE_MASK_OCTET_SIZE * 8),
+        );
+
+        let mut receive_mask = Vec::new();
+        for chunk_index in (waiting_for_chunk_index + 1)..mask_end {
+            if bit_array.get(chunk_index) {
+                let bit_index = chunk_index - waiting_for_chunk_index - 1;
+                receive_mask.resize(bit_index / 8 + 1, 0);
+                receive_mask[bit_index / 8] |= 1 << (bit_index % 8);
+            }
+        }
+
+        AckChunkMaskData {
+            waiting_for_chunk_index: chunk_index_to_wire(waiting_for_chunk_index),
             receive_mask_after_last: receive_mask,
-        })
+        }
+    }
+
+    /// Checks if a chunk has been received that is more than 64 chunks after the first missing
+    /// chunk. [`Self::ack`] can not report such a chunk, but [`Self::ack_mask`] can.
+    #[must_use]
+    pub fn needs_ack_mask(&self) -> bool {
+        let bit_array = &self.in_stream.bit_array;
+        let chunk_count = bit_array.bit_count();
+        let waiting_for_chunk_index = bit_array.first_unset_bit().unwrap_or(chunk_count);
+        let ack_end = chunk_count.min(waiting_for_chunk_index + 1 + u64::BITS as usize);
+        let acked_count = waiting_for_chunk_index
+            + ((waiting_for_chunk_index + 1)..ack_end)
+                .filter(|&chunk_index| bit_array.get(chunk_index))
+                .count();
+        self.in_stream.received_chunk_count() > acked_count
+    }
+
+    /// Creates a request for the chunks that are missing before the last received chunk, i.e.
+    /// the gaps that were most likely lost on the way, so the sender can send them again
+    /// without waiting for the resend interval.
+    ///
+    /// Chunks after the last received chunk are not requested, since the sender might not have
+    /// sent them yet.
+    ///
+    /// # Returns
+    ///
+    /// The first [`MAX_REQUESTED_CHUNK_COUNT`] missing chunks, in ascending order. It is empty
+    /// if there are no gaps.
+    #[must_use]
+    pub fn request_chunks(&self) -> RequestChunksData {
+        let bit_array = &self.in_stream.bit_array;
+        let last_received = (0..bit_array.bit_count())
+            .rev()
+            .find(|&chunk_index| bit_array.get(chunk_index))
+            .unwrap_or(0);
+        RequestChunksData {
+            chunk_indices: self
+                .in_stream
+                .missing_chunks()
+                .take_while(|&chunk_index| chunk_index < last_received)
+                .take(MAX_REQUESTED_CHUNK_COUNT)
+                .map(chunk_index_to_wire)
+                .collect(),
+        }
+    }
+
+    /// Encodes the complete set of received chunks, e.g. to let the sender skip them when a
+    /// transfer is resumed. Unlike [`Self::ack`] and [`Self::ack_mask`], the chunks before the
+    /// first missing chunk are included as well.
+    ///
+    /// # Returns
+    ///
+    /// The set encoded with [`received_set::encode`], which the sender applies with
+    /// [`crate::out_logic::Logic::apply_full_received`].
+    #[must_use]
+    pub fn full_received_ack(&self) -> Vec<u8> {
+        received_set::encode(&self.in_stream.bit_array)
+    }
+
+    /// Returns the number of chunks in the [`Self::acceptance_window`] that have not been
+    /// received yet, i.e. how many more chunks the sender may send right now.
+    ///
+    /// The credit saturates at `u32::MAX`.
+    #[must_use]
+    pub fn credit(&self) -> u32 {
+        let window = self.acceptance_window();
+        let missing_count = if window.end == self.in_stream.chunk_count() {
+            // All chunks before the window have been received, so all missing chunks are in it.
+            window.end - self.in_stream.received_chunk_count()
+        } else {
+            window
+                .filter(|&chunk_index| !self.in_stream.bit_array.get(chunk_index))
+                .count()
+        };
+        u32::try_from(missing_count).unwrap_or(u32::MAX)
+    }
+
+    /// Creates an acknowledgement of the chunks that have been received so far, combined with
+    /// the [`Self::credit`].
+    #[must_use]
+    pub fn ack_with_credit(&self) -> AckChunkCreditData {
+        AckChunkCreditData {
+            ack: self.ack(),
+            credit: self.credit(),
+        }
+    }
+
+    /// Creates an acknowledgement that reports progress in octets instead of chunks.
+    ///
+    /// `received_up_to_byte` is the length of the prefix of the blob that has been received
+    /// without any gaps, which is useful for peers that track progress in octets.
+    #[must_use]
+    pub fn ack_bytes(&self) -> AckBytesData {
+        AckBytesData {
+            received_up_to_byte: self.in_stream.contiguous_octet_count() as u64,
+        }
+    }
+
+    /// Returns the counters of the chunks that have been received, including the rejected ones.
+    #[must_use]
+    pub const fn stats(&self) -> &ReceiveStats {
+        &self.stats
+    }
+
+    /// Returns the number of octets that were wasted on receiving chunks that had already
+    /// been received.
+    #[must_use]
+    pub const fn wasted_bytes(&self) -> usize {
+        self.in_stream.wasted_bytes()
+    }
+
+    /// Returns the size of each chunk, as given to [`Self::new`]. The last chunk can be smaller.
+    #[must_use]
+    pub const fn chunk_size(&self) -> usize {
+        self.in_stream.fixed_chunk_size
+    }
+
+    /// Returns the total number of octets in the blob.
+    #[must_use]
+    pub const fn octet_count(&self) -> usize {
+        self.in_stream.octet_count
+    }
+
+    /// Returns the total number of chunks in the blob.
+    #[must_use]
+    pub const fn chunk_count(&self) -> usize {
+        self.in_stream.chunk_count()
+    }
+
+    /// Verifies that the complete blob adds up to the declared total octet size.
+    ///
+    /// It is called automatically in debug builds when a chunk is received.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::InconsistentOctetCount` if the sizes of the received chunks do not
+    /// add up to the total octet size. An incomplete blob is not checked.
+    pub fn assert_complete_consistency(&self) -> Result<(), BlobError> {
+        self.in_stream.assert_complete_consistency()
+    }
+
+    /// Shrinks the blob to `octet_count` octets, keeping the chunks that have been received,
+    /// e.g. when the sender restarts the transfer with a smaller blob.
+    ///
+    /// # Arguments
+    ///
+    /// * `octet_count` - The new total number of octets.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors from [`BlobStreamIn::truncate`], if a received chunk does not fit the
+    /// new size.
+    pub fn truncate(&mut self, octet_count: usize) -> Result<(), BlobError> {
+        self.in_stream.truncate(octet_count)
+    }
+
+    /// Verifies that the hash of the complete blob matches `expected_hash`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hasher` - The hasher that computes the hash of the blob.
+    /// * `expected_hash` - The hash that the sender computed for the blob.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::HashMismatch` if the hash from `hasher` differs from `expected_hash`.
+    /// An incomplete blob is not checked.
+    pub fn verify_hash(
+        &self,
+        hasher: &dyn BlobHasher,
+        expected_hash: &[u8],
+    ) -> Result<(), BlobError> {
+        match self.blob() {
+            Some(blob) if hasher.hash(blob) != expected_hash => Err(BlobError::HashMismatch),
+            _ => Ok(()),
+        }
+    }
+
+    /// Verifies that the first `up_to_chunk` chunks match a hash checkpoint from the sender.
+    ///
+    /// A checkpoint can only be verified once all chunks before `up_to_chunk` have been
+    /// received. If an earlier checkpoint has been verified, a mismatch means that the
+    /// corruption lies in the chunks between the two checkpoints.
+    ///
+    /// The hash of the prefix is kept from one checkpoint to the next, so only the chunks after
+    /// the previous checkpoint are hashed, as long as the checkpoints are verified in ascending
+    /// order.
+    ///
+    /// # Arguments
+    ///
+    /// * `hasher` - The hasher that computes the hash of the chunks. It must be the same for
+    ///   all checkpoints of the blob.
+    /// * `up_to_chunk` - The number of chunks, from the start of the blob, that the hash covers.
+    /// * `expected_hash` - The hash that the sender computed for the chunks.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the hash matches, or `false` if some of the chunks have not been received yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::InvalidChunkIndex` if `up_to_chunk` is larger than the chunk count,
+    /// or `BlobError::CheckpointMismatch` if the hash differs from `expected_hash`.
+    pub fn verify_checkpoint(
+        &mut self,
+        hasher: &dyn BlobHasher,
+        up_to_chunk: usize,
+        expected_hash: &[u8],
+    ) -> Result<bool, BlobError> {
+        let chunk_count = self.in_stream.chunk_count();
+        if up_to_chunk > chunk_count {
+            return Err(BlobError::InvalidChunkIndex(up_to_chunk, chunk_count));
+        }
+        if self
+            .in_stream
+            .first_missing()
+            .is_some_and(|first_missing| first_missing < up_to_chunk)
+        {
+            return Ok(false);
+        }
+
+        let octet_count =
+            (up_to_chunk * self.in_stream.fixed_chunk_size).min(self.in_stream.octet_count);
+        let prefix_hasher = match &mut self.checkpoint_hasher.0 {
+            Some(prefix_hasher) if prefix_hasher.hashed_octet_count() <= octet_count => {
+                prefix_hasher
+            }
+            // An earlier checkpoint covers more octets, so the hash has to start over.
+            slot => slot.insert(PrefixHasher::new(hasher.start())),
+        };
+        prefix_hasher.update(&self.in_stream.storage()[..octet_count]);
+        if prefix_hasher.digest() != expected_hash {
+            return Err(BlobError::CheckpointMismatch(up_to_chunk));
+        }
+        Ok(true)
+    }
+
+    /// Checks if all chunks have been received.
+    ///
+    /// # Returns
+    ///
+    /// `true` if all chunks have been received; `false` otherwise.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.in_stream.is_complete()
     }
 
     /// Retrieves the full blob data if all chunks have been received.
@@ -131,4 +899,62 @@ impl Logic {
     pub fn blob(&self) -> Option<&[u8]> {
         self.in_stream.blob()
     }
+
+    /// Returns the octets of the first chunk, as soon as it has been received, e.g. to parse the
+    /// header of a format before the rest of the blob has arrived.
+    #[must_use]
+    pub fn header(&self) -> Option<&[u8]> {
+        self.in_stream.get_chunk(0).ok().flatten()
+    }
+
+    /// Moves the complete blob out of the logic, without copying it.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the blob if all chunks have been received, or `None` if the blob is incomplete.
+    #[must_use]
+    pub fn take_blob(self) -> Option<Vec<u8>> {
+        self.in_stream.take_blob()
+    }
+}
+
+/// Computes the acknowledgement that a fresh receiver emits after receiving only the chunk
+/// at `received_index`.
+///
+/// This is the same `AckChunkData` that [`Logic::update`] returns for the first chunk of a
+/// transfer, which makes it convenient for writing focused protocol tests.
+///
+/// # Arguments
+///
+/// * `chunk_count` - The total number of chunks in the transfer.
+/// * `received_index` - The index of the only chunk that has been received.
+///
+/// # Panics
+///
+/// Panics if `received_index` is not less than `chunk_count`.
+///
+/// # Returns
+///
+/// The `AckChunkData` that acknowledges the single received chunk.
+#[must_use]
+pub fn ack_after_single(chunk_count: usize, received_index: ChunkIndex) -> AckChunkData {
+    assert!(
+        received_index < chunk_count,
+        "received_index {received_index} must be less than chunk_count {chunk_count}"
+    );
+
+    if received_index == 0 {
+        // The frontier advances past the received chunk, and nothing after it has been received.
+        return AckChunkData {
+            waiting_for_chunk_index: 1,
+            receive_mask_after_last: 0,
+        };
+    }
+
+    // Chunk 0 is still missing, so the mask starts at index 1.
+    let bit_index = received_index - 1;
+    AckChunkData {
+        waiting_for_chunk_index: 0,
+        receive_mask_after_last: if bit_index < 64 { 1 << bit_index } else { 0 },
+    }
 }
@@ -2,14 +2,27 @@
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
-use crate::in_logic::Logic;
-use crate::protocol::TransferId;
+use crate::err::{BlobError, FrontError};
+use crate::hash::BlobHasher;
+use crate::in_logic::{Logic, ReceiveStats};
+use crate::in_stream::check_min_chunk_size;
+use crate::millis::Millis;
+use crate::protocol::{
+    CompleteTransferData, HashCheckpointData, PauseTransferData, ResumeTransferData, SetChunkData,
+    StartTransferData, TransferId, MAX_PAYLOAD_OCTET_SIZE,
+};
 use crate::protocol_front::{
-    AckChunkFrontData, ReceiverToSenderFrontCommands, SenderToReceiverFrontCommands,
+    AckBytesFrontData, AckChunkCreditFrontData, AckChunkFrontData, AckChunkMaskFrontData,
+    ReceiverToSenderFrontCommands, RequestChunksFrontData, SenderToReceiverFrontCommands,
+    SetChunkFrontData,
 };
-use crate::ChunkIndex;
-use std::io;
-use std::io::ErrorKind;
+use crate::{chunk_index_from_wire, ChunkIndex};
+use flood_rs::InOctetStream;
+use log::{debug, warn};
+use std::collections::BTreeMap;
+use std::slice;
+use std::sync::mpsc::SyncSender;
+use std::time::Duration;
 
 pub struct Info {
     pub transfer_id: TransferId,
@@ -19,18 +32,192 @@ pub struct Info {
     pub waiting_for_chunk_index: ChunkIndex,
 }
 
+/// The progress of a single transfer, as returned by [`FrontLogic::transfer_status`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TransferStatus {
+    pub octet_count: usize,
+    pub chunk_size: usize,
+    pub received_chunk_count: usize,
+    pub chunk_count: usize,
+    pub is_complete: bool,
+}
+
+/// Identifies the peer that a transfer is received from, for receivers that serve several peers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PeerId(pub u64);
+
 #[derive(Debug)]
 pub struct State {
     transfer_id: TransferId,
+    peer_id: Option<PeerId>,
     logic: Logic,
+    schedule: Vec<u64>,
+    blob_hash: Option<[u8; 32]>,
+    last_activity: Millis,
+    /// Hash checkpoints that can not be verified until more chunks have been received, as
+    /// `(up_to_chunk, hash)` in ascending order.
+    checkpoints: Vec<(usize, Vec<u8>)>,
+    verified_chunk_count: usize,
+}
+
+/// How a `StartTransfer` is handled when it is received for a transfer that is already complete.
+///
+/// This typically happens when the `Completed` command was lost on the way to the sender.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum CompletedStartPolicy {
+    /// Answer with `Completed` again, so the sender knows that it can stop.
+    #[default]
+    AckComplete,
+    /// Discard the received blob and start the transfer over.
+    Restart,
+}
+
+/// How a `SetChunk` is handled when it is received for a transfer that has not been started.
+///
+/// This typically happens when the `StartTransfer` command was lost on the way to the receiver.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum UnknownTransferPolicy {
+    /// Reject the chunk with an error.
+    #[default]
+    Reject,
+    /// Keep the chunk and answer with `NeedStart`, asking the sender to resend `StartTransfer`.
+    /// The kept chunks are applied when the transfer is started.
+    NeedStart,
+}
+
+/// How a `StartTransfer` is handled when the receiver already receives the maximum number of
+/// transfers, see [`TransferLimits::max_transfers`].
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum TransferCapacityPolicy {
+    /// Reject the new transfer with an error.
+    #[default]
+    Reject,
+    /// Remove the incomplete transfer that has gone the longest without receiving a command,
+    /// to make room for the new one. Completed transfers are never evicted, so the new transfer
+    /// is rejected if all transfers are complete. The evicted ids are returned by
+    /// [`FrontLogic::take_evicted_transfer_ids`].
+    EvictLeastRecentlyActive,
+}
+
+/// Upper bounds on what a `StartTransfer` may ask the receiver to allocate, for receivers
+/// that accept transfers from untrusted senders.
+///
+/// The default has no limits.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TransferLimits {
+    /// The maximum `total_octet_size` of a single transfer.
+    pub max_octet_size: usize,
+    /// The maximum number of chunks in a single transfer.
+    pub max_chunk_count: usize,
+    /// The maximum number of transfers that are received at the same time.
+    pub max_transfers: usize,
+}
+
+impl TransferLimits {
+    /// No limits, which is the default.
+    pub const UNLIMITED: Self = Self {
+        max_octet_size: usize::MAX,
+        max_chunk_count: usize::MAX,
+        max_transfers: usize::MAX,
+    };
+}
+
+impl Default for TransferLimits {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+/// A callback for the first chunk of a transfer, see [`FrontLogic::with_header_hook`].
+struct HeaderHook(Box<HeaderFn>);
+
+type HeaderFn = dyn FnMut(TransferId, &[u8]) + Send;
+
+impl core::fmt::Debug for HeaderHook {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("HeaderHook")
+    }
+}
+
+/// A callback for completed blobs, see [`FrontLogic::with_completion_hook`].
+struct CompletionHook(Box<CompletionFn>);
+
+type CompletionFn = dyn FnMut(TransferId, Vec<u8>) + Send;
+
+impl core::fmt::Debug for CompletionHook {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("CompletionHook")
+    }
+}
+
+/// Caps the `chunk_size` from a `StartTransfer` at `total_octet_size`, so that a blob that is
+/// smaller than a chunk is a single chunk of exactly its size. A blob of zero octets gets a
+/// chunk size of one, since it has no chunks anyway.
+const fn capped_chunk_size(chunk_size: usize, total_octet_size: usize) -> usize {
+    if chunk_size <= total_octet_size {
+        chunk_size
+    } else if total_octet_size == 0 {
+        1
+    } else {
+        total_octet_size
+    }
+}
+
+/// The maximum number of chunks that are kept for transfers that have not been started yet.
+pub const MAX_PENDING_CHUNK_COUNT: usize = 64;
+
+/// A chunk for a transfer that has not been started yet, together with the peer that sent it.
+#[derive(Debug)]
+struct PendingChunk {
+    peer_id: Option<PeerId>,
+    chunk: SetChunkFrontData,
+}
+
+/// The maximum number of hash checkpoints that are kept per transfer until they can be verified.
+/// The oldest checkpoint is dropped when another one is received.
+pub const MAX_PENDING_CHECKPOINT_COUNT: usize = 16;
+
+/// The maximum number of delivered transfer ids that are remembered.
+///
+/// A repeated `StartTransfer` with the same parameters, or a `CompleteTransfer`, for them is
+/// answered with `Completed`. The oldest id is forgotten when another transfer is delivered.
+pub const MAX_DELIVERED_TRANSFER_COUNT: usize = 16;
+
+/// A transfer whose blob has been handed to the completion hook or channel, with the
+/// parameters that it was started with.
+#[derive(Debug)]
+struct DeliveredTransfer {
+    transfer_id: TransferId,
+    octet_count: usize,
+    chunk_size: usize,
+    blob_hash: Option<[u8; 32]>,
 }
 
 /// `Logic` handles the logic for receiving and processing chunks of data
 /// in a streaming context. It manages the internal state and interactions
 /// between the sender and receiver commands.
+///
+/// Several transfers can be received at the same time. The queries that do not take a
+/// transfer id, like [`Self::info`], are for the current transfer, which is the one that
+/// was started last.
 #[derive(Debug, Default)]
 pub struct FrontLogic {
-    state: Option<State>,
+    transfers: BTreeMap<u16, State>,
+    current_transfer_id: Option<u16>,
+    completed_start_policy: CompletedStartPolicy,
+    completion_sender: Option<SyncSender<(TransferId, Vec<u8>)>>,
+    completion_hook: Option<CompletionHook>,
+    delivered_transfers: Vec<DeliveredTransfer>,
+    unknown_transfer_policy: UnknownTransferPolicy,
+    transfer_capacity_policy: TransferCapacityPolicy,
+    evicted_transfer_ids: Vec<u16>,
+    pending_chunks: Vec<PendingChunk>,
+    hasher: Option<Box<dyn BlobHasher + Send>>,
+    min_chunk_size: usize,
+    limits: TransferLimits,
+    receive_window: Option<usize>,
+    removed_stats: ReceiveStats,
+    header_hook: Option<HeaderHook>,
 }
 
 impl FrontLogic {
@@ -47,37 +234,332 @@ impl FrontLogic {
     ///
     #[must_use]
     pub const fn new() -> Self {
-        Self { state: None }
+        Self {
+            transfers: BTreeMap::new(),
+            current_transfer_id: None,
+            completed_start_policy: CompletedStartPolicy::AckComplete,
+            completion_sender: None,
+            completion_hook: None,
+            delivered_transfers: Vec::new(),
+            unknown_transfer_policy: UnknownTransferPolicy::Reject,
+            transfer_capacity_policy: TransferCapacityPolicy::Reject,
+            evicted_transfer_ids: Vec::new(),
+            pending_chunks: Vec::new(),
+            hasher: None,
+            min_chunk_size: 0,
+            limits: TransferLimits::UNLIMITED,
+            receive_window: None,
+            removed_stats: ReceiveStats::new(),
+            header_hook: None,
+        }
+    }
+
+    /// Sets how a `StartTransfer` for an already completed transfer is handled.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Whether to answer with `Completed` or to restart the transfer.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the policy applied.
+    #[must_use]
+    pub const fn with_completed_start_policy(mut self, policy: CompletedStartPolicy) -> Self {
+        self.completed_start_policy = policy;
+        self
+    }
+
+    /// Sets how a `SetChunk` for a transfer that has not been started is handled.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Whether to reject the chunk or to keep it and ask for `StartTransfer`.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the policy applied.
+    #[must_use]
+    pub const fn with_unknown_transfer_policy(mut self, policy: UnknownTransferPolicy) -> Self {
+        self.unknown_transfer_policy = policy;
+        self
+    }
+
+    /// Sets how a `StartTransfer` is handled when the maximum number of transfers is reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Whether to reject the new transfer or to evict the stalest transfer.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the policy applied.
+    #[must_use]
+    pub const fn with_transfer_capacity_policy(mut self, policy: TransferCapacityPolicy) -> Self {
+        self.transfer_capacity_policy = policy;
+        self
+    }
+
+    /// Rejects a `StartTransfer` with a chunk size below `min_chunk_size`, unless the whole blob
+    /// fits in a single chunk. This bounds the number of chunks, and with it the bookkeeping,
+    /// for a blob of a given size. There is no minimum by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_chunk_size` - The smallest accepted chunk size.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the minimum applied.
+    #[must_use]
+    pub const fn with_min_chunk_size(mut self, min_chunk_size: usize) -> Self {
+        self.min_chunk_size = min_chunk_size;
+        self
+    }
+
+    /// Rejects a `StartTransfer` that exceeds any of the `limits`, before anything is allocated
+    /// for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The maximum size, chunk count and number of concurrent transfers.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the limits applied.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: TransferLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Rejects a `StartTransfer` with more than `max_chunk_count` chunks, before the bookkeeping
+    /// for its chunks is allocated. The other [`TransferLimits`] are kept as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_chunk_count` - The maximum number of chunks in a single transfer.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the limit applied.
+    #[must_use]
+    pub const fn with_max_chunk_count(mut self, max_chunk_count: usize) -> Self {
+        self.limits.max_chunk_count = max_chunk_count;
+        self
+    }
+
+    /// Limits how far ahead of the first missing chunk that the chunks of each transfer are
+    /// accepted, see [`Logic::with_receive_window`].
+    ///
+    /// The chunks are then acknowledged with `AckChunkCredit` instead of `AckChunk`, so the
+    /// sender does not send more chunks than fit in the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `receive_window` - The number of chunks, starting with the first missing chunk,
+    ///   that are accepted.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the window applied.
+    #[must_use]
+    pub const fn with_receive_window(mut self, receive_window: usize) -> Self {
+        self.receive_window = Some(receive_window);
+        self
+    }
+
+    /// Verifies the hash in `CompleteTransfer` commands with `hasher`.
+    ///
+    /// Without a hasher, the hash in `CompleteTransfer` is accepted as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `hasher` - The hasher that the sender used to compute the hash of the blob.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the hasher applied.
+    #[must_use]
+    pub fn with_hasher(mut self, hasher: Box<dyn BlobHasher + Send>) -> Self {
+        self.hasher = Some(hasher);
+        self
+    }
+
+    /// Delivers completed blobs on a channel instead of keeping them in the receiver.
+    ///
+    /// When a transfer completes, the owned blob is sent together with the transfer id on
+    /// `sender`, and the transfer is removed from the receiver. Sending blocks while the
+    /// channel is full, which applies back pressure to the receiving side.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - The sending half of the channel that the completed blobs are sent on.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the completion sender applied.
+    #[must_use]
+    pub fn with_completion_sender(mut self, sender: SyncSender<(TransferId, Vec<u8>)>) -> Self {
+        self.completion_sender = Some(sender);
+        self
+    }
+
+    /// Calls `hook` with each completed blob as soon as its transfer completes, e.g. in an
+    /// event-driven server, instead of keeping the blobs in the receiver.
+    ///
+    /// The owned blob is handed over exactly once, and the transfer is removed from the
+    /// receiver before `hook` is called. The hook takes the place of a completion sender, if
+    /// both are set.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - Called with the transfer id and the completed blob.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the completion hook applied.
+    #[must_use]
+    pub fn with_completion_hook(
+        mut self,
+        hook: impl FnMut(TransferId, Vec<u8>) + Send + 'static,
+    ) -> Self {
+        self.completion_hook = Some(CompletionHook(Box::new(hook)));
+        self
+    }
+
+    /// Calls `hook` with the octets of the first chunk of each transfer as soon as that chunk has
+    /// been received, so that a header can be parsed before the rest of the blob has arrived.
+    ///
+    /// Every accepted chunk is acknowledged in the answer to its `SetChunk`, so the sender
+    /// learns right away that the header has arrived as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - Called with the transfer id and the octets of the first chunk.
+    ///
+    /// # Returns
+    ///
+    /// The `FrontLogic` with the header hook applied.
+    #[must_use]
+    pub fn with_header_hook(
+        mut self,
+        hook: impl FnMut(TransferId, &[u8]) + Send + 'static,
+    ) -> Self {
+        self.header_hook = Some(HeaderHook(Box::new(hook)));
+        self
+    }
+
+    /// Reconstructs a receiver by applying a captured sequence of serialized commands in order.
+    ///
+    /// The commands are applied to this receiver, which should be configured the same way as
+    /// the receiver that the commands were captured from, e.g. with the same limits and
+    /// policies. The errors of rejected commands, e.g. resent chunks, are skipped, but such a
+    /// command has the same effect as when it was captured, e.g. it is counted in the
+    /// [`Self::stats`], or a transfer that fails a hash check is removed. The result then
+    /// matches the final state of the receiver that the commands were captured from.
+    ///
+    /// # Arguments
+    ///
+    /// * `commands` - The times that the serialized `SenderToReceiverFrontCommands` were
+    ///   received, together with the commands, in the order they were received.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FrontError::Protocol` if any of the commands can not be decoded.
+    pub fn replay(mut self, commands: &[(Millis, Vec<u8>)]) -> Result<Self, FrontError> {
+        for (now, octets) in commands {
+            let mut in_stream = InOctetStream::new(octets);
+            let command = SenderToReceiverFrontCommands::from_stream(&mut in_stream)?;
+            if let Err(err) = self.update(*now, &command) {
+                debug!("replay skipped rejected command: {err}");
+            }
+        }
+        Ok(self)
     }
 
     /// Updates the internal state based on a `SenderToReceiverFrontCommands` command.
     ///
-    /// This method processes either a `StartTransfer` or `SetChunk` command sent by the sender.
-    /// If a `StartTransfer` command is received, the current state (including `transfer_id` and
-    /// `logic`) is reinitialized if necessary. If a `SetChunk` command is received, it applies
-    /// the chunk of data to the current logic.
+    /// This method processes a `StartTransfer`, `SetChunk`, `SetChunks`, `AbortTransfer`,
+    /// `CompleteTransfer`, `HashCheckpoint` or `Keepalive` command sent by the sender.
+    /// If a `StartTransfer` command is received, the state of that transfer is initialized if
+    /// necessary, and it becomes the current transfer. A `chunk_size` that is larger than the
+    /// `total_octet_size` is capped at it, so such a blob is received as a single chunk. If a
+    /// `SetChunk` command is received, it applies the chunk of data to the logic of its
+    /// transfer. If an `AbortTransfer` command is received, the transfer is dropped.
+    ///
+    /// Any command for a known transfer counts as activity of that transfer for [`Self::expire`],
+    /// including a `Keepalive` from a sender that has no chunks to send.
+    ///
+    /// The time is a [`Millis`] from a clock that the caller supplies, like for
+    /// [`crate::out_logic::Logic::send`]. Earlier versions did not take the time at all, so this
+    /// is a breaking change for existing callers.
     ///
     /// # Arguments
     ///
+    /// * `now` - The current time, supplied by the caller.
     /// * `command` - A command sent by the sender to either start a new transfer or update
-    ///               an existing one with a chunk of data.
+    ///   an existing one with a chunk of data.
     ///
     /// # Returns
     ///
     /// On success, this method returns a corresponding response:
     /// * If a `StartTransfer` command is processed, it returns `AckStart` with the `transfer_id`.
+    ///   If the transfer is already complete, it returns `Completed` unless the
+    ///   [`CompletedStartPolicy`] is set to restart the transfer.
+    ///   A transfer of zero octets has no chunks, so it is complete right away and it returns
+    ///   `Completed`.
     /// * If a `SetChunk` command is processed successfully, it returns `AckChunk` with information
     ///   on the last chunk received in order as well as a receive-mask for up to 64 chunks
-    ///   after that.
+    ///   after that. If a receive window is set with [`Self::with_receive_window`], this and
+    ///   the other acknowledgements below are `AckChunkCredit` instead. If a chunk has been
+    ///   received more than 64 chunks after the first missing one, they are `AckChunkMask`.
+    /// * If the `SetChunk` command completed the blob, it returns `Completed` with the `transfer_id`
+    ///   instead of `AckChunk`.
+    ///   If a completion sender or hook is set, the blob is handed to it and the transfer is
+    ///   removed. Any later `SetChunk` for the complete or delivered transfer returns
+    ///   `Completed` as well, so the sender stops resending even if a `Completed` was lost.
+    /// * If a `SetChunk` command is received for a transfer that has not been started and the
+    ///   [`UnknownTransferPolicy`] is `NeedStart`, the chunk is kept and it returns `NeedStart`
+    ///   with the `transfer_id`.
+    /// * If an `AbortTransfer` command is processed, the matching transfer is dropped and it
+    ///   returns `AckAbort` with the `transfer_id`.
+    /// * If a `CompleteTransfer` command is processed for a complete blob, it returns `Completed`
+    ///   with the `transfer_id`. If chunks are still missing, it returns `AckChunk` instead, so
+    ///   the sender knows which chunks to send again.
+    /// * If a `Keepalive` command is processed, it returns `AckChunk` with the current state of
+    ///   the transfer, or `Completed` if the blob is complete.
     ///
     /// # Errors
     ///
-    /// This function returns an `io::Error` in the following cases:
-    /// * If a `SetChunk` command is received and the transfer state has not been initialized
-    ///   (i.e., no `StartTransfer` has been processed), it returns an `io::Error` with
-    ///   `ErrorKind::InvalidData` and a message indicating that the `transfer_id` is unknown.
+    /// This function returns a [`FrontError`] in the following cases, where the kinds are
+    /// those of [`FrontError::kind`]:
+    /// * If a `SetChunk`, `HashCheckpoint`, `CompleteTransfer` or `Keepalive` command is
+    ///   received and the transfer state has not been initialized (i.e., no `StartTransfer`
+    ///   has been processed), it returns `FrontError::TransferNotFound` with the `transfer_id`.
+    ///
+    /// * If a `StartTransfer` command is received for a known transfer, but with a different
+    ///   `total_octet_size` or `chunk_size`, it returns `FrontError::StartMismatch`, and the
+    ///   known transfer is kept as is. A completed transfer that is restarted due to the
+    ///   [`CompletedStartPolicy`] may change its parameters.
+    ///
+    /// * If a `StartTransfer` command exceeds the [`TransferLimits`], it returns
+    ///   `FrontError::TransferTooLarge`. A chunk size above
+    ///   [`crate::protocol::MAX_PAYLOAD_OCTET_SIZE`] returns `FrontError::ChunkSizeTooLarge`,
+    ///   and a chunk size below the minimum chunk size is returned as `FrontError::Blob`.
+    ///
+    /// * If a `StartTransfer` command for a new transfer is received while the receiver already
+    ///   receives [`TransferLimits::max_transfers`], and no transfer can be evicted, it returns
+    ///   `FrontError::TooManyTransfers`.
+    ///
+    /// * If a hash is received and a hasher is set, it returns
+    ///   `FrontError::DigestSizeMismatch` if the length of the hash differs from the digest
+    ///   size of the hasher. A `CompleteTransfer` hash that does not match the received blob is
+    ///   returned as `FrontError::Blob` with `BlobError::HashMismatch`, and a `HashCheckpoint`
+    ///   that does not match with `BlobError::CheckpointMismatch`.
     ///
-    /// * Any I/O error encountered during the update of the logic will be propagated.
+    /// * A chunk that can not be set, e.g. since it is outside of the acceptance window or has
+    ///   already been received, is returned as `FrontError::Blob` with the error from
+    ///   [`Logic::update`].
     ///
     /// # Example
     ///
@@ -85,6 +567,7 @@ impl FrontLogic {
     /// use blob_stream::in_logic_front::FrontLogic;
     /// use blob_stream::protocol::StartTransferData;
     /// use blob_stream::protocol_front::SenderToReceiverFrontCommands;
+    /// use blob_stream::millis::Millis;
     ///
     /// let mut logic_front = FrontLogic::new();
     ///
@@ -92,52 +575,842 @@ impl FrontLogic {
     ///     transfer_id: 1234,
     ///     total_octet_size: 1024,
     ///     chunk_size: 256,
+    ///     schedule: vec![],
+    ///     blob_hash: None,
     /// });
     ///
-    /// let response = logic_front.update(&start_command);
+    /// let response = logic_front.update(Millis::new(0), &start_command);
     /// assert!(response.is_ok());
     /// ```
     pub fn update(
         &mut self,
+        now: Millis,
+        command: &SenderToReceiverFrontCommands,
+    ) -> Result<ReceiverToSenderFrontCommands, FrontError> {
+        self.update_from(now, None, command)
+    }
+
+    /// Same as [`Self::update`], but for a command received from `peer_id`.
+    ///
+    /// A transfer started with this method belongs to `peer_id`, so that all transfers of the
+    /// peer can be dropped with [`Self::cancel_peer`], e.g. when it disconnects.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, supplied by the caller.
+    /// * `peer_id` - The peer that sent the command.
+    /// * `command` - The command sent by the peer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::update`]. It also returns `FrontError::PeerMismatch`
+    /// if the transfer belongs to another peer.
+    pub fn update_for_peer(
+        &mut self,
+        now: Millis,
+        peer_id: PeerId,
+        command: &SenderToReceiverFrontCommands,
+    ) -> Result<ReceiverToSenderFrontCommands, FrontError> {
+        let transfer_id = command.transfer_id();
+        if let Some(state) = self.transfers.get(&transfer_id) {
+            if state.peer_id != Some(peer_id) {
+                return Err(FrontError::PeerMismatch {
+                    transfer_id,
+                    peer_id,
+                });
+            }
+        }
+        self.update_from(now, Some(peer_id), command)
+    }
+
+    fn update_from(
+        &mut self,
+        now: Millis,
+        peer_id: Option<PeerId>,
+        command: &SenderToReceiverFrontCommands,
+    ) -> Result<ReceiverToSenderFrontCommands, FrontError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "front_update",
+            transfer_id = command.transfer_id(),
+            command = command.to_octet(),
+        )
+        .entered();
+        let result = self.apply_command(now, peer_id, command);
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(answer) => {
+                tracing::debug!(name: "front_result", outcome = "accepted", answer = answer.to_octet());
+            }
+            Err(err) => tracing::debug!(name: "front_result", outcome = "error", error = %err),
+        }
+        result
+    }
+
+    fn apply_command(
+        &mut self,
+        now: Millis,
+        peer_id: Option<PeerId>,
         command: &SenderToReceiverFrontCommands,
-    ) -> io::Result<ReceiverToSenderFrontCommands> {
+    ) -> Result<ReceiverToSenderFrontCommands, FrontError> {
+        if let Some(state) = self.transfers.get_mut(&command.transfer_id()) {
+            state.last_activity = now;
+        }
+
         match command {
             SenderToReceiverFrontCommands::StartTransfer(start_transfer_data) => {
-                if self
-                    .state
-                    .as_ref()
-                    .map_or(true, |s| s.transfer_id.0 != start_transfer_data.transfer_id)
-                {
-                    // Either logic is not set or the transfer_id is different, so we start with a fresh InLogic.
-                    self.state = Some(State {
-                        transfer_id: TransferId(start_transfer_data.transfer_id),
-                        logic: Logic::new(
-                            start_transfer_data.total_octet_size as usize,
-                            start_transfer_data.chunk_size as usize,
-                        ),
-                    });
+                let transfer_id = start_transfer_data.transfer_id;
+                if self.apply_start_parameters(start_transfer_data)? {
+                    // Shrinking the blob left only chunks that have already been received.
+                    self.current_transfer_id = Some(transfer_id);
+                    self.verify_completed(transfer_id)?;
+                    self.deliver_completed(transfer_id);
+                    return Ok(ReceiverToSenderFrontCommands::Completed(transfer_id));
+                }
+                match self.transfers.get(&transfer_id) {
+                    Some(state) if !state.logic.is_complete() => {
+                        // The transfer is already in progress, keep what has been received so far.
+                        self.current_transfer_id = Some(transfer_id);
+                    }
+                    Some(_) if self.completed_start_policy == CompletedStartPolicy::AckComplete => {
+                        return Ok(ReceiverToSenderFrontCommands::Completed(
+                            start_transfer_data.transfer_id,
+                        ));
+                    }
+                    None if self.completed_start_policy == CompletedStartPolicy::AckComplete
+                        && self.was_delivered_with(start_transfer_data) =>
+                    {
+                        // The blob has already been delivered on the completion channel or hook.
+                        // A delivered id with other parameters is a new transfer that reuses it.
+                        return Ok(ReceiverToSenderFrontCommands::Completed(
+                            start_transfer_data.transfer_id,
+                        ));
+                    }
+                    _ => {
+                        // Either the transfer is unknown or the completed transfer should be
+                        // restarted, so we start with a fresh InLogic.
+                        self.make_room_for_transfer(transfer_id)?;
+                        self.insert_state(
+                            transfer_id,
+                            State {
+                                transfer_id: TransferId(transfer_id),
+                                peer_id,
+                                logic: self.new_logic(start_transfer_data)?,
+                                schedule: start_transfer_data.schedule.clone(),
+                                blob_hash: start_transfer_data.blob_hash,
+                                last_activity: now,
+                                checkpoints: Vec::new(),
+                                verified_chunk_count: 0,
+                            },
+                        );
+                        self.current_transfer_id = Some(transfer_id);
+                        if self.apply_pending_chunks(transfer_id, peer_id)? {
+                            return Ok(ReceiverToSenderFrontCommands::Completed(
+                                start_transfer_data.transfer_id,
+                            ));
+                        }
+                    }
                 }
                 Ok(ReceiverToSenderFrontCommands::AckStart(
                     start_transfer_data.transfer_id,
                 ))
             }
-            SenderToReceiverFrontCommands::SetChunk(chunk_data) => {
-                if let Some(ref mut state) = self.state {
-                    let ack = state.logic.update(&chunk_data.data)?;
-                    Ok(ReceiverToSenderFrontCommands::AckChunk(AckChunkFrontData {
-                        transfer_id: chunk_data.transfer_id,
-                        data: ack,
-                    }))
-                } else {
-                    Err(io::Error::new(
-                        ErrorKind::InvalidData,
-                        format!("Unknown transfer_id {}", chunk_data.transfer_id.0),
-                    ))
+            SenderToReceiverFrontCommands::SetChunk(chunk_data) => self.receive_chunks(
+                peer_id,
+                chunk_data.transfer_id,
+                slice::from_ref(&chunk_data.data),
+            ),
+            SenderToReceiverFrontCommands::SetChunks(chunks_data) => {
+                self.receive_chunks(peer_id, chunks_data.transfer_id, &chunks_data.chunks)
+            }
+            SenderToReceiverFrontCommands::AbortTransfer(abort_data) => {
+                self.remove_state(abort_data.transfer_id);
+                self.pending_chunks
+                    .retain(|pending| pending.chunk.transfer_id.0 != abort_data.transfer_id);
+                // Always acknowledge, so the sender can stop even if a previous `AckAbort` was lost.
+                Ok(ReceiverToSenderFrontCommands::AckAbort(
+                    abort_data.transfer_id,
+                ))
+            }
+            SenderToReceiverFrontCommands::CompleteTransfer(complete_data) => {
+                self.complete_transfer(complete_data)
+            }
+            SenderToReceiverFrontCommands::HashCheckpoint(checkpoint_data) => {
+                self.receive_checkpoint(checkpoint_data)
+            }
+            SenderToReceiverFrontCommands::Keepalive(keepalive_data) => {
+                self.keep_alive(keepalive_data.transfer_id)
+            }
+        }
+    }
+
+    /// Answers a `Keepalive` with the state of the transfer. The activity of the transfer has
+    /// already been updated by [`Self::apply_command`].
+    fn keep_alive(&self, transfer_id: u16) -> Result<ReceiverToSenderFrontCommands, FrontError> {
+        let state = self
+            .transfers
+            .get(&transfer_id)
+            .ok_or(FrontError::TransferNotFound(transfer_id))?;
+        if state.logic.is_complete() {
+            return Ok(ReceiverToSenderFrontCommands::Completed(transfer_id));
+        }
+        Ok(self.ack_chunks(state))
+    }
+
+    /// Acknowledges the chunks that have been received for a transfer, together with the
+    /// credit if a receive window is set.
+    ///
+    /// If a chunk has been received more than 64 chunks after the first missing chunk, the
+    /// acknowledgement is an `AckChunkMask` instead, without credit, so that the sender does
+    /// not resend that chunk. The sender keeps the credit from before.
+    fn ack_chunks(&self, state: &State) -> ReceiverToSenderFrontCommands {
+        if state.logic.needs_ack_mask() {
+            return ReceiverToSenderFrontCommands::AckChunkMask(AckChunkMaskFrontData {
+                transfer_id: state.transfer_id,
+                data: state.logic.ack_mask(),
+            });
+        }
+        if self.receive_window.is_some() {
+            return ReceiverToSenderFrontCommands::AckChunkCredit(AckChunkCreditFrontData {
+                transfer_id: state.transfer_id,
+                data: state.logic.ack_with_credit(),
+            });
+        }
+        ReceiverToSenderFrontCommands::AckChunk(AckChunkFrontData {
+            transfer_id: state.transfer_id,
+            data: state.logic.ack(),
+        })
+    }
+
+    /// Applies the chunks of a `SetChunk` or `SetChunks` command and acknowledges them with a
+    /// single command.
+    fn receive_chunks(
+        &mut self,
+        peer_id: Option<PeerId>,
+        transfer_id: TransferId,
+        chunks: &[SetChunkData],
+    ) -> Result<ReceiverToSenderFrontCommands, FrontError> {
+        if let Some(state) = self.transfers.get_mut(&transfer_id.0) {
+            let had_header = state.logic.header().is_some();
+            let was_complete = state.logic.is_complete();
+            let result = match chunks {
+                // A single chunk counts a redundant resend in the wasted bytes.
+                [chunk] => state.logic.update(chunk),
+                _ => state.logic.update_batch(chunks),
+            };
+            if was_complete {
+                // The sender has not received `Completed` yet, so it resends the chunks.
+                if let Err(err) = result {
+                    debug!("chunk for complete transfer_id {}: {err}", transfer_id.0);
+                }
+                return Ok(ReceiverToSenderFrontCommands::Completed(transfer_id.0));
+            }
+            result?;
+            let is_complete = state.logic.is_complete();
+            if !had_header {
+                self.report_header(transfer_id.0);
+            }
+            self.verify_checkpoints(transfer_id.0)?;
+            if is_complete {
+                self.verify_completed(transfer_id.0)?;
+                self.deliver_completed(transfer_id.0);
+                // Later chunks for the transfer are answered with `Completed` as well, in case
+                // this answer is lost.
+                return Ok(ReceiverToSenderFrontCommands::Completed(transfer_id.0));
+            }
+            Ok(self.ack_chunks(&self.transfers[&transfer_id.0]))
+        } else if self.was_delivered(transfer_id.0) {
+            // The blob has already been delivered on the completion channel or hook.
+            Ok(ReceiverToSenderFrontCommands::Completed(transfer_id.0))
+        } else if self.unknown_transfer_policy == UnknownTransferPolicy::NeedStart {
+            for chunk in chunks {
+                if self.pending_chunks.len() < MAX_PENDING_CHUNK_COUNT {
+                    self.pending_chunks.push(PendingChunk {
+                        peer_id,
+                        chunk: SetChunkFrontData {
+                            transfer_id,
+                            data: chunk.clone(),
+                        },
+                    });
+                }
+            }
+            Ok(ReceiverToSenderFrontCommands::NeedStart(transfer_id.0))
+        } else {
+            Err(FrontError::TransferNotFound(transfer_id.0))
+        }
+    }
+
+    /// Keeps a hash checkpoint for the transfer and verifies it as soon as all the chunks that it
+    /// covers have been received. Checkpoints are ignored if no hasher is set.
+    fn receive_checkpoint(
+        &mut self,
+        checkpoint_data: &HashCheckpointData,
+    ) -> Result<ReceiverToSenderFrontCommands, FrontError> {
+        self.check_digest_octet_size(&checkpoint_data.hash)?;
+        let transfer_id = checkpoint_data.transfer_id;
+        let Some(state) = self.transfers.get_mut(&transfer_id) else {
+            return Err(FrontError::TransferNotFound(transfer_id));
+        };
+        let up_to_chunk = chunk_index_from_wire(checkpoint_data.up_to_chunk);
+        let chunk_count = state.logic.info().chunk_count;
+        if up_to_chunk > chunk_count {
+            return Err(BlobError::InvalidChunkIndex(up_to_chunk, chunk_count).into());
+        }
+
+        if self.hasher.is_some() && up_to_chunk > state.verified_chunk_count {
+            if state.checkpoints.len() >= MAX_PENDING_CHECKPOINT_COUNT {
+                state.checkpoints.remove(0);
+            }
+            let position = state
+                .checkpoints
+                .partition_point(|(pending_up_to_chunk, _)| *pending_up_to_chunk < up_to_chunk);
+            state
+                .checkpoints
+                .insert(position, (up_to_chunk, checkpoint_data.hash.clone()));
+            self.verify_checkpoints(transfer_id)?;
+        }
+
+        Ok(self.ack_chunks(&self.transfers[&transfer_id]))
+    }
+
+    /// Verifies the pending hash checkpoints of the transfer that all chunks have been received
+    /// for. A transfer that does not match a checkpoint is removed, so that it can be started
+    /// over.
+    fn verify_checkpoints(&mut self, transfer_id: u16) -> Result<(), FrontError> {
+        let (Some(hasher), Some(state)) = (&self.hasher, self.transfers.get_mut(&transfer_id))
+        else {
+            return Ok(());
+        };
+        let State {
+            logic,
+            checkpoints,
+            verified_chunk_count,
+            ..
+        } = state;
+        let mut mismatch = None;
+        checkpoints.retain(|(up_to_chunk, hash)| {
+            if mismatch.is_some() {
+                return false;
+            }
+            match logic.verify_checkpoint(hasher.as_ref(), *up_to_chunk, hash) {
+                Ok(true) => {
+                    *verified_chunk_count = *up_to_chunk;
+                    false
+                }
+                Ok(false) => true,
+                Err(err) => {
+                    mismatch = Some((*verified_chunk_count..*up_to_chunk, err));
+                    false
+                }
+            }
+        });
+
+        let Some((corrupted_chunks, err)) = mismatch else {
+            return Ok(());
+        };
+        warn!("chunks {corrupted_chunks:?} of transfer_id {transfer_id} are corrupted: {err}");
+        self.remove_state(transfer_id);
+        Err(err.into())
+    }
+
+    /// Checks that `hash` has the size of the digests of the hasher, if a hasher is set.
+    fn check_digest_octet_size(&self, hash: &[u8]) -> Result<(), FrontError> {
+        if let Some(hasher) = &self.hasher {
+            let digest_octet_size = hasher.digest_octet_size();
+            if hash.len() != digest_octet_size {
+                return Err(FrontError::DigestSizeMismatch {
+                    octet_count: hash.len(),
+                    digest_octet_size,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the chunk size of a `StartTransfer` is neither zero nor below the minimum
+    /// chunk size, that the size and chunk count are within the limits, that
+    /// the blob hash has the size of the digests of the hasher, and
+    /// that a `StartTransfer` for a known transfer declares the same size and chunk size as when
+    /// the transfer was started, unless the transfer is completed and will be restarted.
+    ///
+    /// A smaller size is accepted if all chunks received so far fit in it, and the blob of the
+    /// transfer is shrunk to the new size. Returns `true` if that completed the blob.
+    fn apply_start_parameters(
+        &mut self,
+        start_transfer_data: &StartTransferData,
+    ) -> Result<bool, FrontError> {
+        let total_octet_size = start_transfer_data.total_octet_size as usize;
+        let requested_chunk_size = start_transfer_data.chunk_size as usize;
+        if requested_chunk_size == 0 {
+            // Checked before `Logic::try_new`, since the chunk count is needed for the limits.
+            return Err(BlobError::InvalidChunkSize(requested_chunk_size).into());
+        }
+        let chunk_size = capped_chunk_size(requested_chunk_size, total_octet_size);
+        if chunk_size > MAX_PAYLOAD_OCTET_SIZE {
+            // The chunks could never be received, since longer payloads are rejected.
+            return Err(FrontError::ChunkSizeTooLarge {
+                transfer_id: start_transfer_data.transfer_id,
+                chunk_size,
+            });
+        }
+        check_min_chunk_size(total_octet_size, chunk_size, self.min_chunk_size)?;
+        let chunk_count = total_octet_size.div_ceil(chunk_size);
+        if total_octet_size > self.limits.max_octet_size
+            || chunk_count > self.limits.max_chunk_count
+        {
+            return Err(FrontError::TransferTooLarge {
+                transfer_id: start_transfer_data.transfer_id,
+                octet_count: total_octet_size,
+                chunk_count,
+            });
+        }
+        if let Some(blob_hash) = &start_transfer_data.blob_hash {
+            self.check_digest_octet_size(blob_hash)?;
+        }
+
+        let Some(state) = self.transfers.get_mut(&start_transfer_data.transfer_id) else {
+            return Ok(false);
+        };
+        let is_complete = state.logic.is_complete();
+        if is_complete && self.completed_start_policy == CompletedStartPolicy::Restart {
+            return Ok(false);
+        }
+
+        let info = state.logic.info();
+        if !is_complete
+            && info.chunk_octet_size
+                == capped_chunk_size(requested_chunk_size, info.total_octet_size)
+            && total_octet_size < info.total_octet_size
+        {
+            state.logic.truncate(total_octet_size)?;
+            return Ok(state.logic.is_complete());
+        }
+        if info.total_octet_size != total_octet_size
+            || capped_chunk_size(info.chunk_octet_size, total_octet_size) != chunk_size
+        {
+            return Err(FrontError::StartMismatch {
+                transfer_id: start_transfer_data.transfer_id,
+                octet_count: total_octet_size,
+                chunk_size,
+                started_octet_count: info.total_octet_size,
+                started_chunk_size: info.chunk_octet_size,
+            });
+        }
+
+        Ok(false)
+    }
+
+    /// Creates the receiving logic for a transfer that is started with `start_transfer_data`.
+    fn new_logic(&self, start_transfer_data: &StartTransferData) -> Result<Logic, BlobError> {
+        let total_octet_size = start_transfer_data.total_octet_size as usize;
+        let logic = Logic::try_new(
+            total_octet_size,
+            capped_chunk_size(start_transfer_data.chunk_size as usize, total_octet_size),
+        )?;
+        Ok(match self.receive_window {
+            Some(receive_window) => logic.with_receive_window(receive_window),
+            None => logic,
+        })
+    }
+
+    /// Checks that starting `transfer_id` does not exceed the maximum number of transfers, or
+    /// evicts a transfer to make room for it, depending on the [`TransferCapacityPolicy`].
+    fn make_room_for_transfer(&mut self, transfer_id: u16) -> Result<(), FrontError> {
+        if !self.transfers.contains_key(&transfer_id)
+            && self.transfers.len() >= self.limits.max_transfers
+        {
+            if self.transfer_capacity_policy == TransferCapacityPolicy::EvictLeastRecentlyActive {
+                let stalest_transfer_id = self
+                    .transfers
+                    .iter()
+                    .filter(|(_, state)| !state.logic.is_complete())
+                    .min_by_key(|(id, state)| (state.last_activity, **id))
+                    .map(|(id, _)| *id);
+                if let Some(stalest_transfer_id) = stalest_transfer_id {
+                    self.remove_state(stalest_transfer_id);
+                    self.evicted_transfer_ids.push(stalest_transfer_id);
+                    return Ok(());
                 }
             }
+            return Err(FrontError::TooManyTransfers {
+                transfer_id,
+                max_transfers: self.limits.max_transfers,
+            });
+        }
+        Ok(())
+    }
+
+    /// Verifies the hash of a `CompleteTransfer` and reports whether the blob is complete.
+    fn complete_transfer(
+        &self,
+        complete_data: &CompleteTransferData,
+    ) -> Result<ReceiverToSenderFrontCommands, FrontError> {
+        self.check_digest_octet_size(&complete_data.hash)?;
+
+        let transfer_id = complete_data.transfer_id;
+        let Some(state) = self.transfers.get(&transfer_id) else {
+            if self.was_delivered(transfer_id) {
+                // The blob has already been delivered on the completion channel or hook.
+                return Ok(ReceiverToSenderFrontCommands::Completed(transfer_id));
+            }
+            return Err(FrontError::TransferNotFound(transfer_id));
+        };
+
+        if !state.logic.is_complete() {
+            return Ok(self.ack_chunks(state));
+        }
+
+        if let Some(hasher) = &self.hasher {
+            state
+                .logic
+                .verify_hash(hasher.as_ref(), &complete_data.hash)?;
+        }
+
+        Ok(ReceiverToSenderFrontCommands::Completed(transfer_id))
+    }
+
+    /// Removes the transfers that no command has been received for in more than `max_idle`,
+    /// e.g. because the sender has disappeared.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, supplied by the caller.
+    /// * `max_idle` - How long a transfer may go without receiving any command.
+    ///
+    /// # Returns
+    ///
+    /// The ids of the removed transfers, in ascending order.
+    pub fn expire(&mut self, now: Millis, max_idle: Duration) -> Vec<u16> {
+        let mut expired_transfer_ids: Vec<u16> = self
+            .transfers
+            .iter()
+            .filter(|(_, state)| now.saturating_duration_since(state.last_activity) > max_idle)
+            .map(|(transfer_id, _)| *transfer_id)
+            .collect();
+        expired_transfer_ids.sort_unstable();
+        for &transfer_id in &expired_transfer_ids {
+            self.remove_state(transfer_id);
+        }
+        expired_transfer_ids
+    }
+
+    /// Removes all transfers that were started by `peer_id` with [`Self::update_for_peer`],
+    /// e.g. when the peer has disconnected. The chunks that the peer sent for transfers that
+    /// have not been started yet are dropped as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `peer_id` - The peer whose transfers are removed.
+    ///
+    /// # Returns
+    ///
+    /// The ids of the removed transfers, in ascending order.
+    pub fn cancel_peer(&mut self, peer_id: PeerId) -> Vec<u16> {
+        let mut cancelled_transfer_ids: Vec<u16> = self
+            .transfers
+            .iter()
+            .filter(|(_, state)| state.peer_id == Some(peer_id))
+            .map(|(transfer_id, _)| *transfer_id)
+            .collect();
+        cancelled_transfer_ids.sort_unstable();
+        for &transfer_id in &cancelled_transfer_ids {
+            self.remove_state(transfer_id);
+        }
+        self.pending_chunks
+            .retain(|pending| pending.peer_id != Some(peer_id));
+        cancelled_transfer_ids
+    }
+
+    /// Removes the transfer with `transfer_id` from the receiver.
+    ///
+    /// Use it to release a completed transfer when no completion sender or hook is set, since the
+    /// blob is otherwise kept by the receiver. A later `StartTransfer` with the same
+    /// `transfer_id` starts the transfer over.
+    ///
+    /// # Arguments
+    ///
+    /// * `transfer_id` - The id of the transfer to remove.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the blob if the removed transfer was complete, or `None` if it was
+    /// incomplete or not found.
+    pub fn remove_transfer(&mut self, transfer_id: u16) -> Option<Vec<u8>> {
+        self.remove_state(transfer_id)
+            .and_then(|state| state.logic.take_blob())
+    }
+
+    /// Returns the ids of the transfers that have been evicted to make room for new ones since
+    /// the last call, see [`TransferCapacityPolicy::EvictLeastRecentlyActive`].
+    ///
+    /// # Returns
+    ///
+    /// The evicted ids, in the order they were evicted.
+    pub fn take_evicted_transfer_ids(&mut self) -> Vec<u16> {
+        core::mem::take(&mut self.evicted_transfer_ids)
+    }
+
+    /// Returns the [`ReceiveStats`] summed over all transfers, including the ones that have
+    /// been removed.
+    #[must_use]
+    pub fn stats(&self) -> ReceiveStats {
+        let mut stats = self.removed_stats;
+        for state in self.transfers.values() {
+            stats += *state.logic.stats();
+        }
+        stats
+    }
+
+    /// Starts tracking `state`, keeping the stats of a transfer with the same id that it replaces.
+    fn insert_state(&mut self, transfer_id: u16, state: State) {
+        if let Some(replaced) = self.transfers.insert(transfer_id, state) {
+            self.removed_stats += *replaced.logic.stats();
+        }
+    }
+
+    /// Stops tracking the transfer with `transfer_id`, keeping its stats.
+    fn remove_state(&mut self, transfer_id: u16) -> Option<State> {
+        let state = self.transfers.remove(&transfer_id)?;
+        self.removed_stats += *state.logic.stats();
+        Some(state)
+    }
+
+    /// Returns the progress of the transfer with `transfer_id`.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the status, or `None` if the transfer is not known by the receiver.
+    #[must_use]
+    pub fn transfer_status(&self, transfer_id: u16) -> Option<TransferStatus> {
+        self.transfers.get(&transfer_id).map(|state| {
+            let info = state.logic.info();
+            TransferStatus {
+                octet_count: info.total_octet_size,
+                chunk_size: info.chunk_octet_size,
+                received_chunk_count: info.chunk_count_received,
+                chunk_count: info.chunk_count,
+                is_complete: state.logic.is_complete(),
+            }
+        })
+    }
+
+    /// Returns the ids of all transfers that the receiver keeps, in no particular order.
+    ///
+    /// Completed transfers are included until they are delivered on the completion channel or
+    /// hook, removed or expired.
+    pub fn active_transfer_ids(&self) -> impl Iterator<Item = u16> + '_ {
+        self.transfers.keys().copied()
+    }
+
+    /// Returns the current transfer, which is the transfer that was started last.
+    fn current(&self) -> Option<&State> {
+        self.current_transfer_id
+            .and_then(|transfer_id| self.transfers.get(&transfer_id))
+    }
+
+    /// Creates a `Pause` command for the current transfer, asking the sender to stop sending chunks.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the `Pause` command, or `None` if no transfer has been started.
+    #[must_use]
+    pub fn pause(&self) -> Option<ReceiverToSenderFrontCommands> {
+        self.current().map(|state| {
+            ReceiverToSenderFrontCommands::Pause(PauseTransferData {
+                transfer_id: state.transfer_id.0,
+            })
+        })
+    }
+
+    /// Creates a `Resume` command for the current transfer, asking the sender to continue
+    /// sending chunks after a `Pause`.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the `Resume` command, or `None` if no transfer has been started.
+    #[must_use]
+    pub fn resume(&self) -> Option<ReceiverToSenderFrontCommands> {
+        self.current().map(|state| {
+            ReceiverToSenderFrontCommands::Resume(ResumeTransferData {
+                transfer_id: state.transfer_id.0,
+            })
+        })
+    }
+
+    /// Applies the chunks that were kept while the transfer with `transfer_id` was unknown, if
+    /// they were sent by `peer_id`, the peer that started the transfer. Kept chunks for the
+    /// transfer from other peers are dropped, and the kept chunks for other transfers are left
+    /// in place.
+    ///
+    /// Returns `true` if the kept chunks completed the blob.
+    fn apply_pending_chunks(
+        &mut self,
+        transfer_id: u16,
+        peer_id: Option<PeerId>,
+    ) -> Result<bool, FrontError> {
+        let (pending_chunks, other_chunks): (Vec<_>, Vec<_>) =
+            core::mem::take(&mut self.pending_chunks)
+                .into_iter()
+                .partition(|pending| pending.chunk.transfer_id.0 == transfer_id);
+        self.pending_chunks = other_chunks;
+        let Some(state) = self.transfers.get_mut(&transfer_id) else {
+            return Ok(false);
+        };
+        for pending in &pending_chunks {
+            let chunk_index = pending.chunk.data.chunk_index;
+            if pending.peer_id != peer_id {
+                debug!("dropped kept chunk {chunk_index} from another peer");
+                continue;
+            }
+            if let Err(err) = state.logic.update(&pending.chunk.data) {
+                debug!("skipped kept chunk {chunk_index}: {err}");
+            }
+        }
+        let is_complete = state.logic.is_complete();
+        self.report_header(transfer_id);
+        if is_complete {
+            self.verify_completed(transfer_id)?;
+            self.deliver_completed(transfer_id);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Calls the header hook, if set, with the first chunk of the transfer if it has been received.
+    fn report_header(&mut self, transfer_id: u16) {
+        let (Some(hook), Some(state)) = (&mut self.header_hook, self.transfers.get(&transfer_id))
+        else {
+            return;
+        };
+        if let Some(header) = state.logic.header() {
+            (hook.0)(state.transfer_id, header);
+        }
+    }
+
+    /// Verifies the completed blob against the `blob_hash` from `StartTransfer`, if both the
+    /// hash and a hasher are set. A blob that does not match is removed, so that the transfer
+    /// can be started over.
+    fn verify_completed(&mut self, transfer_id: u16) -> Result<(), FrontError> {
+        let (Some(hasher), Some(state)) = (&self.hasher, self.transfers.get(&transfer_id)) else {
+            return Ok(());
+        };
+        let Some(blob_hash) = &state.blob_hash else {
+            return Ok(());
+        };
+        let result = state.logic.verify_hash(hasher.as_ref(), blob_hash);
+        if result.is_err() {
+            self.remove_state(transfer_id);
+        }
+        Ok(result?)
+    }
+
+    /// Returns `true` if the blob of `transfer_id` has recently been handed to the completion
+    /// hook or channel.
+    fn was_delivered(&self, transfer_id: u16) -> bool {
+        self.delivered_transfers
+            .iter()
+            .any(|delivered| delivered.transfer_id.0 == transfer_id)
+    }
+
+    /// Returns `true` if the blob of the transfer has recently been handed to the completion
+    /// hook or channel, and it was started with the same parameters as `start_transfer_data`.
+    fn was_delivered_with(&self, start_transfer_data: &StartTransferData) -> bool {
+        let octet_count = start_transfer_data.total_octet_size as usize;
+        let chunk_size = capped_chunk_size(start_transfer_data.chunk_size as usize, octet_count);
+        self.delivered_transfers.iter().any(|delivered| {
+            delivered.transfer_id.0 == start_transfer_data.transfer_id
+                && delivered.octet_count == octet_count
+                && delivered.chunk_size == chunk_size
+                && delivered.blob_hash == start_transfer_data.blob_hash
+        })
+    }
+
+    /// Removes the completed transfer and hands its blob to the completion hook or channel, if
+    /// set.
+    fn deliver_completed(&mut self, transfer_id: u16) {
+        if self.completion_sender.is_none() && self.completion_hook.is_none() {
+            return;
+        }
+        let Some(state) = self.remove_state(transfer_id) else {
+            return;
+        };
+        let transfer_id = state.transfer_id;
+        self.delivered_transfers
+            .retain(|delivered| delivered.transfer_id != transfer_id);
+        if self.delivered_transfers.len() >= MAX_DELIVERED_TRANSFER_COUNT {
+            self.delivered_transfers.remove(0);
+        }
+        self.delivered_transfers.push(DeliveredTransfer {
+            transfer_id,
+            octet_count: state.logic.octet_count(),
+            chunk_size: state.logic.chunk_size(),
+            blob_hash: state.blob_hash,
+        });
+        let Some(blob) = state.logic.take_blob() else {
+            return;
+        };
+        if let Some(hook) = &mut self.completion_hook {
+            (hook.0)(transfer_id, blob);
+        } else if let Some(sender) = &self.completion_sender {
+            if sender.send((transfer_id, blob)).is_err() {
+                warn!(
+                    "completion receiver is gone, dropping blob for transfer {}",
+                    transfer_id.0
+                );
+            }
         }
     }
 
+    /// Creates an `AckBytes` command for the current transfer.
+    ///
+    /// It can be sent alongside the regular chunk acknowledgements to peers that
+    /// track progress in octets rather than chunks.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the `AckBytes` command, or `None` if no transfer has been started.
+    #[must_use]
+    pub fn ack_bytes(&self) -> Option<ReceiverToSenderFrontCommands> {
+        self.current().map(|state| {
+            ReceiverToSenderFrontCommands::AckBytes(AckBytesFrontData {
+                transfer_id: state.transfer_id,
+                data: state.logic.ack_bytes(),
+            })
+        })
+    }
+
+    /// Creates a `RequestChunks` command for the gaps in the transfer with `transfer_id`, see
+    /// [`Logic::request_chunks`].
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the `RequestChunks` command, or `None` if the transfer is not known by the
+    /// receiver or has no gaps.
+    #[must_use]
+    pub fn request_chunks(&self, transfer_id: u16) -> Option<ReceiverToSenderFrontCommands> {
+        let state = self.transfers.get(&transfer_id)?;
+        let request = state.logic.request_chunks();
+        (!request.chunk_indices.is_empty()).then_some(ReceiverToSenderFrontCommands::RequestChunks(
+            RequestChunksFrontData {
+                transfer_id: state.transfer_id,
+                data: request,
+            },
+        ))
+    }
+
+    /// Returns the advisory schedule announced by the sender for the current transfer.
+    ///
+    /// The schedule is only a hint of when chunks are expected to arrive and is not
+    /// enforced by the receiver.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the schedule, which may be empty, or `None` if no transfer has been started.
+    #[must_use]
+    pub fn schedule(&self) -> Option<&[u64]> {
+        self.current().map(|state| state.schedule.as_slice())
+    }
+
     /// Retrieves the full blob data if all chunks have been received.
     ///
     /// # Returns
@@ -146,12 +1419,26 @@ impl FrontLogic {
     /// or `None` if the blob is incomplete.
     #[must_use]
     pub fn blob(&self) -> Option<&[u8]> {
-        self.state.as_ref().and_then(|state| state.logic.blob())
+        self.current().and_then(|state| state.logic.blob())
+    }
+
+    /// Retrieves the full blob data of the transfer with `transfer_id`, e.g. when several
+    /// transfers are received at the same time.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the blob if all chunks of the transfer have been received, or `None` if the
+    /// transfer is incomplete or not known by the receiver.
+    #[must_use]
+    pub fn transfer_blob(&self, transfer_id: u16) -> Option<&[u8]> {
+        self.transfers
+            .get(&transfer_id)
+            .and_then(|state| state.logic.blob())
     }
 
     #[must_use]
     pub fn info(&self) -> Option<Info> {
-        self.state.as_ref().map(|s| {
+        self.current().map(|s| {
             let info = s.logic.info();
             Info {
                 transfer_id: s.transfer_id,
@@ -0,0 +1,136 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::err::BlobError;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// A stream of binary data where each chunk carries its own octet offset, so chunks
+/// can be of any size.
+///
+/// The received octets are tracked as sorted, non-overlapping octet ranges.
+#[derive(Debug)]
+pub struct BlobRangeStreamIn {
+    received: Vec<Range<usize>>,
+    blob: Vec<u8>,
+}
+
+impl BlobRangeStreamIn {
+    /// Creates a new `BlobRangeStreamIn` instance with the specified number of octets.
+    ///
+    /// # Parameters
+    /// - `octet_count`: The total number of octets (bytes) in the stream.
+    ///
+    /// # Returns
+    /// A new `BlobRangeStreamIn` instance.
+    #[must_use]
+    pub fn new(octet_count: usize) -> Self {
+        Self {
+            received: Vec::new(),
+            blob: vec![0u8; octet_count],
+        }
+    }
+
+    /// Returns the total number of octets in the stream.
+    #[must_use]
+    pub const fn octet_count(&self) -> usize {
+        self.blob.len()
+    }
+
+    /// Returns the received octet ranges, sorted and merged.
+    #[must_use]
+    pub fn received_ranges(&self) -> &[Range<usize>] {
+        &self.received
+    }
+
+    /// Checks if all octets have been received.
+    ///
+    /// # Returns
+    /// `true` if all octets have been received; `false` otherwise.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        match self.received.as_slice() {
+            [] => self.blob.is_empty(),
+            [range] => range.start == 0 && range.end == self.blob.len(),
+            _ => false,
+        }
+    }
+
+    /// Returns a reference to the complete blob if all octets have been received.
+    ///
+    /// # Returns
+    /// An `Option` containing a reference to the blob if complete; otherwise, `None`.
+    #[must_use]
+    pub fn blob(&self) -> Option<&[u8]> {
+        self.is_complete().then_some(&self.blob[..])
+    }
+
+    /// Sets the `payload` octets starting at `octet_offset`.
+    ///
+    /// Overlapping a previously received range is accepted as long as the overlapping
+    /// octets are identical, so an exact duplicate is idempotent.
+    ///
+    /// # Parameters
+    /// - `octet_offset`: The offset in the blob where the payload starts.
+    /// - `payload`: A slice of octets representing the chunk's data.
+    ///
+    /// # Errors
+    /// Returns a `BlobError` if:
+    /// - The payload does not fit within the blob.
+    /// - The payload overlaps a previously received range with different contents.
+    ///
+    /// # Returns
+    /// `Ok(())` if the chunk was set successfully; otherwise, a `BlobError`.
+    pub fn set_range(&mut self, octet_offset: usize, payload: &[u8]) -> Result<(), BlobError> {
+        let end = octet_offset
+            .checked_add(payload.len())
+            .filter(|end| *end <= self.blob.len())
+            .ok_or(BlobError::OutOfBounds)?;
+        let new_range = octet_offset..end;
+
+        for existing_range in &self.received {
+            let overlap_start = existing_range.start.max(new_range.start);
+            let overlap_end = existing_range.end.min(new_range.end);
+            if overlap_start >= overlap_end {
+                continue;
+            }
+
+            let existing_octets = &self.blob[overlap_start..overlap_end];
+            let new_octets = &payload[overlap_start - octet_offset..overlap_end - octet_offset];
+            if existing_octets != new_octets {
+                return Err(BlobError::OverlappingChunk {
+                    existing_range: existing_range.clone(),
+                    new_range,
+                });
+            }
+        }
+
+        if new_range.is_empty() {
+            return Ok(());
+        }
+
+        self.blob[new_range.clone()].copy_from_slice(payload);
+        self.insert_range(new_range);
+
+        Ok(())
+    }
+
+    fn insert_range(&mut self, range: Range<usize>) {
+        let mut merged = range;
+        self.received.retain(|existing| {
+            let touches = existing.start <= merged.end && merged.start <= existing.end;
+            if touches {
+                merged.start = merged.start.min(existing.start);
+                merged.end = merged.end.max(existing.end);
+            }
+            !touches
+        });
+
+        let index = self
+            .received
+            .partition_point(|existing| existing.start < merged.start);
+        self.received.insert(index, merged);
+    }
+}
@@ -3,19 +3,152 @@
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
 use crate::err::BlobError;
+#[cfg(feature = "sha256")]
+use crate::hash::{PrefixHasher, Sha256State};
+use crate::storage::BlobStorage;
+#[cfg(feature = "std")]
+use crate::storage::WriterStorage;
 use crate::ChunkIndex;
+use alloc::vec;
+use alloc::vec::Vec;
 use bit_array_rs::BitArray;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::io;
+
+/// Checks that `chunk_size` is at least `min_chunk_size`, to keep the per-chunk overhead and
+/// bookkeeping of a transfer bounded.
+///
+/// A smaller chunk size is allowed if the whole blob fits in a single chunk anyway.
+///
+/// # Parameters
+/// - `octet_count`: The total number of octets in the blob.
+/// - `chunk_size`: The size of each chunk.
+/// - `min_chunk_size`: The smallest allowed chunk size.
+///
+/// # Errors
+/// Returns `BlobError::ChunkSizeTooSmall` if `chunk_size` is below `min_chunk_size` and the
+/// blob needs more than one chunk.
+pub const fn check_min_chunk_size(
+    octet_count: usize,
+    chunk_size: usize,
+    min_chunk_size: usize,
+) -> Result<(), BlobError> {
+    if chunk_size < min_chunk_size && chunk_size < octet_count {
+        return Err(BlobError::ChunkSizeTooSmall(chunk_size, min_chunk_size));
+    }
+    Ok(())
+}
+
+/// Reports the size of the last chunk both as the naive `octet_count % chunk_size` and as the
+/// correct size, to help finding implementations that use the naive formula.
+///
+/// The two only differ when `octet_count` is an exact, non-zero multiple of `chunk_size`, where
+/// the naive formula gives zero although the last chunk is a full chunk.
+///
+/// # Parameters
+/// - `octet_count`: The total number of octets in the blob.
+/// - `chunk_size`: The size of each chunk.
+///
+/// # Panics
+/// Will panic if `chunk_size` is zero.
+///
+/// # Returns
+/// The naive and the correct size of the last chunk, in that order. Both are zero for an
+/// empty blob.
+#[must_use]
+pub const fn last_chunk_size_report(octet_count: usize, chunk_size: usize) -> (usize, usize) {
+    assert!(chunk_size > 0, "chunk_size must be greater than zero");
+    let naive = octet_count % chunk_size;
+    let correct = if octet_count == 0 {
+        0
+    } else {
+        (octet_count - 1) % chunk_size + 1
+    };
+    (naive, correct)
+}
 
 /// A struct representing a stream of binary data divided into fixed-size chunks.
+///
+/// The received octets are written into a [`BlobStorage`], which is a `Vec<u8>` by default.
 #[allow(unused)]
 #[derive(Debug)]
-pub struct BlobStreamIn {
+pub struct BlobStreamIn<S = Vec<u8>> {
     pub(crate) bit_array: BitArray,
     pub(crate) fixed_chunk_size: usize,
     pub(crate) octet_count: usize,
-    blob: Vec<u8>,
+    /// The octet offset of each chunk followed by `octet_count`, for chunks of different
+    /// sizes. Empty if all chunks are `fixed_chunk_size` octets.
+    chunk_offsets: Vec<usize>,
+    blob: S,
+    wasted_octet_count: usize,
+    ignore_duplicates: bool,
+    #[cfg(feature = "sha256")]
+    prefix_hasher: PrefixHasher<Sha256State>,
+}
+
+/// Reads a complete blob through [`io::Read`], without copying it into a `Vec` first.
+/// Created with [`BlobStreamIn::reader`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct BlobReader<'a> {
+    blob: &'a [u8],
+    position: usize,
+}
+
+#[cfg(feature = "std")]
+impl io::Read for BlobReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = &self.blob[self.position..];
+        let octet_count = remaining.len().min(buf.len());
+        buf[..octet_count].copy_from_slice(&remaining[..octet_count]);
+        self.position += octet_count;
+        Ok(octet_count)
+    }
 }
 
+/// Copies the bits of `bit_array`, set by set, since `BitArray` does not implement `Clone`.
+fn clone_bit_array(bit_array: &BitArray) -> BitArray {
+    let mut cloned = BitArray::new(bit_array.bit_count());
+    for index in (0..bit_array.bit_count()).filter(|&index| bit_array.get(index)) {
+        cloned.set(index);
+    }
+    cloned
+}
+
+impl<S: Clone> Clone for BlobStreamIn<S> {
+    fn clone(&self) -> Self {
+        Self {
+            bit_array: clone_bit_array(&self.bit_array),
+            fixed_chunk_size: self.fixed_chunk_size,
+            octet_count: self.octet_count,
+            chunk_offsets: self.chunk_offsets.clone(),
+            blob: self.blob.clone(),
+            wasted_octet_count: self.wasted_octet_count,
+            ignore_duplicates: self.ignore_duplicates,
+            #[cfg(feature = "sha256")]
+            prefix_hasher: self.prefix_hasher.clone(),
+        }
+    }
+}
+
+/// Two streams are equal if they have the same layout, the same received chunks and the same
+/// octets in the storage. The [`BlobStreamIn::wasted_bytes`] statistic is not compared, so
+/// that two streams that converged on the same chunks in a different way are still equal.
+impl<S: BlobStorage> PartialEq for BlobStreamIn<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.fixed_chunk_size == other.fixed_chunk_size
+            && self.octet_count == other.octet_count
+            && self.chunk_offsets == other.chunk_offsets
+            && self.chunk_count() == other.chunk_count()
+            && (0..self.chunk_count())
+                .all(|index| self.bit_array.get(index) == other.bit_array.get(index))
+            && self.blob.octets() == other.blob.octets()
+    }
+}
+
+impl<S: BlobStorage> Eq for BlobStreamIn<S> {}
+
 impl BlobStreamIn {
     /// Creates a new `BlobStreamIn` instance with the specified number of octets and chunk size.
     ///
@@ -31,20 +164,275 @@ impl BlobStreamIn {
     #[allow(unused)]
     #[must_use]
     pub fn new(octet_count: usize, fixed_chunk_size: usize) -> Self {
+        Self::with_storage(vec![0u8; octet_count], fixed_chunk_size)
+    }
+
+    /// Creates a new `BlobStreamIn` instance like [`Self::new`], but returns an error instead of
+    /// panicking if `fixed_chunk_size` is zero, e.g. when the size comes from a peer.
+    ///
+    /// # Parameters
+    /// - `octet_count`: The total number of octets (bytes) in the stream.
+    /// - `fixed_chunk_size`: The size of each chunk in the stream.
+    ///
+    /// # Errors
+    /// Returns `BlobError::InvalidChunkSize` if `fixed_chunk_size` is zero.
+    pub fn try_new(octet_count: usize, fixed_chunk_size: usize) -> Result<Self, BlobError> {
+        if fixed_chunk_size == 0 {
+            return Err(BlobError::InvalidChunkSize(fixed_chunk_size));
+        }
+        Ok(Self::new(octet_count, fixed_chunk_size))
+    }
+
+    /// Creates a new `BlobStreamIn` instance for chunks of different sizes, e.g. for a source
+    /// that is naturally split on frame boundaries.
+    ///
+    /// The offset of each chunk is the sum of the sizes of the chunks before it.
+    ///
+    /// # Parameters
+    /// - `octet_count`: The total number of octets (bytes) in the stream.
+    /// - `chunk_sizes`: The size of each chunk, in order.
+    ///
+    /// # Errors
+    /// Returns `BlobError::InvalidChunkSize` if any of the chunk sizes is zero, or
+    /// `BlobError::InconsistentOctetCount` if the chunk sizes do not add up to `octet_count`.
+    pub fn with_chunk_sizes(octet_count: usize, chunk_sizes: &[usize]) -> Result<Self, BlobError> {
+        let mut chunk_offsets = Vec::with_capacity(chunk_sizes.len() + 1);
+        let mut octet_offset: usize = 0;
+        for &chunk_size in chunk_sizes {
+            if chunk_size == 0 {
+                return Err(BlobError::InvalidChunkSize(chunk_size));
+            }
+            chunk_offsets.push(octet_offset);
+            octet_offset = octet_offset.saturating_add(chunk_size);
+        }
+        if octet_offset != octet_count {
+            return Err(BlobError::InconsistentOctetCount(octet_count, octet_offset));
+        }
+        chunk_offsets.push(octet_count);
+
+        let largest_chunk_size = chunk_sizes.iter().copied().max().unwrap_or(1);
+        let mut stream = Self::new(octet_count, largest_chunk_size);
+        stream.bit_array = BitArray::new(chunk_sizes.len());
+        stream.chunk_offsets = chunk_offsets;
+        Ok(stream)
+    }
+
+    /// Creates a `BlobStreamIn` from a previously persisted, partially received blob.
+    ///
+    /// This makes it possible to resume a transfer, e.g. after a restart, instead of
+    /// receiving all the chunks again. Chunks listed in `received` are treated as already
+    /// received, so setting them again is rejected as redundant.
+    ///
+    /// # Parameters
+    /// - `blob`: The full-length buffer, with the octets of the received chunks in place.
+    /// - `fixed_chunk_size`: The size of each chunk in the stream.
+    /// - `received`: The indices of the chunks that have already been received.
+    ///
+    /// # Errors
+    /// Returns `BlobError::InvalidChunkIndex` if any of the `received` indices is out of range
+    /// for the length of the `blob`.
+    ///
+    /// # Panics
+    /// Will panic if `fixed_chunk_size` is zero.
+    ///
+    /// # Returns
+    /// A `BlobStreamIn` instance with the `received` chunks marked as received.
+    pub fn from_existing(
+        blob: Vec<u8>,
+        fixed_chunk_size: usize,
+        received: &[ChunkIndex],
+    ) -> Result<Self, BlobError> {
+        let mut stream = Self::with_storage(blob, fixed_chunk_size);
+        let chunk_count = stream.chunk_count();
+        for &chunk_index in received {
+            if chunk_index >= chunk_count {
+                return Err(BlobError::InvalidChunkIndex(chunk_index, chunk_count));
+            }
+            stream.bit_array.set(chunk_index);
+        }
+        stream.hash_contiguous_prefix();
+        Ok(stream)
+    }
+
+    /// Shrinks the stream to `octet_count` octets, keeping the chunks that have been received.
+    ///
+    /// This is only possible if every received chunk has the same octets before and after the
+    /// shrink, i.e. it lies completely before the new end, and a received last chunk keeps
+    /// its size.
+    ///
+    /// # Parameters
+    /// - `octet_count`: The new total number of octets, which must not be larger than before.
+    ///
+    /// # Errors
+    /// Returns `BlobError::TruncateBeyondSize` if `octet_count` is larger than the current octet
+    /// count, or `BlobError::ReceivedChunkBeyondSize` if a received chunk does not fit the new
+    /// size. Nothing is changed in that case.
+    pub fn truncate(&mut self, octet_count: usize) -> Result<(), BlobError> {
+        if octet_count > self.octet_count {
+            return Err(BlobError::TruncateBeyondSize(octet_count, self.octet_count));
+        }
+
+        let chunk_count = if self.chunk_offsets.is_empty() {
+            octet_count.div_ceil(self.fixed_chunk_size)
+        } else {
+            self.chunk_offsets[..self.chunk_count()].partition_point(|&offset| offset < octet_count)
+        };
+        for chunk_index in (0..self.chunk_count()).filter(|&index| self.bit_array.get(index)) {
+            let chunk_end = self.chunk_offset(chunk_index) + self.chunk_size(chunk_index);
+            if chunk_end > octet_count
+                || (chunk_index + 1 == chunk_count && chunk_end != octet_count)
+            {
+                return Err(BlobError::ReceivedChunkBeyondSize(chunk_index, octet_count));
+            }
+        }
+
+        let mut bit_array = BitArray::new(chunk_count);
+        for chunk_index in (0..chunk_count).filter(|&index| self.bit_array.get(index)) {
+            bit_array.set(chunk_index);
+        }
+        self.bit_array = bit_array;
+        if !self.chunk_offsets.is_empty() {
+            self.chunk_offsets.truncate(chunk_count);
+            self.chunk_offsets.push(octet_count);
+        }
+        self.blob.truncate(octet_count);
+        self.octet_count = octet_count;
+        #[cfg(feature = "sha256")]
+        {
+            self.prefix_hasher = PrefixHasher::default();
+        }
+        self.hash_contiguous_prefix();
+
+        Ok(())
+    }
+
+    /// Prepares the stream for a new transfer, reusing the allocation of the blob, e.g. for a
+    /// pooled receiver that would otherwise allocate a new buffer for every transfer.
+    ///
+    /// The blob is zeroed, no chunks are received and the wasted bytes start over. The
+    /// duplicate mode from [`Self::with_ignore_duplicates`] is kept, while chunk sizes from
+    /// [`Self::with_chunk_sizes`] are replaced by `fixed_chunk_size`.
+    ///
+    /// # Parameters
+    /// - `octet_count`: The total number of octets (bytes) in the new transfer.
+    /// - `fixed_chunk_size`: The size of each chunk in the new transfer.
+    ///
+    /// # Panics
+    /// Will panic if `fixed_chunk_size` is zero.
+    pub fn reset(&mut self, octet_count: usize, fixed_chunk_size: usize) {
+        assert!(
+            fixed_chunk_size > 0,
+            "fixed_chunk_size must be greater than zero"
+        );
+
+        self.blob.clear();
+        self.blob.resize(octet_count, 0);
+        self.bit_array = BitArray::new(octet_count.div_ceil(fixed_chunk_size));
+        self.fixed_chunk_size = fixed_chunk_size;
+        self.octet_count = octet_count;
+        self.chunk_offsets.clear();
+        self.wasted_octet_count = 0;
+        #[cfg(feature = "sha256")]
+        {
+            self.prefix_hasher = PrefixHasher::default();
+        }
+    }
+
+    /// Moves the complete blob out of the stream, without copying it.
+    ///
+    /// # Returns
+    /// An `Option` containing the blob if all chunks have been received; otherwise, `None`.
+    #[must_use]
+    pub fn take_blob(self) -> Option<Vec<u8>> {
+        self.is_complete().then_some(self.blob)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write + io::Seek> BlobStreamIn<WriterStorage<W>> {
+    /// Creates a new `BlobStreamIn` instance that writes each received chunk to `writer` at
+    /// the offset of the chunk, instead of keeping the blob in memory, e.g. for a blob that is
+    /// too large for RAM.
+    ///
+    /// Which chunks have been received is still tracked, so [`Self::is_complete`] and the
+    /// missing chunks work as usual, but [`Self::blob`] returns `None`.
+    ///
+    /// # Parameters
+    /// - `octet_count`: The total number of octets (bytes) in the stream.
+    /// - `fixed_chunk_size`: The size of each chunk in the stream.
+    /// - `writer`: The sink for the received octets.
+    ///
+    /// # Panics
+    /// Will panic if `fixed_chunk_size` is zero.
+    ///
+    /// # Returns
+    /// A new `BlobStreamIn` instance.
+    #[must_use]
+    pub fn with_writer(octet_count: usize, fixed_chunk_size: usize, writer: W) -> Self {
+        Self::with_storage(WriterStorage::new(writer, octet_count), fixed_chunk_size)
+    }
+}
+
+impl<S: BlobStorage> BlobStreamIn<S> {
+    /// Creates a new `BlobStreamIn` instance that writes the received chunks into `storage`.
+    ///
+    /// The total number of octets in the stream is the length of the storage.
+    ///
+    /// # Parameters
+    /// - `storage`: The storage to write the received octets into.
+    /// - `fixed_chunk_size`: The size of each chunk in the stream.
+    ///
+    /// # Panics
+    /// Will panic if `fixed_chunk_size` is zero.
+    ///
+    /// # Returns
+    /// A new `BlobStreamIn` instance.
+    #[must_use]
+    pub fn with_storage(storage: S, fixed_chunk_size: usize) -> Self {
         assert!(
             fixed_chunk_size > 0,
             "fixed_chunk_size must be greater than zero"
         );
 
+        let octet_count = storage.octet_count();
         let chunk_count = octet_count.div_ceil(fixed_chunk_size);
         Self {
             bit_array: BitArray::new(chunk_count),
             fixed_chunk_size,
             octet_count,
-            blob: vec![0u8; octet_count],
+            chunk_offsets: Vec::new(),
+            blob: storage,
+            wasted_octet_count: 0,
+            ignore_duplicates: false,
+            #[cfg(feature = "sha256")]
+            prefix_hasher: PrefixHasher::default(),
         }
     }
 
+    /// Sets if a chunk that is received again with the same contents is silently accepted.
+    ///
+    /// Resent chunks are expected on a lossy network, so with this enabled [`Self::set_chunk`]
+    /// returns `Ok(())` for them instead of `BlobError::RedundantSameContents`. A resent chunk
+    /// with different contents is still an error, and resent chunks are still counted in
+    /// [`Self::wasted_bytes`].
+    ///
+    /// # Parameters
+    /// - `ignore_duplicates`: `true` to accept identical resent chunks.
+    ///
+    /// # Returns
+    /// The `BlobStreamIn` with the mode applied.
+    #[must_use]
+    pub const fn with_ignore_duplicates(mut self, ignore_duplicates: bool) -> Self {
+        self.ignore_duplicates = ignore_duplicates;
+        self
+    }
+
+    /// Returns a reference to the storage that the chunks are written into.
+    #[must_use]
+    pub const fn storage(&self) -> &S {
+        &self.blob
+    }
+
     /// Returns the total number of expected chunks.
     ///
     /// This function provides the total count of chunks that are expected
@@ -58,22 +446,252 @@ impl BlobStreamIn {
         self.bit_array.bit_count()
     }
 
+    /// Returns the expected size of the chunk at `chunk_index`.
+    ///
+    /// All chunks are `fixed_chunk_size` octets, except for the last chunk which holds the
+    /// remaining octets. When `octet_count` is an exact multiple of `fixed_chunk_size`, the
+    /// last chunk is a full chunk as well. Chunks of different sizes have the size that was
+    /// given to [`BlobStreamIn::with_chunk_sizes`].
+    pub(crate) fn chunk_size(&self, chunk_index: ChunkIndex) -> usize {
+        if !self.chunk_offsets.is_empty() {
+            return self.chunk_offsets[chunk_index + 1] - self.chunk_offsets[chunk_index];
+        }
+        let octet_offset = chunk_index * self.fixed_chunk_size;
+        self.fixed_chunk_size
+            .min(self.octet_count.saturating_sub(octet_offset))
+    }
+
+    /// Returns the octet offset of the chunk at `chunk_index` in the blob.
+    fn chunk_offset(&self, chunk_index: ChunkIndex) -> usize {
+        self.chunk_offsets
+            .get(chunk_index)
+            .copied()
+            .unwrap_or(chunk_index * self.fixed_chunk_size)
+    }
+
+    /// Returns the number of chunks that have been received so far.
+    #[must_use]
+    pub fn received_chunk_count(&self) -> usize {
+        self.bit_array.count_set_bits()
+    }
+
+    /// Returns the total number of payload octets received for chunks that had already been
+    /// received, regardless if the contents were the same or not.
+    #[must_use]
+    pub const fn wasted_bytes(&self) -> usize {
+        self.wasted_octet_count
+    }
+
+    /// Returns how far along the transfer is, as the ratio of received chunks to the
+    /// total number of chunks.
+    ///
+    /// # Returns
+    /// A value between `0.0` and `1.0`. A stream without any chunks is considered done
+    /// and returns `1.0`.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn progress(&self) -> f32 {
+        let chunk_count = self.chunk_count();
+        if chunk_count == 0 {
+            return 1.0;
+        }
+        self.received_chunk_count() as f32 / chunk_count as f32
+    }
+
+    /// Returns the number of octets that have been received in sequence from the start of the blob.
+    ///
+    /// This is the length of the prefix up to the first chunk that has not been received.
+    #[must_use]
+    pub fn contiguous_octet_count(&self) -> usize {
+        self.bit_array
+            .first_unset_bit()
+            .map_or(self.octet_count, |index| self.chunk_offset(index))
+    }
+
+    /// Returns the octets that have been received in sequence from the start of the blob, e.g.
+    /// to start decoding a blob before all of it has arrived.
+    ///
+    /// The prefix ends at the first chunk that has not been received, and is empty if the
+    /// first chunk is missing or if the storage is not [readable](BlobStorage::is_readable).
+    #[must_use]
+    pub fn contiguous_prefix(&self) -> &[u8] {
+        if !self.blob.is_readable() {
+            return &[];
+        }
+        &self.blob.octets()[..self.contiguous_octet_count()]
+    }
+
     /// Checks if all chunks have been received.
     ///
+    /// A stream of zero octets has no chunks and is complete from the start.
+    ///
     /// # Returns
     /// `true` if all chunks have been received; `false` otherwise.
     #[must_use]
     pub const fn is_complete(&self) -> bool {
-        self.bit_array.all_set()
+        self.chunk_count() == 0 || self.bit_array.all_set()
+    }
+
+    /// Verifies that a complete blob adds up to the declared octet count.
+    ///
+    /// The sizes of all received chunks are summed and compared with the octet count, as well
+    /// as with the length of the storage. This catches boundary bugs in the chunk size
+    /// calculation that would otherwise result in a truncated or padded blob.
+    ///
+    /// # Errors
+    /// Returns `BlobError::InconsistentOctetCount` with the expected and the found octet count
+    /// if the invariant does not hold. An incomplete blob is not checked and returns `Ok(())`.
+    pub fn assert_complete_consistency(&self) -> Result<(), BlobError> {
+        if !self.is_complete() {
+            return Ok(());
+        }
+
+        let received_octet_count: usize = (0..self.chunk_count())
+            .filter(|&index| self.bit_array.get(index))
+            .map(|index| self.chunk_size(index))
+            .sum();
+        if received_octet_count != self.octet_count {
+            return Err(BlobError::InconsistentOctetCount(
+                self.octet_count,
+                received_octet_count,
+            ));
+        }
+
+        let storage_octet_count = self.blob.octet_count();
+        if storage_octet_count != self.octet_count {
+            return Err(BlobError::InconsistentOctetCount(
+                self.octet_count,
+                storage_octet_count,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns an iterator over the indices of the chunks that have not been received yet.
+    ///
+    /// The indices are yielded in ascending order, and nothing is allocated, so it can be
+    /// used at any point during a transfer, e.g. to request retransmission of missing chunks.
+    pub fn missing_chunks(&self) -> impl Iterator<Item = ChunkIndex> + '_ {
+        (0..self.bit_array.bit_count()).filter(move |&index| !self.bit_array.get(index))
+    }
+
+    /// Returns the octet ranges of the blob that have been received, with consecutive received
+    /// chunks merged into a single range, e.g. to show the progress of a resumable download.
+    ///
+    /// # Returns
+    /// The ranges in ascending order. The range of a received last chunk ends at the octet
+    /// count, also if the last chunk is shorter than the others.
+    #[must_use]
+    pub fn received_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for chunk_index in (0..self.chunk_count()).filter(|&index| self.bit_array.get(index)) {
+            let start = self.chunk_offset(chunk_index);
+            let end = start + self.chunk_size(chunk_index);
+            match ranges.last_mut() {
+                Some(range) if range.end == start => range.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        ranges
+    }
+
+    /// Returns the index of the first chunk that has not been received yet, or `None` if the
+    /// stream is complete.
+    #[must_use]
+    pub fn first_missing(&self) -> Option<ChunkIndex> {
+        self.bit_array.first_unset_bit()
+    }
+
+    /// Calls `f` with the index of each chunk that has not been received yet, in ascending order.
+    ///
+    /// This is the same as iterating over [`Self::missing_chunks`], for callers that prefer a
+    /// callback, e.g. to fill in their own acknowledgement format.
+    pub fn for_each_missing(&self, f: impl FnMut(ChunkIndex)) {
+        self.missing_chunks().for_each(f);
+    }
+
+    /// Returns the octets of a single received chunk.
+    ///
+    /// # Parameters
+    /// - `chunk_index`: The index of the chunk to read.
+    ///
+    /// # Errors
+    /// Returns `BlobError::InvalidChunkIndex` if the `chunk_index` is out of range.
+    ///
+    /// # Returns
+    /// `Ok(Some(...))` with the octets of the chunk, with the correct size for the last chunk,
+    /// or `Ok(None)` if the chunk has not been received yet or the storage is not
+    /// [readable](BlobStorage::is_readable).
+    pub fn get_chunk(&self, chunk_index: ChunkIndex) -> Result<Option<&[u8]>, BlobError> {
+        let chunk_count = self.chunk_count();
+        if chunk_index >= chunk_count {
+            return Err(BlobError::InvalidChunkIndex(chunk_index, chunk_count));
+        }
+
+        if !self.bit_array.get(chunk_index) || !self.blob.is_readable() {
+            return Ok(None);
+        }
+
+        let octet_offset = self.chunk_offset(chunk_index);
+        let size = self.chunk_size(chunk_index);
+        Ok(Some(&self.blob.octets()[octet_offset..octet_offset + size]))
     }
 
     /// Returns a reference to the complete blob if all chunks have been received.
     ///
     /// # Returns
-    /// An `Option` containing a reference to the blob if complete; otherwise, `None`.
+    /// An `Option` containing a reference to the blob if complete; otherwise, `None`. A stream
+    /// of zero octets returns an empty blob. A storage that is not
+    /// [readable](BlobStorage::is_readable) always returns `None`.
     #[must_use]
     pub fn blob(&self) -> Option<&[u8]> {
-        self.is_complete().then(|| &self.blob[..])
+        (self.is_complete() && self.blob.is_readable()).then(|| self.blob.octets())
+    }
+
+    /// Returns the SHA-256 digest of the complete blob, e.g. to compare it with the
+    /// `blob_hash` from `StartTransfer`.
+    ///
+    /// The contiguous prefix is hashed as it grows, also when chunks arrive out of order, so
+    /// no pass over the whole blob is needed at completion.
+    ///
+    /// # Returns
+    /// The digest if all chunks have been received; otherwise, `None`. A storage that is not
+    /// [readable](BlobStorage::is_readable) always returns `None`.
+    #[cfg(feature = "sha256")]
+    #[must_use]
+    pub fn blob_hash(&self) -> Option<[u8; 32]> {
+        self.blob()
+            .and_then(|_| self.prefix_hasher.digest().try_into().ok())
+    }
+
+    /// Returns a reader over the complete blob, e.g. to decode it with an API that takes an
+    /// [`io::Read`].
+    ///
+    /// # Returns
+    /// A [`BlobReader`] positioned at the start of the blob if complete; otherwise, `None`.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn reader(&self) -> Option<BlobReader<'_>> {
+        self.blob().map(|blob| BlobReader { blob, position: 0 })
+    }
+
+    /// Returns an iterator over the payloads of all chunks of the complete blob.
+    ///
+    /// The slices borrow from the storage, so nothing is copied or allocated. The last
+    /// slice has the remaining size if the blob is not an exact multiple of the chunk size.
+    /// If the blob is not complete yet, the iterator yields nothing.
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        let blob = self.blob().unwrap_or_default();
+        let chunk_count = if blob.is_empty() {
+            0
+        } else {
+            self.chunk_count()
+        };
+        (0..chunk_count).map(move |chunk_index| {
+            let octet_offset = self.chunk_offset(chunk_index);
+            &blob[octet_offset..octet_offset + self.chunk_size(chunk_index)]
+        })
     }
 
     /// Sets a chunk of data at the specified `chunk_index` with the provided `payload`.
@@ -86,26 +704,87 @@ impl BlobStreamIn {
     /// Returns a `BlobError` if:
     /// - The `chunk_index` is invalid.
     /// - The `payload` size does not match the expected size for the chunk.
-    /// - The chunk has already been set, with either the same or different contents.
+    /// - The chunk does not fit in the storage, `BlobError::PayloadExceedsBlob`, e.g. if the
+    ///   storage has shrunk.
+    /// - The chunk has already been set, with either the same or different contents. The same
+    ///   contents are accepted if enabled with [`Self::with_ignore_duplicates`].
+    /// - The storage could not be written, e.g. `BlobError::Io` for a `WriterStorage`.
     ///
     /// # Returns
     /// `Ok(())` if the chunk was set successfully; otherwise, a `BlobError`.
     pub fn set_chunk(&mut self, chunk_index: ChunkIndex, payload: &[u8]) -> Result<(), BlobError> {
+        let result = self.write_chunk(chunk_index, payload);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            name: "set_chunk",
+            chunk_index,
+            octet_count = payload.len(),
+            outcome = result.as_ref().map_or_else(BlobError::outcome, |()| "accepted"),
+        );
+        result
+    }
+
+    fn write_chunk(&mut self, chunk_index: ChunkIndex, payload: &[u8]) -> Result<(), BlobError> {
+        let octet_offset = self.chunk_octet_offset(chunk_index, payload)?;
+
+        if self.bit_array.get(chunk_index) {
+            let err = self.redundant_chunk(chunk_index, payload);
+            return self.accept_duplicate(err);
+        }
+
+        self.blob.write_at(octet_offset, payload)?;
+
+        self.bit_array.set(chunk_index);
+        self.hash_contiguous_prefix();
+
+        Ok(())
+    }
+
+    /// Checks if [`Self::set_chunk`] would accept `payload` for the chunk at `chunk_index`,
+    /// without changing the stream.
+    ///
+    /// # Parameters
+    /// - `chunk_index`: The index of the chunk to check.
+    /// - `payload`: A slice of octets representing the chunk's data.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`Self::set_chunk`]. A redundant chunk is not counted in
+    /// [`Self::wasted_bytes`].
+    pub fn check_chunk(&self, chunk_index: ChunkIndex, payload: &[u8]) -> Result<(), BlobError> {
+        self.chunk_octet_offset(chunk_index, payload)?;
+
+        if self.bit_array.get(chunk_index) {
+            return self.accept_duplicate(self.redundant_chunk_error(chunk_index, payload));
+        }
+
+        Ok(())
+    }
+
+    /// Turns the error for a redundant chunk into `Ok(())` if it has the same contents and
+    /// duplicates are ignored.
+    ///
+    /// It is not `const`, since `BlobError::Io` can not be dropped in a const fn.
+    #[allow(clippy::missing_const_for_fn)]
+    fn accept_duplicate(&self, err: BlobError) -> Result<(), BlobError> {
+        match err {
+            BlobError::RedundantSameContents(_) if self.ignore_duplicates => Ok(()),
+            _ => Err(err),
+        }
+    }
+
+    /// Returns the octet offset of the chunk at `chunk_index`, after checking that the index is
+    /// valid and that `payload` has the expected size for the chunk.
+    fn chunk_octet_offset(
+        &self,
+        chunk_index: ChunkIndex,
+        payload: &[u8],
+    ) -> Result<usize, BlobError> {
         let chunk_count = self.bit_array.bit_count();
         if chunk_index >= chunk_count {
             return Err(BlobError::InvalidChunkIndex(chunk_index, chunk_count));
         }
 
-        let expected_size = if chunk_index == chunk_count - 1 {
-            // It was the last chunk
-            if self.octet_count % self.fixed_chunk_size == 0 {
-                self.fixed_chunk_size
-            } else {
-                self.octet_count % self.fixed_chunk_size
-            }
-        } else {
-            self.fixed_chunk_size
-        };
+        let expected_size = self.chunk_size(chunk_index);
 
         if payload.len() != expected_size {
             return Err(BlobError::UnexpectedChunkSize(
@@ -114,29 +793,156 @@ impl BlobStreamIn {
                 chunk_index,
             ));
         }
-        let octet_offset = chunk_index * self.fixed_chunk_size;
-        if octet_offset + expected_size > self.blob.len() {
-            return Err(BlobError::OutOfBounds);
+        let octet_offset = self.chunk_offset(chunk_index);
+        let end = octet_offset + expected_size;
+        let blob_len = self.blob.octet_count();
+        if end > blob_len {
+            return Err(BlobError::PayloadExceedsBlob {
+                chunk_index,
+                end,
+                blob_len,
+            });
         }
 
-        if self.bit_array.get(chunk_index) {
-            // It has been set previously
-            let is_same_contents =
-                &self.blob[octet_offset..octet_offset + expected_size] == payload;
+        Ok(octet_offset)
+    }
 
-            let err = if is_same_contents {
-                BlobError::RedundantSameContents(chunk_index)
-            } else {
-                BlobError::RedundantContentDiffers(chunk_index)
-            };
+    /// Sets multiple consecutive chunks from a single contiguous buffer.
+    ///
+    /// This is more efficient than calling [`Self::set_chunk`] for each chunk when a larger
+    /// region of the blob has been received in one piece, e.g. from a stream socket.
+    ///
+    /// # Parameters
+    /// - `start_chunk`: The index of the first chunk in `data`.
+    /// - `data`: The octets of the chunks. It must end on a chunk boundary, which is either
+    ///   a multiple of the chunk size or the end of the blob for a shorter last chunk.
+    ///
+    /// # Errors
+    /// Returns a `BlobError` if:
+    /// - The `start_chunk` is invalid.
+    /// - The `data` extends past the end of the blob, as `BlobError::PayloadExceedsBlob`.
+    /// - The `data` does not end on a chunk boundary.
+    /// - Any of the chunks has already been set. Nothing is written in that case.
+    ///
+    /// # Returns
+    /// The number of chunks that were set.
+    pub fn set_contiguous(
+        &mut self,
+        start_chunk: ChunkIndex,
+        data: &[u8],
+    ) -> Result<usize, BlobError> {
+        let chunk_count = self.bit_array.bit_count();
+        if start_chunk >= chunk_count {
+            return Err(BlobError::InvalidChunkIndex(start_chunk, chunk_count));
+        }
 
-            return Err(err);
+        let octet_offset = self.chunk_offset(start_chunk);
+        let end_octet_offset = octet_offset + data.len();
+        if end_octet_offset > self.octet_count {
+            return Err(BlobError::PayloadExceedsBlob {
+                chunk_index: start_chunk,
+                end: end_octet_offset,
+                blob_len: self.octet_count,
+            });
         }
 
-        self.blob[octet_offset..octet_offset + expected_size].copy_from_slice(payload);
+        let covered_chunk_count = (start_chunk..chunk_count)
+            .take_while(|&index| self.chunk_offset(index) < end_octet_offset)
+            .count();
+        if covered_chunk_count > 0 {
+            // All chunks but the last are covered completely, so only the last one needs to be
+            // checked.
+            let last_chunk_index = start_chunk + covered_chunk_count - 1;
+            let last_chunk_size = end_octet_offset - self.chunk_offset(last_chunk_index);
+            let expected_last_chunk_size = self.chunk_size(last_chunk_index);
+            if last_chunk_size != expected_last_chunk_size {
+                return Err(BlobError::UnexpectedChunkSize(
+                    expected_last_chunk_size,
+                    last_chunk_size,
+                    last_chunk_index,
+                ));
+            }
+        }
 
-        self.bit_array.set(chunk_index);
+        let chunk_indices = start_chunk..start_chunk + covered_chunk_count;
+        if let Some(chunk_index) = chunk_indices
+            .clone()
+            .find(|&index| self.bit_array.get(index))
+        {
+            let relative_offset = self.chunk_offset(chunk_index) - octet_offset;
+            let payload = &data[relative_offset..relative_offset + self.chunk_size(chunk_index)];
+            return Err(self.redundant_chunk(chunk_index, payload));
+        }
 
-        Ok(())
+        self.blob.write_at(octet_offset, data)?;
+        for chunk_index in chunk_indices {
+            self.bit_array.set(chunk_index);
+        }
+        self.hash_contiguous_prefix();
+
+        Ok(covered_chunk_count)
+    }
+
+    /// Feeds the octets that have been added to the contiguous prefix to the prefix hasher.
+    /// Does nothing without the `sha256` feature.
+    #[cfg_attr(
+        not(feature = "sha256"),
+        allow(
+            clippy::missing_const_for_fn,
+            clippy::needless_pass_by_ref_mut,
+            clippy::unused_self
+        )
+    )]
+    fn hash_contiguous_prefix(&mut self) {
+        #[cfg(feature = "sha256")]
+        if self.blob.is_readable() {
+            let prefix = &self.blob.octets()[..self.contiguous_octet_count()];
+            self.prefix_hasher.update(prefix);
+        }
+    }
+
+    /// Counts the `payload` of an already received chunk as wasted, and creates the error
+    /// describing whether the contents differ from what was received before.
+    fn redundant_chunk(&mut self, chunk_index: ChunkIndex, payload: &[u8]) -> BlobError {
+        self.wasted_octet_count += payload.len();
+        self.redundant_chunk_error(chunk_index, payload)
+    }
+
+    /// Creates the error describing whether the `payload` of an already received chunk
+    /// differs from what was received before. If the storage is not
+    /// [readable](BlobStorage::is_readable), the contents are assumed to be the same.
+    fn redundant_chunk_error(&self, chunk_index: ChunkIndex, payload: &[u8]) -> BlobError {
+        let octet_offset = self.chunk_offset(chunk_index);
+        let is_same_contents = !self.blob.is_readable()
+            || &self.blob.octets()[octet_offset..octet_offset + payload.len()] == payload;
+
+        if is_same_contents {
+            BlobError::RedundantSameContents(chunk_index)
+        } else {
+            BlobError::RedundantContentDiffers(chunk_index)
+        }
+    }
+
+    /// Sets a chunk of data at the specified `chunk_index` from a reference counted buffer.
+    ///
+    /// The octets are copied straight from `payload` into the storage, the same way as for
+    /// [`Self::set_chunk`], so no intermediate `Vec<u8>` is allocated for the payload.
+    /// Together with [`crate::protocol::SetChunkBytesData`] a received datagram can be decoded
+    /// and applied without any extra allocations.
+    ///
+    /// # Parameters
+    /// - `chunk_index`: The index of the chunk to set.
+    /// - `payload`: The chunk's data.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`Self::set_chunk`].
+    #[cfg(feature = "bytes")]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn set_chunk_bytes(
+        &mut self,
+        chunk_index: ChunkIndex,
+        payload: bytes::Bytes,
+    ) -> Result<(), BlobError> {
+        self.set_chunk(chunk_index, &payload)
     }
 }
@@ -3,12 +3,54 @@
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod codec;
+#[cfg(feature = "std")]
+pub mod datagram;
 pub mod err;
+#[cfg(feature = "tokio")]
+pub mod framed;
+pub mod hash;
+#[cfg(feature = "std")]
 pub mod in_logic;
+#[cfg(feature = "std")]
 pub mod in_logic_front;
+pub mod in_range_stream;
 pub mod in_stream;
+pub mod millis;
+#[cfg(feature = "std")]
+pub mod out_logic;
+#[cfg(feature = "std")]
+pub mod out_logic_front;
+pub mod out_stream;
 pub mod prelude;
+#[cfg(feature = "std")]
 pub mod protocol;
+#[cfg(feature = "std")]
 pub mod protocol_front;
+#[cfg(feature = "std")]
+pub mod received_set;
+pub mod storage;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+pub mod window;
 
 type ChunkIndex = usize;
+
+/// Converts a chunk index from the wire format. An index that does not fit in a `ChunkIndex`
+/// becomes `ChunkIndex::MAX`, which is past the end of any blob, so it is rejected like any
+/// other index that is out of range.
+#[cfg(feature = "std")]
+fn chunk_index_from_wire(chunk_index: u64) -> ChunkIndex {
+    ChunkIndex::try_from(chunk_index).unwrap_or(ChunkIndex::MAX)
+}
+
+/// Converts a chunk index to the wire format, which is lossless since `usize` is at most 64 bits.
+#[cfg(feature = "std")]
+const fn chunk_index_to_wire(chunk_index: ChunkIndex) -> u64 {
+    chunk_index as u64
+}
@@ -0,0 +1,59 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! The timestamps of the clock that the caller supplies to the senders and receivers.
+use core::ops::{Add, AddAssign};
+use core::time::Duration;
+
+/// A point in time in milliseconds, read from a monotonic clock that is supplied by the
+/// caller, e.g. the milliseconds since the application started.
+///
+/// Only the difference between two timestamps is used, so the clock may start anywhere, and a
+/// test can start it at zero and advance it by hand.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Millis(u64);
+
+impl Millis {
+    /// Creates a timestamp from a number of milliseconds.
+    #[must_use]
+    pub const fn new(millis: u64) -> Self {
+        Self(millis)
+    }
+
+    /// Returns the number of milliseconds.
+    #[must_use]
+    pub const fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the time that has passed from `earlier` to `self`, or zero if `earlier` is later.
+    #[must_use]
+    pub const fn saturating_duration_since(self, earlier: Self) -> Duration {
+        Duration::from_millis(self.0.saturating_sub(earlier.0))
+    }
+}
+
+impl From<u64> for Millis {
+    fn from(millis: u64) -> Self {
+        Self(millis)
+    }
+}
+
+/// Advances the timestamp by the whole milliseconds of the duration, saturating at the end of
+/// the clock.
+impl Add<Duration> for Millis {
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self {
+        let millis = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        Self(self.0.saturating_add(millis))
+    }
+}
+
+impl AddAssign<Duration> for Millis {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
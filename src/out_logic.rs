@@ -0,0 +1,740 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::codec::ChunkCodec;
+use crate::datagram::DatagramBatcher;
+use crate::err::BlobError;
+use crate::hash::BlobHasher;
+use crate::in_stream::check_min_chunk_size;
+use crate::millis::Millis;
+use crate::protocol::{
+    AckChunkCreditData, AckChunkData, AckChunkMaskData, RequestChunksData, SetChunkData,
+};
+use crate::protocol_front::SetChunkFrontData;
+use crate::received_set;
+use crate::{chunk_index_from_wire, chunk_index_to_wire, ChunkIndex};
+use bit_array_rs::BitArray;
+use std::io;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// The default time to wait for an acknowledgement before a chunk is sent again.
+pub const DEFAULT_RESEND_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The order in which the chunks that are due for sending are picked.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ResendOrder {
+    /// Always pick the lowest chunk indices first, which advances the receiver's
+    /// `waiting_for_chunk_index` as quickly as possible.
+    #[default]
+    LowestIndexFirst,
+    /// Continue after the chunk that was sent last, wrapping around at the end, which spreads
+    /// the recovery evenly over the blob.
+    RoundRobin,
+}
+
+/// `Logic` handles the sending side of a blob transfer.
+///
+/// It splits the blob into fixed-size chunks, produces the chunks that have not been
+/// acknowledged yet and keeps track of the acknowledgements from the receiver.
+#[derive(Debug)]
+pub struct Logic {
+    blob: Vec<u8>,
+    octet_count: usize,
+    fixed_chunk_size: usize,
+    acked: BitArray,
+    is_paused: bool,
+    resend_order: ResendOrder,
+    next_chunk_index: ChunkIndex,
+    resend_interval: Duration,
+    last_sent: Vec<Option<Millis>>,
+    window_size: usize,
+    credit: Option<usize>,
+    datagram_batcher: Option<DatagramBatcher>,
+    chunk_codec: ChunkCodec,
+    requested_chunks: Vec<ChunkIndex>,
+    max_bytes_per_tick: usize,
+    bytes_sent_this_tick: usize,
+}
+
+impl Logic {
+    /// Creates a new `Logic` instance for sending `blob` in chunks of `fixed_chunk_size` octets.
+    ///
+    /// # Arguments
+    ///
+    /// * `blob` - The octets to send.
+    /// * `fixed_chunk_size` - The size of each chunk. The last chunk holds the remaining octets.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `fixed_chunk_size` is zero.
+    ///
+    /// # Returns
+    ///
+    /// A new `Logic` instance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use blob_stream::out_logic::Logic;
+    /// let out_logic = Logic::new(vec![0u8; 1024], 64);
+    /// ```
+    #[must_use]
+    pub fn new(blob: Vec<u8>, fixed_chunk_size: usize) -> Self {
+        assert!(
+            fixed_chunk_size > 0,
+            "fixed_chunk_size must be greater than zero"
+        );
+
+        let octet_count = blob.len();
+        let chunk_count = octet_count.div_ceil(fixed_chunk_size);
+        Self {
+            blob,
+            octet_count,
+            fixed_chunk_size,
+            acked: BitArray::new(chunk_count),
+            is_paused: false,
+            resend_order: ResendOrder::LowestIndexFirst,
+            next_chunk_index: 0,
+            resend_interval: DEFAULT_RESEND_INTERVAL,
+            last_sent: vec![None; chunk_count],
+            window_size: usize::MAX,
+            credit: None,
+            datagram_batcher: None,
+            chunk_codec: ChunkCodec::Stored,
+            requested_chunks: Vec::new(),
+            max_bytes_per_tick: usize::MAX,
+            bytes_sent_this_tick: 0,
+        }
+    }
+
+    /// Creates a new `Logic` instance like [`Self::new`], but rejects a `fixed_chunk_size` below
+    /// `min_chunk_size`, which would make the per-chunk overhead dominate the transfer.
+    ///
+    /// # Arguments
+    ///
+    /// * `blob` - The octets to send.
+    /// * `fixed_chunk_size` - The size of each chunk. The last chunk holds the remaining octets.
+    /// * `min_chunk_size` - The smallest allowed chunk size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::ChunkSizeTooSmall` if `fixed_chunk_size` is below `min_chunk_size`,
+    /// unless the whole blob fits in a single chunk.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `fixed_chunk_size` is zero.
+    pub fn try_new(
+        blob: Vec<u8>,
+        fixed_chunk_size: usize,
+        min_chunk_size: usize,
+    ) -> Result<Self, BlobError> {
+        check_min_chunk_size(blob.len(), fixed_chunk_size, min_chunk_size)?;
+        Ok(Self::new(blob, fixed_chunk_size))
+    }
+
+    /// Limits the number of chunks that have been sent but not acknowledged yet.
+    ///
+    /// A suitable size can be calculated from the link with [`crate::window::window_for_bdp`].
+    ///
+    /// # Arguments
+    ///
+    /// * `window_size` - The maximum number of unacknowledged chunks in flight.
+    ///
+    /// # Returns
+    ///
+    /// The `Logic` with the window size applied.
+    #[must_use]
+    pub const fn with_window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// Sets how long to wait for an acknowledgement before a chunk is sent again.
+    ///
+    /// # Arguments
+    ///
+    /// * `resend_interval` - The minimum time between two sends of the same chunk.
+    ///
+    /// # Returns
+    ///
+    /// The `Logic` with the interval applied.
+    #[must_use]
+    pub const fn with_resend_interval(mut self, resend_interval: Duration) -> Self {
+        self.resend_interval = resend_interval;
+        self
+    }
+
+    /// Sets how the chunks are grouped into datagrams, which is used by
+    /// [`Self::planned_datagram_count`].
+    ///
+    /// # Arguments
+    ///
+    /// * `datagram_batcher` - The batcher that the chunks from [`Self::send`] are grouped with.
+    ///
+    /// # Returns
+    ///
+    /// The `Logic` with the batcher applied.
+    #[must_use]
+    pub const fn with_datagram_batcher(mut self, datagram_batcher: DatagramBatcher) -> Self {
+        self.datagram_batcher = Some(datagram_batcher);
+        self
+    }
+
+    /// Sets the codec that the chunk payloads are encoded with.
+    ///
+    /// Each chunk falls back to being stored as is if the codec would not make it smaller.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk_codec` - The preferred codec for the chunks.
+    ///
+    /// # Returns
+    ///
+    /// The `Logic` with the codec applied.
+    #[must_use]
+    pub const fn with_chunk_codec(mut self, chunk_codec: ChunkCodec) -> Self {
+        self.chunk_codec = chunk_codec;
+        self
+    }
+
+    /// Sets the order in which the chunks that are due for sending are picked.
+    ///
+    /// # Arguments
+    ///
+    /// * `resend_order` - Lowest index first, or round-robin over the whole blob.
+    ///
+    /// # Returns
+    ///
+    /// The `Logic` with the order applied.
+    #[must_use]
+    pub const fn with_resend_order(mut self, resend_order: ResendOrder) -> Self {
+        self.resend_order = resend_order;
+        self
+    }
+
+    /// Sets the maximum number of chunk octets that [`Self::send`] and [`Self::tick`] produce
+    /// until the next [`Self::reset_tick`], e.g. to respect a byte budget per network tick.
+    ///
+    /// The payload octets of a chunk are counted after it is encoded with the chunk codec. A
+    /// chunk that is larger than the whole budget is only produced at the start of a tick, and
+    /// the octets beyond the budget are carried over to the next ticks. A budget below the
+    /// chunk size then slows the transfer down instead of stalling it, without exceeding the
+    /// budget over time.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_bytes_per_tick` - The byte budget of each tick.
+    ///
+    /// # Returns
+    ///
+    /// The `Logic` with the budget applied.
+    #[must_use]
+    pub const fn with_max_bytes_per_tick(mut self, max_bytes_per_tick: usize) -> Self {
+        self.max_bytes_per_tick = max_bytes_per_tick;
+        self
+    }
+
+    /// Returns the number of chunk payload octets that count against the byte budget of this
+    /// tick: the octets produced since the last [`Self::reset_tick`] and the octets beyond the
+    /// budget that were carried over from earlier ticks.
+    #[must_use]
+    pub const fn bytes_sent_this_tick(&self) -> usize {
+        self.bytes_sent_this_tick
+    }
+
+    /// Starts a new tick, which makes the byte budget from [`Self::with_max_bytes_per_tick`]
+    /// available again, less the octets that the previous tick went beyond the budget.
+    pub const fn reset_tick(&mut self) {
+        self.bytes_sent_this_tick = self
+            .bytes_sent_this_tick
+            .saturating_sub(self.max_bytes_per_tick);
+    }
+
+    /// Returns the total number of chunks in the blob.
+    #[must_use]
+    pub const fn chunk_count(&self) -> usize {
+        self.acked.bit_count()
+    }
+
+    /// Returns the number of chunks that the receiver has acknowledged.
+    #[must_use]
+    pub fn acked_chunk_count(&self) -> usize {
+        self.acked.count_set_bits()
+    }
+
+    /// Returns the number of chunks that have been sent but not acknowledged yet.
+    #[must_use]
+    pub fn in_flight_count(&self) -> usize {
+        (0..self.chunk_count())
+            .filter(|&chunk_index| self.is_in_flight(chunk_index))
+            .count()
+    }
+
+    /// Returns the number of blob octets in the chunks that the receiver has acknowledged.
+    #[must_use]
+    pub fn acked_octet_count(&self) -> usize {
+        (0..self.chunk_count())
+            .filter(|&chunk_index| self.acked.get(chunk_index))
+            .map(|chunk_index| self.chunk_octet_size(chunk_index))
+            .sum()
+    }
+
+    /// Returns the number of blob octets in the chunks that have been sent but not
+    /// acknowledged yet.
+    #[must_use]
+    pub fn in_flight_octet_count(&self) -> usize {
+        (0..self.chunk_count())
+            .filter(|&chunk_index| self.is_in_flight(chunk_index))
+            .map(|chunk_index| self.chunk_octet_size(chunk_index))
+            .sum()
+    }
+
+    /// Returns the number of datagrams needed to send the chunks that have not been
+    /// acknowledged yet, e.g. to pace the sending over a deadline.
+    ///
+    /// The chunks are grouped by the batcher from [`Self::with_datagram_batcher`], or sent
+    /// one chunk per datagram if no batcher is set. Resends of lost chunks are not included.
+    /// The payloads are counted as stored, so the count is an upper bound if a chunk codec
+    /// is set.
+    #[must_use]
+    pub fn planned_datagram_count(&self) -> usize {
+        let command_octet_sizes = (0..self.chunk_count())
+            .filter(|&chunk_index| !self.acked.get(chunk_index))
+            .map(|chunk_index| {
+                SetChunkFrontData::command_octet_size_for_payload(
+                    self.chunk_octet_size(chunk_index),
+                )
+            });
+
+        match self.datagram_batcher {
+            Some(datagram_batcher) => datagram_batcher.datagram_count(command_octet_sizes),
+            None => command_octet_sizes.count(),
+        }
+    }
+
+    /// Checks if the receiver has acknowledged all chunks.
+    ///
+    /// A blob of zero octets has no chunks and is complete from the start.
+    ///
+    /// # Returns
+    ///
+    /// `true` if all chunks have been acknowledged; `false` otherwise.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.chunk_count() == 0 || self.acked.all_set()
+    }
+
+    /// Computes the hash of the first `up_to_chunk` chunks of the blob, for a hash checkpoint
+    /// that the receiver verifies with [`crate::in_logic::Logic::verify_checkpoint`].
+    ///
+    /// # Arguments
+    ///
+    /// * `hasher` - The hasher that the receiver uses as well.
+    /// * `up_to_chunk` - The number of chunks, from the start of the blob, to hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::InvalidChunkIndex` if `up_to_chunk` is larger than the chunk count,
+    /// or `BlobError::OutOfBounds` if the blob has been released by [`Self::finish`].
+    pub fn hash_checkpoint(
+        &self,
+        hasher: &dyn BlobHasher,
+        up_to_chunk: usize,
+    ) -> Result<Vec<u8>, BlobError> {
+        let chunk_count = self.chunk_count();
+        if up_to_chunk > chunk_count {
+            return Err(BlobError::InvalidChunkIndex(up_to_chunk, chunk_count));
+        }
+        if self.blob.len() != self.octet_count {
+            return Err(BlobError::OutOfBounds);
+        }
+        let octet_count = (up_to_chunk * self.fixed_chunk_size).min(self.octet_count);
+        Ok(hasher.hash(&self.blob[..octet_count]))
+    }
+
+    /// Ends the transfer, e.g. when the receiver sent `Completed`, even if some of the
+    /// acknowledgements were lost.
+    ///
+    /// All chunks are marked as acknowledged, so nothing is sent anymore, and the blob is
+    /// released.
+    pub fn finish(&mut self) {
+        for chunk_index in 0..self.chunk_count() {
+            self.acked.set(chunk_index);
+        }
+        self.requested_chunks.clear();
+        self.blob = Vec::new();
+    }
+
+    /// Stops [`Self::send`] from producing any chunks, e.g. when the receiver sent `Pause`.
+    pub const fn pause(&mut self) {
+        self.is_paused = true;
+    }
+
+    /// Lets [`Self::send`] produce chunks again, e.g. when the receiver sent `Resume`.
+    pub const fn resume(&mut self) {
+        self.is_paused = false;
+    }
+
+    /// Checks if sending is paused.
+    #[must_use]
+    pub const fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// Returns the credit from the last acknowledgement with credit, or `None` if the receiver
+    /// has not sent any credit.
+    #[must_use]
+    pub const fn credit(&self) -> Option<usize> {
+        self.credit
+    }
+
+    /// Produces the next chunks that are due for sending, picked according to the [`ResendOrder`].
+    ///
+    /// A chunk is due if it has not been acknowledged, and it has either never been sent or it
+    /// was last sent at least the resend interval ago. Chunks that have never been sent are
+    /// only produced while fewer than the window size of chunks are in flight. No more chunks
+    /// than the [`Self::credit`] are produced.
+    ///
+    /// The chunks from [`Self::receive_request`] that have not been acknowledged are produced
+    /// first, even if they are not due.
+    ///
+    /// No more chunks are produced once the byte budget from [`Self::with_max_bytes_per_tick`]
+    /// is used up, and the remaining chunks are produced after [`Self::reset_tick`].
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, supplied by the caller.
+    /// * `max_count` - The maximum number of chunks to produce.
+    ///
+    /// # Returns
+    ///
+    /// Up to `max_count` chunks that should be sent to the receiver. It is empty if no chunks
+    /// are due, if sending is paused or if the receiver has no credit left.
+    #[must_use]
+    pub fn send(&mut self, now: Millis, max_count: usize) -> Vec<SetChunkData> {
+        if self.is_paused {
+            return Vec::new();
+        }
+        let max_count = max_count.min(self.credit_cap());
+
+        let chunk_count = self.chunk_count();
+        let start_chunk_index = match self.resend_order {
+            ResendOrder::LowestIndexFirst => 0,
+            ResendOrder::RoundRobin => self.next_chunk_index,
+        };
+
+        let mut requested_chunks = core::mem::take(&mut self.requested_chunks);
+        requested_chunks.retain(|&chunk_index| !self.acked.get(chunk_index));
+        let requested_count = requested_chunks.len().min(max_count);
+        let mut chunk_indices: Vec<ChunkIndex> =
+            requested_chunks.drain(..requested_count).collect();
+        self.requested_chunks = requested_chunks;
+
+        let mut in_flight_count = self.in_flight_count()
+            + chunk_indices
+                .iter()
+                .filter(|&&chunk_index| !self.is_in_flight(chunk_index))
+                .count();
+        let due_chunk_indices: Vec<ChunkIndex> = (0..chunk_count)
+            .map(|offset| (start_chunk_index + offset) % chunk_count)
+            .filter(|chunk_index| !chunk_indices.contains(chunk_index))
+            .filter(|&chunk_index| {
+                if !self.is_due(chunk_index, now) {
+                    return false;
+                }
+                if self.is_in_flight(chunk_index) {
+                    // Resending does not add to the chunks in flight.
+                    return true;
+                }
+                if in_flight_count >= self.window_cap() {
+                    return false;
+                }
+                in_flight_count += 1;
+                true
+            })
+            .take(max_count - requested_count)
+            .collect();
+        chunk_indices.extend(due_chunk_indices);
+        let chunks = self.chunks_within_tick_budget(&chunk_indices);
+        if chunks.len() < requested_count {
+            // The requested chunks that did not fit in this tick are sent first in the next one.
+            let postponed = chunk_indices[chunks.len()..requested_count].to_vec();
+            self.requested_chunks.splice(0..0, postponed);
+        }
+
+        if let Some(last_chunk) = chunks.last() {
+            self.next_chunk_index =
+                (chunk_index_from_wire(last_chunk.chunk_index) + 1) % chunk_count;
+        }
+        self.mark_sent(&chunks, now);
+
+        chunks
+    }
+
+    /// Produces all chunks that have been sent before, but were not acknowledged within the
+    /// resend interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, supplied by the caller.
+    ///
+    /// # Returns
+    ///
+    /// The chunks that should be sent again, in ascending chunk index order, but no more than
+    /// the [`Self::credit`] and the remaining byte budget of the tick. It is empty if sending is
+    /// paused or if the receiver has no credit left.
+    #[must_use]
+    pub fn tick(&mut self, now: Millis) -> Vec<SetChunkData> {
+        if self.is_paused {
+            return Vec::new();
+        }
+
+        let chunk_indices: Vec<ChunkIndex> = (0..self.chunk_count())
+            .filter(|&chunk_index| {
+                self.last_sent[chunk_index].is_some() && self.is_due(chunk_index, now)
+            })
+            .take(self.credit_cap())
+            .collect();
+        let chunks = self.chunks_within_tick_budget(&chunk_indices);
+        self.mark_sent(&chunks, now);
+
+        chunks
+    }
+
+    /// Applies an acknowledgement from the receiver.
+    ///
+    /// All chunks before `waiting_for_chunk_index` are marked as acknowledged, as well as the
+    /// chunks that are set in `receive_mask_after_last`, where bit zero is the chunk directly
+    /// after `waiting_for_chunk_index`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ack` - The acknowledgement sent by the receiver.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::InvalidData` if `waiting_for_chunk_index` is
+    /// past the end of the blob.
+    pub fn receive(&mut self, ack: &AckChunkData) -> io::Result<()> {
+        let chunk_count = self.chunk_count();
+        let waiting_for_chunk_index = self.acknowledge_before(ack.waiting_for_chunk_index)?;
+
+        for bit_index in 0..u64::BITS as usize {
+            let chunk_index = waiting_for_chunk_index + 1 + bit_index;
+            if chunk_index >= chunk_count {
+                break;
+            }
+            if ack.receive_mask_after_last & (1 << bit_index) != 0 {
+                self.acked.set(chunk_index);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies an acknowledgement with a receive mask of any length.
+    ///
+    /// All chunks before `waiting_for_chunk_index` are marked as acknowledged, as well as the
+    /// chunks that [`AckChunkMaskData::is_received`] reports as received.
+    ///
+    /// # Arguments
+    ///
+    /// * `ack_mask` - The acknowledgement sent by the receiver.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::receive`].
+    pub fn receive_mask(&mut self, ack_mask: &AckChunkMaskData) -> io::Result<()> {
+        let waiting_for_chunk_index = self.acknowledge_before(ack_mask.waiting_for_chunk_index)?;
+
+        for chunk_index in (waiting_for_chunk_index + 1)..self.chunk_count() {
+            if ack_mask.is_received(chunk_index_to_wire(chunk_index)) {
+                self.acked.set(chunk_index);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies an acknowledgement that is combined with the flow-control credit of the receiver.
+    ///
+    /// The chunks are acknowledged the same way as in [`Self::receive`]. From then on, no more
+    /// than `credit` chunks are kept in flight, on top of the limit from the window size, and
+    /// nothing is sent while the credit is zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `ack_credit` - The acknowledgement and credit sent by the receiver.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::receive`].
+    pub fn receive_with_credit(&mut self, ack_credit: &AckChunkCreditData) -> io::Result<()> {
+        self.receive(&ack_credit.ack)?;
+        self.credit = Some(ack_credit.credit as usize);
+        Ok(())
+    }
+
+    /// Applies a request for missing chunks from [`crate::in_logic::Logic::request_chunks`].
+    ///
+    /// The requested chunks that have not been acknowledged are produced first by the next
+    /// calls to [`Self::send`], without waiting for the resend interval. A new request replaces
+    /// the chunks of an earlier one that have not been sent yet.
+    ///
+    /// Requested chunk indices that are past the end of the blob are ignored, like in
+    /// [`Self::resend_chunks`], so the valid chunks of a request are still resent.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The chunks that the receiver is missing.
+    pub fn receive_request(&mut self, request: &RequestChunksData) {
+        self.resend_chunks(&request.chunk_indices);
+    }
+
+    /// Queues exactly the chunks at `chunk_indices` to be produced first by the next calls to
+    /// [`Self::send`], without waiting for the resend interval, e.g. for a custom
+    /// retransmission scheme. It replaces the chunks of an earlier request that have not been
+    /// sent yet.
+    ///
+    /// Chunk indices that are past the end of the blob are ignored, as are chunks that have
+    /// already been acknowledged.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk_indices` - The chunks to resend, in order.
+    pub fn resend_chunks(&mut self, chunk_indices: &[u64]) {
+        let chunk_count = self.chunk_count();
+        self.requested_chunks.clear();
+        for &chunk_index in chunk_indices {
+            let chunk_index = chunk_index_from_wire(chunk_index);
+            if chunk_index < chunk_count
+                && !self.acked.get(chunk_index)
+                && !self.requested_chunks.contains(&chunk_index)
+            {
+                self.requested_chunks.push(chunk_index);
+            }
+        }
+    }
+
+    /// Applies the complete set of received chunks from [`crate::in_logic::Logic::full_received_ack`],
+    /// marking every chunk in the set as acknowledged.
+    ///
+    /// # Arguments
+    ///
+    /// * `full_received` - The encoded set of received chunks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::InvalidData` if the set cannot be decoded or
+    /// does not have the same number of chunks as the blob.
+    pub fn apply_full_received(&mut self, full_received: &[u8]) -> io::Result<()> {
+        let received = received_set::decode(full_received, self.chunk_count())?;
+        for chunk_index in (0..self.chunk_count()).filter(|&index| received.get(index)) {
+            self.acked.set(chunk_index);
+        }
+        Ok(())
+    }
+
+    /// Marks all chunks before `waiting_for_chunk_index` as acknowledged.
+    fn acknowledge_before(&mut self, waiting_for_chunk_index: u64) -> io::Result<ChunkIndex> {
+        let chunk_count = self.chunk_count();
+        let waiting_for_chunk_index = chunk_index_from_wire(waiting_for_chunk_index);
+        if waiting_for_chunk_index > chunk_count {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "acknowledged chunk index {waiting_for_chunk_index} is past the chunk count {chunk_count}"
+                ),
+            ));
+        }
+
+        for chunk_index in 0..waiting_for_chunk_index {
+            self.acked.set(chunk_index);
+        }
+
+        Ok(waiting_for_chunk_index)
+    }
+
+    /// Checks if the chunk at `chunk_index` is unacknowledged and not waiting for an acknowledgement.
+    fn is_due(&self, chunk_index: ChunkIndex, now: Millis) -> bool {
+        !self.acked.get(chunk_index)
+            && self.last_sent[chunk_index].is_none_or(|last_sent| {
+                now.saturating_duration_since(last_sent) >= self.resend_interval
+            })
+    }
+
+    fn is_in_flight(&self, chunk_index: ChunkIndex) -> bool {
+        self.last_sent[chunk_index].is_some() && !self.acked.get(chunk_index)
+    }
+
+    fn mark_sent(&mut self, chunks: &[SetChunkData], now: Millis) {
+        for chunk in chunks {
+            self.last_sent[chunk_index_from_wire(chunk.chunk_index)] = Some(now);
+        }
+    }
+
+    fn chunk(&self, chunk_index: ChunkIndex) -> SetChunkData {
+        SetChunkData::encode(
+            chunk_index_to_wire(chunk_index),
+            self.chunk_payload(chunk_index),
+            self.chunk_codec,
+        )
+    }
+
+    /// Returns the maximum number of chunks to produce at once, which is the credit from the
+    /// receiver, if any.
+    fn credit_cap(&self) -> usize {
+        self.credit.unwrap_or(usize::MAX)
+    }
+
+    /// Encodes the chunks at `chunk_indices`, from the start, that fit in the remaining byte
+    /// budget of the tick, and counts their payload octets as sent in this tick.
+    ///
+    /// A chunk that is larger than the whole budget only fits if nothing counts against the
+    /// budget yet, and the octets beyond the budget are carried over by [`Self::reset_tick`].
+    fn chunks_within_tick_budget(&mut self, chunk_indices: &[ChunkIndex]) -> Vec<SetChunkData> {
+        let mut chunks = Vec::new();
+        for &chunk_index in chunk_indices {
+            let chunk = self.chunk(chunk_index);
+            let octet_count = chunk.payload.len();
+            if self.bytes_sent_this_tick > 0
+                && self.bytes_sent_this_tick.saturating_add(octet_count) > self.max_bytes_per_tick
+            {
+                break;
+            }
+            self.bytes_sent_this_tick = self.bytes_sent_this_tick.saturating_add(octet_count);
+            chunks.push(chunk);
+        }
+        chunks
+    }
+
+    /// Returns the maximum number of chunks in flight, which is the window size, further
+    /// limited by the credit from the receiver.
+    fn window_cap(&self) -> usize {
+        // The chunks in flight have not been received, so they are part of the credit.
+        self.credit
+            .map_or(self.window_size, |credit| self.window_size.min(credit))
+    }
+
+    /// Returns the number of octets in the chunk at `chunk_index`, where the last chunk may be
+    /// shorter.
+    const fn chunk_octet_size(&self, chunk_index: ChunkIndex) -> usize {
+        let octet_offset = chunk_index * self.fixed_chunk_size;
+        let remaining_octet_count = self.octet_count.saturating_sub(octet_offset);
+        if remaining_octet_count < self.fixed_chunk_size {
+            remaining_octet_count
+        } else {
+            self.fixed_chunk_size
+        }
+    }
+
+    /// Returns the octets of the chunk at `chunk_index`, where the last chunk may be shorter.
+    fn chunk_payload(&self, chunk_index: ChunkIndex) -> &[u8] {
+        let octet_offset = chunk_index * self.fixed_chunk_size;
+        let end_octet_offset = (octet_offset + self.fixed_chunk_size).min(self.blob.len());
+        &self.blob[octet_offset..end_octet_offset]
+    }
+}
@@ -0,0 +1,608 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::chunk_index_to_wire;
+use crate::err::BlobError;
+use crate::hash::BlobHasher;
+use crate::in_stream::check_min_chunk_size;
+use crate::millis::Millis;
+use crate::out_logic::Logic;
+use crate::protocol::{HashCheckpointData, StartTransferData, TransferId};
+use crate::protocol_front::{
+    ReceiverToSenderFrontCommands, SenderToReceiverFrontCommands, SetChunkFrontData,
+};
+use core::fmt::Debug;
+use log::warn;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// How long to wait between `StartTransfer` attempts, and how many attempts to make before the
+/// handshake fails.
+///
+/// The interval doubles after each attempt, up to `max_interval`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct StartBackoff {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for StartBackoff {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_millis(100),
+            max_interval: Duration::from_secs(2),
+            max_attempts: 8,
+        }
+    }
+}
+
+impl StartBackoff {
+    /// Returns the interval to wait after attempt number `attempt_count`, before any jitter.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempt_count` - The number of attempts made so far, starting at one.
+    #[must_use]
+    pub fn interval(&self, attempt_count: u32) -> Duration {
+        let doublings = attempt_count.saturating_sub(1).min(u32::BITS - 1);
+        self.initial_interval
+            .saturating_mul(1 << doublings)
+            .min(self.max_interval)
+    }
+}
+
+/// Supplies the random extra delay that is added to each `StartTransfer` interval, so that many
+/// senders that lost their receiver at the same time do not retry in lockstep.
+pub trait JitterSource: Debug {
+    /// Returns the extra delay to add to `interval`, usually a random fraction of it.
+    fn jitter(&mut self, interval: Duration) -> Duration;
+}
+
+/// The progress of several outgoing transfers added together, see [`aggregate_stats`].
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct AggregateStats {
+    /// The number of blob octets that the receivers have acknowledged.
+    pub acked_octet_count: usize,
+    /// The number of blob octets that have been sent but not acknowledged yet.
+    pub in_flight_octet_count: usize,
+    /// The number of transfers that are not complete yet.
+    pub active_transfer_count: usize,
+}
+
+/// Adds up the progress of `senders`, e.g. for the total upload throughput of an application.
+///
+/// # Arguments
+///
+/// * `senders` - The outgoing transfers. Completed transfers still count towards the
+///   acknowledged octets, but not towards the active transfers.
+#[must_use]
+pub fn aggregate_stats<'a>(senders: impl IntoIterator<Item = &'a OutLogicFront>) -> AggregateStats {
+    senders
+        .into_iter()
+        .fold(AggregateStats::default(), |mut stats, sender| {
+            stats.acked_octet_count += sender.acked_octet_count();
+            stats.in_flight_octet_count += sender.in_flight_octet_count();
+            if !sender.is_complete() {
+                stats.active_transfer_count += 1;
+            }
+            stats
+        })
+}
+
+/// Hands out unique transfer ids for outgoing transfers.
+///
+/// The ids are handed out in increasing order, wrapping around after `u16::MAX`, and ids of
+/// transfers that have not been released yet are skipped.
+#[derive(Debug, Default, Clone)]
+pub struct TransferIdAllocator {
+    next_id: u16,
+    in_use: HashSet<u16>,
+}
+
+impl TransferIdAllocator {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a transfer id that is not in use, and marks it as in use until it is released.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` of `ErrorKind::Other` if all `u16` transfer ids are in use.
+    pub fn allocate(&mut self) -> io::Result<TransferId> {
+        if self.in_use.len() > usize::from(u16::MAX) {
+            return Err(io::Error::other("all transfer ids are in use"));
+        }
+        loop {
+            let id = self.next_id;
+            self.next_id = self.next_id.wrapping_add(1);
+            if self.in_use.insert(id) {
+                return Ok(TransferId(id));
+            }
+        }
+    }
+
+    /// Releases `transfer_id`, e.g. when its transfer has finished, so it can be handed out
+    /// again.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the id was in use.
+    pub fn release(&mut self, transfer_id: TransferId) -> bool {
+        self.in_use.remove(&transfer_id.0)
+    }
+
+    /// Returns the number of transfer ids that are in use.
+    #[must_use]
+    pub fn in_use_count(&self) -> usize {
+        self.in_use.len()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Phase {
+    StartTransfer,
+    Transfer,
+    Completed,
+    Failed,
+}
+
+/// `OutLogicFront` handles the sending side of a transfer, including the `StartTransfer` handshake.
+///
+/// `StartTransfer` is sent until the receiver answers with `AckStart`, with a [`StartBackoff`]
+/// between the attempts. After that the chunks are produced by the [`Logic`].
+#[derive(Debug)]
+pub struct OutLogicFront {
+    transfer_id: TransferId,
+    logic: Logic,
+    fixed_chunk_size: u32,
+    total_octet_size: u32,
+    phase: Phase,
+    start_backoff: StartBackoff,
+    jitter_source: Option<Box<dyn JitterSource + Send>>,
+    attempt_count: u32,
+    next_attempt_at: Option<Millis>,
+}
+
+impl OutLogicFront {
+    /// Creates a new `OutLogicFront` for sending `blob` in chunks of `fixed_chunk_size` octets.
+    ///
+    /// # Arguments
+    ///
+    /// * `transfer_id` - The id of the transfer, as announced in `StartTransfer`.
+    /// * `blob` - The octets to send.
+    /// * `fixed_chunk_size` - The size of each chunk. The last chunk holds the remaining octets.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `fixed_chunk_size` is zero, or if the blob or the chunk size do not fit
+    /// in a `u32`.
+    #[must_use]
+    pub fn new(transfer_id: TransferId, blob: Vec<u8>, fixed_chunk_size: usize) -> Self {
+        let total_octet_size = u32::try_from(blob.len()).expect("blob is too large");
+        let chunk_size = u32::try_from(fixed_chunk_size).expect("chunk size is too large");
+        Self {
+            transfer_id,
+            logic: Logic::new(blob, fixed_chunk_size),
+            fixed_chunk_size: chunk_size,
+            total_octet_size,
+            phase: Phase::StartTransfer,
+            start_backoff: StartBackoff::default(),
+            jitter_source: None,
+            attempt_count: 0,
+            next_attempt_at: None,
+        }
+    }
+
+    /// Creates a new `OutLogicFront` like [`Self::new`], but rejects a `fixed_chunk_size` below
+    /// `min_chunk_size`, which would make the per-chunk overhead dominate the transfer.
+    ///
+    /// # Arguments
+    ///
+    /// * `transfer_id` - The id of the transfer, as announced in `StartTransfer`.
+    /// * `blob` - The octets to send.
+    /// * `fixed_chunk_size` - The size of each chunk. The last chunk holds the remaining octets.
+    /// * `min_chunk_size` - The smallest allowed chunk size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::ChunkSizeTooSmall` if `fixed_chunk_size` is below `min_chunk_size`,
+    /// unless the whole blob fits in a single chunk.
+    ///
+    /// # Panics
+    ///
+    /// Will panic in the same cases as [`Self::new`].
+    pub fn try_new(
+        transfer_id: TransferId,
+        blob: Vec<u8>,
+        fixed_chunk_size: usize,
+        min_chunk_size: usize,
+    ) -> Result<Self, BlobError> {
+        check_min_chunk_size(blob.len(), fixed_chunk_size, min_chunk_size)?;
+        Ok(Self::new(transfer_id, blob, fixed_chunk_size))
+    }
+
+    /// Sets the intervals between the `StartTransfer` attempts and the maximum number of attempts.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_backoff` - The backoff for the `StartTransfer` handshake.
+    ///
+    /// # Returns
+    ///
+    /// The `OutLogicFront` with the backoff applied.
+    #[must_use]
+    pub const fn with_start_backoff(mut self, start_backoff: StartBackoff) -> Self {
+        self.start_backoff = start_backoff;
+        self
+    }
+
+    /// Sets the source of the extra delay added to each `StartTransfer` interval. Without a
+    /// jitter source, the intervals are exactly the ones from the [`StartBackoff`].
+    ///
+    /// # Arguments
+    ///
+    /// * `jitter_source` - The source of the extra delay.
+    ///
+    /// # Returns
+    ///
+    /// The `OutLogicFront` with the jitter source applied.
+    #[must_use]
+    pub fn with_jitter_source(mut self, jitter_source: Box<dyn JitterSource + Send>) -> Self {
+        self.jitter_source = Some(jitter_source);
+        self
+    }
+
+    /// Returns the number of `StartTransfer` commands sent since the handshake began.
+    #[must_use]
+    pub const fn attempt_count(&self) -> u32 {
+        self.attempt_count
+    }
+
+    /// Checks if the receiver has acknowledged the `StartTransfer`.
+    #[must_use]
+    pub const fn is_started(&self) -> bool {
+        matches!(self.phase, Phase::Transfer | Phase::Completed)
+    }
+
+    /// Checks if the receiver has acknowledged all chunks.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.logic.is_complete()
+    }
+
+    /// Returns the number of blob octets that the receiver has acknowledged.
+    #[must_use]
+    pub fn acked_octet_count(&self) -> usize {
+        self.logic.acked_octet_count()
+    }
+
+    /// Returns the number of blob octets that have been sent but not acknowledged yet.
+    #[must_use]
+    pub fn in_flight_octet_count(&self) -> usize {
+        self.logic.in_flight_octet_count()
+    }
+
+    /// Produces the commands that are due for sending.
+    ///
+    /// During the handshake, this is a `StartTransfer` whenever the backoff interval after the
+    /// previous attempt has passed. Once the receiver has acknowledged the start, these are the
+    /// chunks from [`Logic::send`].
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, supplied by the caller.
+    /// * `max_count` - The maximum number of chunks to produce.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::TimedOut` if the receiver has not acknowledged
+    /// the start after the maximum number of attempts.
+    pub fn send(
+        &mut self,
+        now: Millis,
+        max_count: usize,
+    ) -> io::Result<Vec<SenderToReceiverFrontCommands>> {
+        match self.phase {
+            Phase::StartTransfer => self.send_start_transfer(now),
+            Phase::Transfer => Ok(self
+                .logic
+                .send(now, max_count)
+                .into_iter()
+                .map(|data| {
+                    SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                        transfer_id: self.transfer_id,
+                        data,
+                    })
+                })
+                .collect()),
+            Phase::Completed => Ok(Vec::new()),
+            Phase::Failed => Err(self.timed_out()),
+        }
+    }
+
+    /// Creates a `HashCheckpoint` command for the first `up_to_chunk` chunks, which is
+    /// typically sent every few chunks after the chunks themselves.
+    ///
+    /// # Arguments
+    ///
+    /// * `hasher` - The hasher that the receiver uses as well.
+    /// * `up_to_chunk` - The number of chunks, from the start of the blob, that the hash covers.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlobError::InvalidChunkIndex` if `up_to_chunk` is larger than the chunk count.
+    pub fn hash_checkpoint(
+        &self,
+        hasher: &dyn BlobHasher,
+        up_to_chunk: usize,
+    ) -> Result<SenderToReceiverFrontCommands, BlobError> {
+        Ok(SenderToReceiverFrontCommands::HashCheckpoint(
+            HashCheckpointData {
+                transfer_id: self.transfer_id.0,
+                up_to_chunk: chunk_index_to_wire(up_to_chunk),
+                hash: self.logic.hash_checkpoint(hasher, up_to_chunk)?,
+            },
+        ))
+    }
+
+    /// Applies a command from the receiver.
+    ///
+    /// `AckStart` ends the handshake and `NeedStart` begins it again. `Completed` ends the
+    /// transfer, after which nothing is sent and the blob is released, see [`Logic::finish`].
+    /// Acknowledgements and pausing are passed on to the [`Logic`]. Commands for other
+    /// transfers are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command sent by the receiver.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors from applying an acknowledgement to the [`Logic`].
+    pub fn receive(&mut self, command: &ReceiverToSenderFrontCommands) -> io::Result<()> {
+        if command.transfer_id() != self.transfer_id.0 {
+            return Ok(());
+        }
+
+        match command {
+            ReceiverToSenderFrontCommands::AckStart(_) => {
+                if self.phase == Phase::StartTransfer {
+                    self.phase = Phase::Transfer;
+                }
+            }
+            ReceiverToSenderFrontCommands::NeedStart(_) => {
+                if self.phase == Phase::Transfer {
+                    self.phase = Phase::StartTransfer;
+                    self.attempt_count = 0;
+                    self.next_attempt_at = None;
+                }
+            }
+            ReceiverToSenderFrontCommands::AckChunk(ack) => self.logic.receive(&ack.data)?,
+            ReceiverToSenderFrontCommands::AckChunkMask(ack_mask) => {
+                self.logic.receive_mask(&ack_mask.data)?;
+            }
+            ReceiverToSenderFrontCommands::AckChunkCredit(ack_credit) => {
+                self.logic.receive_with_credit(&ack_credit.data)?;
+            }
+            ReceiverToSenderFrontCommands::RequestChunks(request) => {
+                self.logic.receive_request(&request.data);
+            }
+            ReceiverToSenderFrontCommands::Pause(_) => self.logic.pause(),
+            ReceiverToSenderFrontCommands::Resume(_) => self.logic.resume(),
+            ReceiverToSenderFrontCommands::Completed(_) => {
+                self.logic.finish();
+                self.phase = Phase::Completed;
+            }
+            ReceiverToSenderFrontCommands::AckBytes(_)
+            | ReceiverToSenderFrontCommands::AckAbort(_) => {}
+        }
+
+        Ok(())
+    }
+
+    fn send_start_transfer(
+        &mut self,
+        now: Millis,
+    ) -> io::Result<Vec<SenderToReceiverFrontCommands>> {
+        if self
+            .next_attempt_at
+            .is_some_and(|next_attempt_at| now < next_attempt_at)
+        {
+            return Ok(Vec::new());
+        }
+        if self.attempt_count >= self.start_backoff.max_attempts {
+            self.phase = Phase::Failed;
+            return Err(self.timed_out());
+        }
+
+        self.attempt_count += 1;
+        let interval = self.start_backoff.interval(self.attempt_count);
+        let jitter = self
+            .jitter_source
+            .as_mut()
+            .map_or(Duration::ZERO, |jitter_source| {
+                jitter_source.jitter(interval)
+            });
+        self.next_attempt_at = Some(now + interval + jitter);
+
+        Ok(vec![SenderToReceiverFrontCommands::StartTransfer(
+            StartTransferData {
+                transfer_id: self.transfer_id.0,
+                total_octet_size: self.total_octet_size,
+                chunk_size: self.fixed_chunk_size,
+                schedule: vec![],
+                blob_hash: None,
+            },
+        )])
+    }
+
+    fn timed_out(&self) -> io::Error {
+        io::Error::new(
+            ErrorKind::TimedOut,
+            format!(
+                "receiver did not acknowledge StartTransfer for transfer_id {} after {} attempts",
+                self.transfer_id.0, self.attempt_count
+            ),
+        )
+    }
+}
+
+/// Sends several transfers at the same time, the sending counterpart of
+/// [`crate::in_logic_front::FrontLogic`].
+///
+/// Each transfer is an [`OutLogicFront`], with its own `StartTransfer` handshake, and the
+/// commands from the receiver are routed to the transfer that they are for.
+#[derive(Debug, Default)]
+pub struct MultiOutLogicFront {
+    transfers: HashMap<u16, OutLogicFront>,
+    next_transfer_id: u16,
+    failed_transfer_ids: Vec<u16>,
+}
+
+impl MultiOutLogicFront {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts sending `blob` as the transfer `transfer_id`.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, supplied by the caller.
+    /// * `transfer_id` - The id of the transfer, as announced in `StartTransfer`.
+    /// * `blob` - The octets to send.
+    /// * `fixed_chunk_size` - The size of each chunk. The last chunk holds the remaining octets.
+    ///
+    /// # Returns
+    ///
+    /// The first `StartTransfer` command of the transfer. It is sent again by [`Self::send`]
+    /// until the receiver acknowledges it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::AlreadyExists` if a transfer with `transfer_id`
+    /// is already being sent.
+    ///
+    /// # Panics
+    ///
+    /// Will panic in the same cases as [`OutLogicFront::new`].
+    pub fn start_transfer(
+        &mut self,
+        now: Millis,
+        transfer_id: TransferId,
+        blob: Vec<u8>,
+        fixed_chunk_size: usize,
+    ) -> io::Result<Vec<SenderToReceiverFrontCommands>> {
+        if self.transfers.contains_key(&transfer_id.0) {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("transfer_id {} is already being sent", transfer_id.0),
+            ));
+        }
+        let mut sender = OutLogicFront::new(transfer_id, blob, fixed_chunk_size);
+        let commands = sender.send(now, 0)?;
+        self.transfers.insert(transfer_id.0, sender);
+        Ok(commands)
+    }
+
+    /// Produces the commands to send for all transfers, at most `max_count` chunks in total.
+    ///
+    /// The transfers take turns in the order of their ids, starting after the transfer that
+    /// used up the chunks in the previous call, so that no transfer is starved. A transfer whose
+    /// `StartTransfer` handshake times out is removed, see [`Self::take_failed_transfer_ids`].
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, supplied by the caller.
+    /// * `max_count` - The maximum number of chunks to produce.
+    pub fn send(&mut self, now: Millis, max_count: usize) -> Vec<SenderToReceiverFrontCommands> {
+        let mut transfer_ids: Vec<u16> = self.transfers.keys().copied().collect();
+        transfer_ids.sort_unstable();
+        let first = transfer_ids.partition_point(|&id| id < self.next_transfer_id);
+        transfer_ids.rotate_left(first);
+
+        let mut commands = Vec::new();
+        let mut remaining_count = max_count;
+        for transfer_id in transfer_ids {
+            let Some(sender) = self.transfers.get_mut(&transfer_id) else {
+                continue;
+            };
+            match sender.send(now, remaining_count) {
+                Ok(transfer_commands) => {
+                    let chunk_count = transfer_commands
+                        .iter()
+                        .filter(|command| {
+                            matches!(command, SenderToReceiverFrontCommands::SetChunk(_))
+                        })
+                        .count();
+                    commands.extend(transfer_commands);
+                    remaining_count -= chunk_count.min(remaining_count);
+                }
+                Err(err) => {
+                    warn!("dropping transfer {transfer_id}: {err}");
+                    self.transfers.remove(&transfer_id);
+                    self.failed_transfer_ids.push(transfer_id);
+                }
+            }
+            if remaining_count == 0 && max_count > 0 {
+                self.next_transfer_id = transfer_id.wrapping_add(1);
+                break;
+            }
+        }
+        commands
+    }
+
+    /// Applies a command from the receiver to the transfer that it is for. Commands for
+    /// unknown transfers are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command sent by the receiver.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors from [`OutLogicFront::receive`].
+    pub fn receive(&mut self, command: &ReceiverToSenderFrontCommands) -> io::Result<()> {
+        self.transfers
+            .get_mut(&command.transfer_id())
+            .map_or(Ok(()), |sender| sender.receive(command))
+    }
+
+    /// Returns the transfer with `transfer_id`, e.g. to check if it is complete.
+    #[must_use]
+    pub fn transfer(&self, transfer_id: TransferId) -> Option<&OutLogicFront> {
+        self.transfers.get(&transfer_id.0)
+    }
+
+    /// Returns all transfers that are being sent, in no particular order, e.g. for
+    /// [`aggregate_stats`].
+    pub fn transfers(&self) -> impl Iterator<Item = &OutLogicFront> + '_ {
+        self.transfers.values()
+    }
+
+    /// Removes the transfer with `transfer_id`, e.g. once it is complete.
+    ///
+    /// # Returns
+    ///
+    /// The removed transfer, or `None` if it is not known.
+    pub fn remove_transfer(&mut self, transfer_id: TransferId) -> Option<OutLogicFront> {
+        self.transfers.remove(&transfer_id.0)
+    }
+
+    /// Returns the ids of the transfers that have been removed since the last call, because the
+    /// receiver did not acknowledge their `StartTransfer`.
+    ///
+    /// # Returns
+    ///
+    /// The failed ids, in the order they failed.
+    pub fn take_failed_transfer_ids(&mut self) -> Vec<u16> {
+        core::mem::take(&mut self.failed_transfer_ids)
+    }
+}
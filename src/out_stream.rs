@@ -0,0 +1,84 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::err::BlobError;
+use crate::ChunkIndex;
+use alloc::vec::Vec;
+
+/// A blob to send, divided into fixed-size chunks. The counterpart of
+/// [`BlobStreamIn`](crate::in_stream::BlobStreamIn).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BlobStreamOut {
+    blob: Vec<u8>,
+    fixed_chunk_size: usize,
+}
+
+impl BlobStreamOut {
+    /// Creates a new `BlobStreamOut` instance that owns `blob`.
+    ///
+    /// # Parameters
+    /// - `blob`: The octets to send.
+    /// - `fixed_chunk_size`: The size of each chunk, the last chunk may be shorter.
+    ///
+    /// # Panics
+    /// Will panic if `fixed_chunk_size` is zero.
+    ///
+    /// # Returns
+    /// A new `BlobStreamOut` instance.
+    #[must_use]
+    pub fn new(blob: Vec<u8>, fixed_chunk_size: usize) -> Self {
+        assert!(
+            fixed_chunk_size > 0,
+            "fixed_chunk_size must be greater than zero"
+        );
+        Self {
+            blob,
+            fixed_chunk_size,
+        }
+    }
+
+    /// Returns the total number of chunks.
+    #[must_use]
+    pub const fn chunk_count(&self) -> usize {
+        self.blob.len().div_ceil(self.fixed_chunk_size)
+    }
+
+    /// Returns the size of each chunk, except for the last one which may be shorter.
+    #[must_use]
+    pub const fn fixed_chunk_size(&self) -> usize {
+        self.fixed_chunk_size
+    }
+
+    /// Returns the whole blob.
+    #[must_use]
+    pub fn blob(&self) -> &[u8] {
+        &self.blob
+    }
+
+    /// Returns the octets of a single chunk.
+    ///
+    /// # Parameters
+    /// - `chunk_index`: The index of the chunk to read.
+    ///
+    /// # Errors
+    /// Returns `BlobError::InvalidChunkIndex` if the `chunk_index` is out of range.
+    ///
+    /// # Returns
+    /// The octets of the chunk, where the last chunk holds the remaining octets.
+    pub fn chunk(&self, chunk_index: ChunkIndex) -> Result<&[u8], BlobError> {
+        let chunk_count = self.chunk_count();
+        if chunk_index >= chunk_count {
+            return Err(BlobError::InvalidChunkIndex(chunk_index, chunk_count));
+        }
+
+        let octet_offset = chunk_index * self.fixed_chunk_size;
+        let end_octet_offset = (octet_offset + self.fixed_chunk_size).min(self.blob.len());
+        Ok(&self.blob[octet_offset..end_octet_offset])
+    }
+
+    /// Returns an iterator over all chunks, in order, together with their chunk index.
+    pub fn iter_chunks(&self) -> impl Iterator<Item = (ChunkIndex, &[u8])> + '_ {
+        self.blob.chunks(self.fixed_chunk_size).enumerate()
+    }
+}
@@ -8,11 +8,26 @@
 //! The `prelude` module provides easy access to commonly used types, structs, and enums across
 //! various parts of the library. By including this prelude, you can reduce the number of individual
 //! imports needed in your code.
+pub use crate::err::BlobError;
+pub use crate::hash::{BlobHasher, HashState};
+pub use crate::millis::Millis;
+
+#[cfg(feature = "std")]
 pub use {
-    crate::err::BlobError,
-    crate::in_logic_front::{FrontLogic, Info},
+    crate::codec::ChunkCodec,
+    crate::err::FrontError,
+    crate::in_logic::{ReceiveOutcome, ReceiveSnapshot, ReceiveStats},
+    crate::in_logic_front::{
+        CompletedStartPolicy, FrontLogic, Info, PeerId, TransferCapacityPolicy, TransferLimits,
+        TransferStatus, UnknownTransferPolicy,
+    },
+    crate::out_logic_front::{
+        aggregate_stats, AggregateStats, JitterSource, MultiOutLogicFront, OutLogicFront,
+        StartBackoff, TransferIdAllocator,
+    },
     crate::protocol::{SetChunkData, StartTransferData, TransferId},
     crate::protocol_front::{
         ReceiverToSenderFrontCommands, SenderToReceiverFrontCommands, SetChunkFrontData,
+        SetChunksFrontData,
     },
 };
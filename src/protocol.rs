@@ -2,25 +2,109 @@
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
+use crate::chunk_index_to_wire;
+use crate::codec::ChunkCodec;
+use bit_array_rs::BitArray;
+use core::fmt;
 use flood_rs::{ReadOctetStream, WriteOctetStream};
+use std::borrow::Cow;
 use std::io;
 
+/// The maximum number of payload octets in a `SetChunkData` read with [`SetChunkData::from_stream`].
+///
+/// A peer can declare any payload length up to `u32::MAX`, so longer payloads are rejected
+/// before allocating for them.
+pub const MAX_PAYLOAD_OCTET_SIZE: usize = 1024 * 1024;
+
+/// A chunk of the blob, sent from the sender to the receiver.
+///
+/// The `payload` holds the octets of the chunk encoded with `codec`.
+///
+/// With the `serde` feature enabled, the `payload` is serialized as a plain sequence of
+/// octets, e.g. an array of numbers in JSON. This does not affect the wire format of
+/// `to_stream` and `from_stream`.
+///
+/// With the `crc32` feature enabled, `to_stream` writes a CRC32 of the payload after it, and
+/// `from_stream` rejects a chunk where it does not match. Both sides must agree on the feature.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SetChunkData {
-    pub chunk_index: u32,
+    pub chunk_index: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub codec: ChunkCodec,
     pub payload: Vec<u8>,
 }
 
 impl SetChunkData {
+    /// Creates a chunk with the `octets` encoded by `codec`, unless the encoding would not make
+    /// the payload smaller, in which case the octets are stored as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk_index` - The index of the chunk.
+    /// * `octets` - The octets of the chunk.
+    /// * `codec` - The preferred codec for the chunk.
+    ///
+    /// # Returns
+    ///
+    /// The chunk, with the codec that was actually used.
+    #[must_use]
+    pub fn encode(chunk_index: u64, octets: &[u8], codec: ChunkCodec) -> Self {
+        let encoded = codec.encode(octets);
+        if encoded.len() < octets.len() {
+            return Self {
+                chunk_index,
+                codec,
+                payload: encoded,
+            };
+        }
+        Self {
+            chunk_index,
+            codec: ChunkCodec::Stored,
+            payload: octets.to_vec(),
+        }
+    }
+
+    /// Returns the octets of the chunk, decoded with the `codec` of the chunk.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_octet_size` - The maximum number of octets that a compressed payload may decode
+    ///   to, see [`ChunkCodec::decode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::InvalidData` if the payload is not valid for the codec.
+    pub fn decoded_payload(&self, max_octet_size: usize) -> io::Result<Cow<'_, [u8]>> {
+        self.codec.decode(&self.payload, max_octet_size)
+    }
+
+    /// Returns the number of octets that `to_stream` writes for this chunk.
+    #[must_use]
+    pub const fn octet_size(&self) -> usize {
+        Self::octet_size_for_payload(self.payload.len())
+    }
+
+    /// Returns the number of octets that `to_stream` writes for a chunk with a payload of
+    /// `payload_octet_size` octets.
+    #[must_use]
+    pub const fn octet_size_for_payload(payload_octet_size: usize) -> usize {
+        // chunk_index (u64) + codec (u8) + payload length (u32) + payload + trailer
+        8 + 1 + 4 + payload_octet_size + PAYLOAD_TRAILER_OCTET_SIZE
+    }
+
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
-    #[allow(clippy::cast_possible_truncation)]
+    /// It also returns an error if the payload is larger than `u32::MAX` octets.
     pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
-        stream.write_u32(self.chunk_index)?;
-        stream.write_u16(self.payload.len() as u16)?;
+        stream.write_u64(self.chunk_index)?;
+        stream.write_u8(self.codec as u8)?;
+        stream.write_u32(payload_octet_length(&self.payload)?)?;
         stream.write(&self.payload[..])?;
+        #[cfg(feature = "crc32")]
+        stream.write_u32(crc32fast::hash(&self.payload))?;
         Ok(())
     }
 
@@ -28,28 +112,192 @@ impl SetChunkData {
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// With the `crc32` feature, it also returns an error with `ErrorKind::InvalidData` if the
+    /// CRC32 trailer does not match the payload.
+    /// It also returns an error with `ErrorKind::InvalidData` if the payload is longer than
+    /// [`MAX_PAYLOAD_OCTET_SIZE`], before allocating for it.
     pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
-        let chunk_index = stream.read_u32()?;
-        let octet_length = stream.read_u16()?;
+        Self::from_stream_with_max_payload(stream, MAX_PAYLOAD_OCTET_SIZE)
+    }
+
+    /// Same as `from_stream`, but rejects a payload longer than `max_payload_octet_size`
+    /// before allocating for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `from_stream`, and an `io::Error` with
+    /// `ErrorKind::InvalidData` if the payload is longer than `max_payload_octet_size`.
+    pub fn from_stream_with_max_payload(
+        stream: &mut dyn ReadOctetStream,
+        max_payload_octet_size: usize,
+    ) -> io::Result<Self> {
+        let chunk_index = stream.read_u64()?;
+        let codec = ChunkCodec::try_from(stream.read_u8()?)?;
+        let octet_length = stream.read_u32()?;
+        if octet_length as usize > max_payload_octet_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "payload of chunk {chunk_index} is {octet_length} octets, the maximum is {max_payload_octet_size}"
+                ),
+            ));
+        }
         let mut payload = vec![0u8; octet_length as usize];
         stream.read(&mut payload)?;
+        #[cfg(feature = "crc32")]
+        verify_payload_crc32(chunk_index, &payload, stream.read_u32()?)?;
+
+        Ok(Self {
+            chunk_index,
+            codec,
+            payload,
+        })
+    }
+}
+
+/// A chunk of the blob with the payload in a reference counted [`bytes::Bytes`] buffer.
+///
+/// It has the same wire format as [`SetChunkData`], but decoding it with `from_bytes` hands
+/// out a slice of the received buffer as the payload instead of allocating a `Vec<u8>`.
+#[cfg(feature = "bytes")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SetChunkBytesData {
+    pub chunk_index: u64,
+    pub codec: ChunkCodec,
+    pub payload: bytes::Bytes,
+}
+
+#[cfg(feature = "bytes")]
+impl SetChunkBytesData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if the payload is larger than `u32::MAX` octets.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        stream.write_u64(self.chunk_index)?;
+        stream.write_u8(self.codec as u8)?;
+        stream.write_u32(payload_octet_length(&self.payload)?)?;
+        stream.write(&self.payload[..])?;
+        #[cfg(feature = "crc32")]
+        stream.write_u32(crc32fast::hash(&self.payload))?;
+        Ok(())
+    }
+
+    /// Decodes a chunk from the start of `octets`, advancing `octets` past it.
+    ///
+    /// The payload shares the memory of `octets`, so nothing is copied or allocated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::UnexpectedEof` if `octets` is too short, or
+    /// with `ErrorKind::InvalidData` if the codec is unknown or, with the `crc32` feature, if the
+    /// CRC32 trailer does not match the payload.
+    pub fn from_bytes(octets: &mut bytes::Bytes) -> io::Result<Self> {
+        use bytes::Buf;
+
+        const HEADER_OCTET_SIZE: usize = 8 + 1 + 4;
+        if octets.len() < HEADER_OCTET_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "not enough octets for the chunk header",
+            ));
+        }
+        let chunk_index = octets.get_u64();
+        let codec = ChunkCodec::try_from(octets.get_u8())?;
+        let octet_length = octets.get_u32() as usize;
+        if octets.len() < octet_length + PAYLOAD_TRAILER_OCTET_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "expected {octet_length} octets of payload, but only {} remain",
+                    octets.len()
+                ),
+            ));
+        }
+        let payload = octets.split_to(octet_length);
+        #[cfg(feature = "crc32")]
+        verify_payload_crc32(chunk_index, &payload, octets.get_u32())?;
 
         Ok(Self {
             chunk_index,
+            codec,
             payload,
         })
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg(feature = "bytes")]
+impl From<SetChunkBytesData> for SetChunkData {
+    fn from(data: SetChunkBytesData) -> Self {
+        Self {
+            chunk_index: data.chunk_index,
+            codec: data.codec,
+            payload: data.payload.to_vec(),
+        }
+    }
+}
+
+/// The number of octets written after a chunk payload, which is the CRC32 of the payload if the
+/// `crc32` feature is enabled.
+const PAYLOAD_TRAILER_OCTET_SIZE: usize = if cfg!(feature = "crc32") { 4 } else { 0 };
+
+/// Checks that the CRC32 trailer of a chunk matches its payload.
+#[cfg(feature = "crc32")]
+fn verify_payload_crc32(chunk_index: u64, payload: &[u8], expected_crc32: u32) -> io::Result<()> {
+    let crc32 = crc32fast::hash(payload);
+    if crc32 != expected_crc32 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "payload of chunk {chunk_index} has CRC32 {crc32:08x}, but the trailer says {expected_crc32:08x}"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the length prefix for a chunk payload, or an error if it does not fit in the
+/// `u32` length prefix instead of truncating it.
+fn payload_octet_length(payload: &[u8]) -> io::Result<u32> {
+    u32::try_from(payload.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "chunk payload of {} octets is too large for the wire format",
+                payload.len()
+            ),
+        )
+    })
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TransferId(pub u16);
 
+impl fmt::Display for TransferId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u16> for TransferId {
+    fn from(transfer_id: u16) -> Self {
+        Self(transfer_id)
+    }
+}
+
+impl From<TransferId> for u16 {
+    fn from(transfer_id: TransferId) -> Self {
+        transfer_id.0
+    }
+}
+
 impl TransferId {
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
-
     pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
         stream.write_u16(self.0)
     }
@@ -65,19 +313,93 @@ impl TransferId {
 
 // ---------- Receiver
 
+/// Asks the sender to stop sending chunks for the transfer until it is resumed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PauseTransferData {
+    pub transfer_id: u16,
+}
+
+impl PauseTransferData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        stream.write_u16(self.transfer_id)
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            transfer_id: stream.read_u16()?,
+        })
+    }
+}
+
+/// Asks the sender to continue sending chunks for a paused transfer.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResumeTransferData {
+    pub transfer_id: u16,
+}
+
+impl ResumeTransferData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        stream.write_u16(self.transfer_id)
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            transfer_id: stream.read_u16()?,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AckChunkData {
-    pub waiting_for_chunk_index: u32, // first chunk index that remote has not received fully in sequence. (first gap in chunks from the start).
+    pub waiting_for_chunk_index: u64, // first chunk index that remote has not received fully in sequence. (first gap in chunks from the start).
     pub receive_mask_after_last: u64, // receive bit mask for chunks after the `waiting_for_chunk_index`
 }
 
 impl AckChunkData {
+    /// Creates the acknowledgement for the chunks in `received`, e.g. for a custom receiver.
+    ///
+    /// `waiting_for_chunk_index` is the first chunk that has not been received, or the chunk
+    /// count if all have, and `receive_mask_after_last` holds the 64 chunks after it.
+    ///
+    /// # Arguments
+    ///
+    /// * `received` - One bit per chunk, set if the chunk has been received.
+    #[must_use]
+    pub fn from_bit_array(received: &BitArray) -> Self {
+        let waiting_for_chunk_index = received
+            .first_unset_bit()
+            .unwrap_or_else(|| received.bit_count());
+        Self {
+            waiting_for_chunk_index: chunk_index_to_wire(waiting_for_chunk_index),
+            receive_mask_after_last: received.atom_from_index(waiting_for_chunk_index + 1),
+        }
+    }
+
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
     pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
-        stream.write_u32(self.waiting_for_chunk_index)?;
+        stream.write_u64(self.waiting_for_chunk_index)?;
         stream.write_u64(self.receive_mask_after_last)?;
         Ok(())
     }
@@ -88,29 +410,252 @@ impl AckChunkData {
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
     pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
         Ok(Self {
-            waiting_for_chunk_index: stream.read_u32()?,
+            waiting_for_chunk_index: stream.read_u64()?,
             receive_mask_after_last: stream.read_u64()?,
         })
     }
 }
 
+/// The maximum number of octets in an `AckChunkMaskData` receive mask.
+pub const MAX_RECEIVE_MASK_OCTET_SIZE: usize = u16::MAX as usize;
+
+/// Same as `AckChunkData`, but the receive mask can be wider than 64 bits.
+///
+/// Bit `n % 8` of octet `n / 8` in `receive_mask_after_last` is set if the chunk at
+/// `waiting_for_chunk_index + 1 + n` has been received. Chunks past the end of the mask have
+/// not been received. It is sent with its own command, so receivers that only send
+/// `AckChunkData` keep working.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AckChunkMaskData {
+    pub waiting_for_chunk_index: u64, // first chunk index that remote has not received fully in sequence.
+    pub receive_mask_after_last: Vec<u8>, // receive bit mask for chunks after the `waiting_for_chunk_index`
+}
+
+impl AckChunkMaskData {
+    /// Checks if the mask reports the chunk at `chunk_index` as received.
+    ///
+    /// All chunks before `waiting_for_chunk_index` are reported as received.
+    #[must_use]
+    pub fn is_received(&self, chunk_index: u64) -> bool {
+        if chunk_index < self.waiting_for_chunk_index {
+            return true;
+        }
+        if chunk_index == self.waiting_for_chunk_index {
+            return false;
+        }
+        // An index that does not fit in `usize` is far past the end of any mask.
+        usize::try_from(chunk_index - self.waiting_for_chunk_index - 1).is_ok_and(|bit_index| {
+            self.receive_mask_after_last
+                .get(bit_index / 8)
+                .is_some_and(|octet| octet & (1 << (bit_index % 8)) != 0)
+        })
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if the mask is longer than [`MAX_RECEIVE_MASK_OCTET_SIZE`].
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        if self.receive_mask_after_last.len() > MAX_RECEIVE_MASK_OCTET_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "receive mask length {} exceeds the maximum of {MAX_RECEIVE_MASK_OCTET_SIZE}",
+                    self.receive_mask_after_last.len()
+                ),
+            ));
+        }
+        stream.write_u64(self.waiting_for_chunk_index)?;
+        stream.write_u16(self.receive_mask_after_last.len() as u16)?;
+        stream.write(&self.receive_mask_after_last)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        let waiting_for_chunk_index = stream.read_u64()?;
+        let mask_octet_length = stream.read_u16()? as usize;
+        let mut receive_mask_after_last = vec![0u8; mask_octet_length];
+        stream.read(&mut receive_mask_after_last)?;
+
+        Ok(Self {
+            waiting_for_chunk_index,
+            receive_mask_after_last,
+        })
+    }
+}
+
+/// The maximum number of chunk indices in a `RequestChunksData`.
+pub const MAX_REQUESTED_CHUNK_COUNT: usize = 256;
+
+/// Asks the sender to send the listed chunks again right away, instead of waiting for the
+/// resend interval.
+///
+/// The chunk indices are prefixed with their count as a `u16`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestChunksData {
+    pub chunk_indices: Vec<u64>,
+}
+
+impl RequestChunksData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if there are more than [`MAX_REQUESTED_CHUNK_COUNT`] chunk indices.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        if self.chunk_indices.len() > MAX_REQUESTED_CHUNK_COUNT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "requested chunk count {} exceeds the maximum of {MAX_REQUESTED_CHUNK_COUNT}",
+                    self.chunk_indices.len()
+                ),
+            ));
+        }
+        stream.write_u16(self.chunk_indices.len() as u16)?;
+        for chunk_index in &self.chunk_indices {
+            stream.write_u64(*chunk_index)?;
+        }
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if there are more than [`MAX_REQUESTED_CHUNK_COUNT`] chunk indices.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        let chunk_count = stream.read_u16()? as usize;
+        if chunk_count > MAX_REQUESTED_CHUNK_COUNT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "requested chunk count {chunk_count} exceeds the maximum of {MAX_REQUESTED_CHUNK_COUNT}"
+                ),
+            ));
+        }
+        let mut chunk_indices = Vec::with_capacity(chunk_count);
+        for _ in 0..chunk_count {
+            chunk_indices.push(stream.read_u64()?);
+        }
+
+        Ok(Self { chunk_indices })
+    }
+}
+
+/// An `AckChunkData` combined with the flow-control credit of the receiver.
+///
+/// It is sent with its own command, so receivers that only send `AckChunkData` keep working.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AckChunkCreditData {
+    pub ack: AckChunkData,
+    pub credit: u32, // number of chunks, not yet received, that the receiver accepts right now.
+}
+
+impl AckChunkCreditData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        self.ack.to_stream(stream)?;
+        stream.write_u32(self.credit)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            ack: AckChunkData::from_stream(stream)?,
+            credit: stream.read_u32()?,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AckBytesData {
+    pub received_up_to_byte: u64, // number of octets received fully in sequence from the start of the blob.
+}
+
+impl AckBytesData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        stream.write_u64(self.received_up_to_byte)
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            received_up_to_byte: stream.read_u64()?,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StartTransferData {
     pub transfer_id: u16, // Unique transfer_id for this session
     pub total_octet_size: u32,
-    pub chunk_size: u16,
+    pub chunk_size: u32,
+    pub schedule: Vec<u64>, // Advisory order the sender intends to send the chunks in. Can be empty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub blob_hash: Option<[u8; 32]>, // Hash of the complete blob, verified by the receiver when it has a hasher.
 }
 
+/// The maximum number of chunk indices in a `StartTransferData` schedule.
+pub const MAX_SCHEDULE_LENGTH: usize = 256;
+
 impl StartTransferData {
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if the schedule is longer than [`MAX_SCHEDULE_LENGTH`].
     #[allow(clippy::cast_possible_truncation)]
     pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        if self.schedule.len() > MAX_SCHEDULE_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "schedule length {} exceeds the maximum of {MAX_SCHEDULE_LENGTH}",
+                    self.schedule.len()
+                ),
+            ));
+        }
         stream.write_u16(self.transfer_id)?;
         stream.write_u32(self.total_octet_size)?;
-        stream.write_u16(self.chunk_size)?;
+        stream.write_u32(self.chunk_size)?;
+        stream.write_u16(self.schedule.len() as u16)?;
+        for chunk_index in &self.schedule {
+            stream.write_u64(*chunk_index)?;
+        }
+        match &self.blob_hash {
+            Some(blob_hash) => {
+                stream.write_u8(1)?;
+                stream.write(blob_hash)?;
+            }
+            None => stream.write_u8(0)?,
+        }
         Ok(())
     }
 
@@ -121,12 +666,201 @@ impl StartTransferData {
     pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
         let transfer_id = stream.read_u16()?;
         let total_octet_size = stream.read_u32()?;
-        let chunk_size = stream.read_u16()?;
+        let chunk_size = stream.read_u32()?;
+        let schedule_length = stream.read_u16()? as usize;
+        if schedule_length > MAX_SCHEDULE_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "schedule length {schedule_length} exceeds the maximum of {MAX_SCHEDULE_LENGTH}"
+                ),
+            ));
+        }
+        let mut schedule = Vec::with_capacity(schedule_length);
+        for _ in 0..schedule_length {
+            schedule.push(stream.read_u64()?);
+        }
+        let blob_hash = match stream.read_u8()? {
+            0 => None,
+            1 => {
+                let mut blob_hash = [0u8; 32];
+                stream.read(&mut blob_hash)?;
+                Some(blob_hash)
+            }
+            value => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid blob hash marker {value}"),
+                ))
+            }
+        };
 
         Ok(Self {
             transfer_id,
             total_octet_size,
             chunk_size,
+            schedule,
+            blob_hash,
+        })
+    }
+}
+
+/// The maximum number of octets in the hash of a `CompleteTransferData`.
+pub const MAX_HASH_OCTET_SIZE: usize = u8::MAX as usize;
+
+/// Tells the receiver that the sender has sent all chunks, together with a hash of the blob.
+///
+/// The hash is length-prefixed, so any digest size up to [`MAX_HASH_OCTET_SIZE`] is supported.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompleteTransferData {
+    pub transfer_id: u16,
+    pub hash: Vec<u8>,
+}
+
+impl CompleteTransferData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if the hash is longer than [`MAX_HASH_OCTET_SIZE`].
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        if self.hash.len() > MAX_HASH_OCTET_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "hash length {} exceeds the maximum of {MAX_HASH_OCTET_SIZE}",
+                    self.hash.len()
+                ),
+            ));
+        }
+        stream.write_u16(self.transfer_id)?;
+        stream.write_u8(self.hash.len() as u8)?;
+        stream.write(&self.hash)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        let transfer_id = stream.read_u16()?;
+        let hash_octet_length = stream.read_u8()? as usize;
+        let mut hash = vec![0u8; hash_octet_length];
+        stream.read(&mut hash)?;
+
+        Ok(Self { transfer_id, hash })
+    }
+}
+
+/// A hash of the first `up_to_chunk` chunks of the blob, which the sender sends every few
+/// chunks, so the receiver can detect corruption before the whole blob has been received.
+///
+/// The hash is length-prefixed like in [`CompleteTransferData`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashCheckpointData {
+    pub transfer_id: u16,
+    pub up_to_chunk: u64,
+    pub hash: Vec<u8>,
+}
+
+impl HashCheckpointData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if the hash is longer than [`MAX_HASH_OCTET_SIZE`].
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        if self.hash.len() > MAX_HASH_OCTET_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "hash length {} exceeds the maximum of {MAX_HASH_OCTET_SIZE}",
+                    self.hash.len()
+                ),
+            ));
+        }
+        stream.write_u16(self.transfer_id)?;
+        stream.write_u64(self.up_to_chunk)?;
+        stream.write_u8(self.hash.len() as u8)?;
+        stream.write(&self.hash)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        let transfer_id = stream.read_u16()?;
+        let up_to_chunk = stream.read_u64()?;
+        let hash_octet_length = stream.read_u8()? as usize;
+        let mut hash = vec![0u8; hash_octet_length];
+        stream.read(&mut hash)?;
+
+        Ok(Self {
+            transfer_id,
+            up_to_chunk,
+            hash,
+        })
+    }
+}
+
+/// Tells the receiver that the sender is still alive, e.g. while it is rate limited and has no
+/// chunks to send, so the transfer is not expired.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeepaliveData {
+    pub transfer_id: u16,
+}
+
+impl KeepaliveData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        stream.write_u16(self.transfer_id)
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            transfer_id: stream.read_u16()?,
+        })
+    }
+}
+
+/// Tells the receiver that the sender has abandoned the transfer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AbortTransferData {
+    pub transfer_id: u16,
+}
+
+impl AbortTransferData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        stream.write_u16(self.transfer_id)
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            transfer_id: stream.read_u16()?,
         })
     }
 }
@@ -2,23 +2,63 @@
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
-use crate::protocol::{AckChunkData, SetChunkData, StartTransferData, TransferId};
-use flood_rs::{ReadOctetStream, WriteOctetStream};
+use crate::protocol::{
+    AbortTransferData, AckBytesData, AckChunkCreditData, AckChunkData, AckChunkMaskData,
+    CompleteTransferData, HashCheckpointData, KeepaliveData, PauseTransferData, RequestChunksData,
+    ResumeTransferData, SetChunkData, StartTransferData, TransferId,
+};
+use flood_rs::{InOctetStream, OutOctetStream, ReadOctetStream, WriteOctetStream};
 use std::io;
 use std::io::ErrorKind;
 
+/// The version of the wire format, which is written in front of every command.
+///
+/// It must be increased whenever the serialized form of a command changes, so that peers with
+/// different versions reject each other's commands instead of misreading them.
+pub const PROTOCOL_VERSION: u8 = 2;
+
+fn write_protocol_version(stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+    stream.write_u8(PROTOCOL_VERSION)
+}
+
+fn read_protocol_version(stream: &mut dyn ReadOctetStream) -> io::Result<()> {
+    let version = stream.read_u8()?;
+    if version != PROTOCOL_VERSION {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported protocol version {version}, expected {PROTOCOL_VERSION}"),
+        ));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SetChunkFrontData {
     pub transfer_id: TransferId,
     pub data: SetChunkData,
 }
 
 impl SetChunkFrontData {
+    /// Returns the number of octets that the serialized `SetChunk` command for this chunk
+    /// occupies, including the version and command octets.
+    #[must_use]
+    pub const fn command_octet_size(&self) -> usize {
+        Self::command_octet_size_for_payload(self.data.payload.len())
+    }
+
+    /// Returns the number of octets that a serialized `SetChunk` command with a payload of
+    /// `payload_octet_size` octets occupies, including the version and command octets.
+    #[must_use]
+    pub const fn command_octet_size_for_payload(payload_octet_size: usize) -> usize {
+        // version (u8) + command (u8) + transfer_id (u16) + chunk
+        1 + 1 + 2 + SetChunkData::octet_size_for_payload(payload_octet_size)
+    }
+
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
-    #[allow(clippy::cast_possible_truncation)]
     pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
         self.transfer_id.to_stream(stream)?;
         self.data.to_stream(stream)?;
@@ -37,16 +77,185 @@ impl SetChunkFrontData {
     }
 }
 
+/// The maximum number of chunks in a single `SetChunks` command.
+pub const MAX_SET_CHUNKS_COUNT: usize = 256;
+
+/// The maximum number of payload octets, summed over all chunks, in a single `SetChunks` command.
+pub const MAX_SET_CHUNKS_OCTET_SIZE: usize = 1024 * 1024;
+
+/// Several chunks of the same transfer, sent in a single command to save the per-command overhead.
+///
+/// The chunks are prefixed with their count as a `u16`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetChunksFrontData {
+    pub transfer_id: TransferId,
+    pub chunks: Vec<SetChunkData>,
+}
+
+impl SetChunksFrontData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if there are more than [`MAX_SET_CHUNKS_COUNT`] chunks, or more
+    /// than [`MAX_SET_CHUNKS_OCTET_SIZE`] payload octets.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        if self.chunks.len() > MAX_SET_CHUNKS_COUNT {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} chunks exceed the maximum of {MAX_SET_CHUNKS_COUNT} chunks in SetChunks",
+                    self.chunks.len()
+                ),
+            ));
+        }
+        let octet_size: usize = self.chunks.iter().map(|chunk| chunk.payload.len()).sum();
+        if octet_size > MAX_SET_CHUNKS_OCTET_SIZE {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{octet_size} payload octets exceed the maximum of {MAX_SET_CHUNKS_OCTET_SIZE} octets in SetChunks"
+                ),
+            ));
+        }
+
+        self.transfer_id.to_stream(stream)?;
+        stream.write_u16(self.chunks.len() as u16)?;
+        for chunk in &self.chunks {
+            chunk.to_stream(stream)?;
+        }
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error if there are more than [`MAX_SET_CHUNKS_COUNT`] chunks, or more
+    /// than [`MAX_SET_CHUNKS_OCTET_SIZE`] payload octets, before allocating for them.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        let transfer_id = TransferId::from_stream(stream)?;
+        let chunk_count = stream.read_u16()? as usize;
+        if chunk_count > MAX_SET_CHUNKS_COUNT {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{chunk_count} chunks exceed the maximum of {MAX_SET_CHUNKS_COUNT} chunks in SetChunks"
+                ),
+            ));
+        }
+
+        let mut remaining_octet_size = MAX_SET_CHUNKS_OCTET_SIZE;
+        let mut chunks = Vec::with_capacity(chunk_count);
+        for _ in 0..chunk_count {
+            let chunk = SetChunkData::from_stream_with_max_payload(stream, remaining_octet_size)?;
+            remaining_octet_size -= chunk.payload.len();
+            chunks.push(chunk);
+        }
+
+        Ok(Self {
+            transfer_id,
+            chunks,
+        })
+    }
+}
+
+/// Writes octets into a caller supplied buffer, so that a command can be serialized without
+/// allocating.
+struct SliceOctetStream<'a> {
+    octets: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> SliceOctetStream<'a> {
+    const fn new(octets: &'a mut [u8]) -> Self {
+        Self {
+            octets,
+            position: 0,
+        }
+    }
+
+    fn write_octets(&mut self, octets: &[u8]) -> io::Result<()> {
+        let end = self.position + octets.len();
+        let buffer_octet_size = self.octets.len();
+        let target = self.octets.get_mut(self.position..end).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::WriteZero,
+                format!("buffer of {buffer_octet_size} octets is too small for the command"),
+            )
+        })?;
+        target.copy_from_slice(octets);
+        self.position = end;
+        Ok(())
+    }
+}
+
+impl WriteOctetStream for SliceOctetStream<'_> {
+    fn write_u64(&mut self, v: u64) -> io::Result<()> {
+        self.write_octets(&v.to_be_bytes())
+    }
+
+    fn write_i64(&mut self, v: i64) -> io::Result<()> {
+        self.write_octets(&v.to_be_bytes())
+    }
+
+    fn write_u32(&mut self, v: u32) -> io::Result<()> {
+        self.write_octets(&v.to_be_bytes())
+    }
+
+    fn write_i32(&mut self, v: i32) -> io::Result<()> {
+        self.write_octets(&v.to_be_bytes())
+    }
+
+    fn write_u16(&mut self, v: u16) -> io::Result<()> {
+        self.write_octets(&v.to_be_bytes())
+    }
+
+    fn write_i16(&mut self, v: i16) -> io::Result<()> {
+        self.write_octets(&v.to_be_bytes())
+    }
+
+    fn write_u8(&mut self, v: u8) -> io::Result<()> {
+        self.write_octets(&[v])
+    }
+
+    fn write_i8(&mut self, v: i8) -> io::Result<()> {
+        self.write_octets(&v.to_be_bytes())
+    }
+
+    fn write(&mut self, v: &[u8]) -> io::Result<()> {
+        self.write_octets(v)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SenderToReceiverFrontCommands {
     SetChunk(SetChunkFrontData),
     StartTransfer(StartTransferData),
+    /// The sender has abandoned the transfer. The receiver drops it and replies with `AckAbort`.
+    AbortTransfer(AbortTransferData),
+    /// The sender has sent all chunks. The hash lets the receiver verify the blob.
+    CompleteTransfer(CompleteTransferData),
+    /// Several chunks of a transfer, which the receiver applies together.
+    SetChunks(SetChunksFrontData),
+    /// A hash of the first chunks of a transfer, for early corruption detection.
+    HashCheckpoint(HashCheckpointData),
+    /// The sender is still alive, although it has no chunks to send right now.
+    Keepalive(KeepaliveData),
 }
 
 #[repr(u8)]
 enum SenderToReceiverFrontCommand {
     SetChunk = 0x01,
     StartTransfer = 0x02,
+    AbortTransfer = 0x03,
+    CompleteTransfer = 0x04,
+    SetChunks = 0x05,
+    HashCheckpoint = 0x06,
+    Keepalive = 0x07,
 }
 
 impl TryFrom<u8> for SenderToReceiverFrontCommand {
@@ -56,6 +265,11 @@ impl TryFrom<u8> for SenderToReceiverFrontCommand {
         match value {
             0x01 => Ok(Self::SetChunk),
             0x02 => Ok(Self::StartTransfer),
+            0x03 => Ok(Self::AbortTransfer),
+            0x04 => Ok(Self::CompleteTransfer),
+            0x05 => Ok(Self::SetChunks),
+            0x06 => Ok(Self::HashCheckpoint),
+            0x07 => Ok(Self::Keepalive),
             _ => Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 format!("Unknown command {value}"),
@@ -65,32 +279,106 @@ impl TryFrom<u8> for SenderToReceiverFrontCommand {
 }
 
 impl SenderToReceiverFrontCommands {
+    /// Returns the id of the transfer that the command is for.
+    #[must_use]
+    pub const fn transfer_id(&self) -> u16 {
+        match self {
+            Self::SetChunk(set_chunk) => set_chunk.transfer_id.0,
+            Self::StartTransfer(transfer_data) => transfer_data.transfer_id,
+            Self::AbortTransfer(abort_data) => abort_data.transfer_id,
+            Self::CompleteTransfer(complete_data) => complete_data.transfer_id,
+            Self::SetChunks(set_chunks) => set_chunks.transfer_id.0,
+            Self::HashCheckpoint(checkpoint_data) => checkpoint_data.transfer_id,
+            Self::Keepalive(keepalive_data) => keepalive_data.transfer_id,
+        }
+    }
+
     #[must_use]
     pub const fn to_octet(&self) -> u8 {
         match self {
             Self::SetChunk(_) => SenderToReceiverFrontCommand::SetChunk as u8,
             Self::StartTransfer(_) => SenderToReceiverFrontCommand::StartTransfer as u8,
+            Self::AbortTransfer(_) => SenderToReceiverFrontCommand::AbortTransfer as u8,
+            Self::CompleteTransfer(_) => SenderToReceiverFrontCommand::CompleteTransfer as u8,
+            Self::SetChunks(_) => SenderToReceiverFrontCommand::SetChunks as u8,
+            Self::HashCheckpoint(_) => SenderToReceiverFrontCommand::HashCheckpoint as u8,
+            Self::Keepalive(_) => SenderToReceiverFrontCommand::Keepalive as u8,
         }
     }
 
+    /// Writes the [`PROTOCOL_VERSION`], the command octet and the command.
+    ///
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
-
     pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        write_protocol_version(stream)?;
         stream.write_u8(self.to_octet())?;
         match self {
             Self::SetChunk(set_chunk_header) => set_chunk_header.to_stream(stream),
             Self::StartTransfer(transfer_data) => transfer_data.to_stream(stream),
+            Self::AbortTransfer(abort_data) => abort_data.to_stream(stream),
+            Self::CompleteTransfer(complete_data) => complete_data.to_stream(stream),
+            Self::SetChunks(set_chunks) => set_chunks.to_stream(stream),
+            Self::HashCheckpoint(checkpoint_data) => checkpoint_data.to_stream(stream),
+            Self::Keepalive(keepalive_data) => keepalive_data.to_stream(stream),
         }
     }
 
+    /// Serializes the command into `octets`, e.g. a buffer that is reused for every datagram,
+    /// without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `octets` - The buffer to write the command into, from the start.
+    ///
+    /// # Returns
+    ///
+    /// The number of octets written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::WriteZero` if `octets` is too small for the
+    /// command, in which case the contents of `octets` are unspecified. Also returns the same
+    /// errors as `to_stream`.
+    pub fn write_to_slice(&self, octets: &mut [u8]) -> io::Result<usize> {
+        let mut stream = SliceOctetStream::new(octets);
+        self.to_stream(&mut stream)?;
+        Ok(stream.position)
+    }
+
+    /// Serializes the command into a new `Vec`, like `to_stream` into an [`OutOctetStream`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `to_stream`, e.g. for a command that exceeds a protocol limit.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut stream = OutOctetStream::new();
+        self.to_stream(&mut stream)?;
+        Ok(stream.octets())
+    }
+
+    /// Reads a command written by `to_bytes` or `to_stream` from the start of `octets`. Any
+    /// octets after the command are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `from_stream`.
+    pub fn from_bytes(octets: &[u8]) -> io::Result<Self> {
+        Self::from_stream(&mut InOctetStream::new(octets))
+    }
+
+    /// Reads a command written by `to_stream`.
+    ///
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error with `ErrorKind::InvalidData` if the command was written with
+    /// another [`PROTOCOL_VERSION`].
     pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        read_protocol_version(stream)?;
         let command_value = stream.read_u8()?;
         let command = SenderToReceiverFrontCommand::try_from(command_value)?;
         let x = match command {
@@ -100,6 +388,21 @@ impl SenderToReceiverFrontCommands {
             SenderToReceiverFrontCommand::StartTransfer => {
                 Self::StartTransfer(StartTransferData::from_stream(stream)?)
             }
+            SenderToReceiverFrontCommand::AbortTransfer => {
+                Self::AbortTransfer(AbortTransferData::from_stream(stream)?)
+            }
+            SenderToReceiverFrontCommand::CompleteTransfer => {
+                Self::CompleteTransfer(CompleteTransferData::from_stream(stream)?)
+            }
+            SenderToReceiverFrontCommand::SetChunks => {
+                Self::SetChunks(SetChunksFrontData::from_stream(stream)?)
+            }
+            SenderToReceiverFrontCommand::HashCheckpoint => {
+                Self::HashCheckpoint(HashCheckpointData::from_stream(stream)?)
+            }
+            SenderToReceiverFrontCommand::Keepalive => {
+                Self::Keepalive(KeepaliveData::from_stream(stream)?)
+            }
         };
         Ok(x)
     }
@@ -109,6 +412,15 @@ impl SenderToReceiverFrontCommands {
 enum ReceiverToSenderFrontCommand {
     AckChunk = 0x02,
     AckStart = 0x03,
+    Completed = 0x04,
+    AckBytes = 0x05,
+    AckAbort = 0x06,
+    Pause = 0x07,
+    Resume = 0x08,
+    NeedStart = 0x09,
+    AckChunkCredit = 0x0A,
+    AckChunkMask = 0x0B,
+    RequestChunks = 0x0C,
 }
 
 impl TryFrom<u8> for ReceiverToSenderFrontCommand {
@@ -118,6 +430,15 @@ impl TryFrom<u8> for ReceiverToSenderFrontCommand {
         match value {
             0x02 => Ok(Self::AckChunk),
             0x03 => Ok(Self::AckStart),
+            0x04 => Ok(Self::Completed),
+            0x05 => Ok(Self::AckBytes),
+            0x06 => Ok(Self::AckAbort),
+            0x07 => Ok(Self::Pause),
+            0x08 => Ok(Self::Resume),
+            0x09 => Ok(Self::NeedStart),
+            0x0A => Ok(Self::AckChunkCredit),
+            0x0B => Ok(Self::AckChunkMask),
+            0x0C => Ok(Self::RequestChunks),
             _ => Err(io::Error::new(
                 ErrorKind::InvalidData,
                 format!("Unknown command {value}"),
@@ -127,6 +448,7 @@ impl TryFrom<u8> for ReceiverToSenderFrontCommand {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AckChunkFrontData {
     pub transfer_id: TransferId,
     pub data: AckChunkData,
@@ -156,37 +478,265 @@ impl AckChunkFrontData {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AckChunkCreditFrontData {
+    pub transfer_id: TransferId,
+    pub data: AckChunkCreditData,
+}
+
+impl AckChunkCreditFrontData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        self.transfer_id.to_stream(stream)?;
+        self.data.to_stream(stream)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            transfer_id: TransferId::from_stream(stream)?,
+            data: AckChunkCreditData::from_stream(stream)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AckChunkMaskFrontData {
+    pub transfer_id: TransferId,
+    pub data: AckChunkMaskData,
+}
+
+impl AckChunkMaskFrontData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        self.transfer_id.to_stream(stream)?;
+        self.data.to_stream(stream)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            transfer_id: TransferId::from_stream(stream)?,
+            data: AckChunkMaskData::from_stream(stream)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestChunksFrontData {
+    pub transfer_id: TransferId,
+    pub data: RequestChunksData,
+}
+
+impl RequestChunksFrontData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        self.transfer_id.to_stream(stream)?;
+        self.data.to_stream(stream)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            transfer_id: TransferId::from_stream(stream)?,
+            data: RequestChunksData::from_stream(stream)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AckBytesFrontData {
+    pub transfer_id: TransferId,
+    pub data: AckBytesData,
+}
+
+impl AckBytesFrontData {
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        self.transfer_id.to_stream(stream)?;
+        self.data.to_stream(stream)?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if there is an issue with writing to the stream.
+    /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        Ok(Self {
+            transfer_id: TransferId::from_stream(stream)?,
+            data: AckBytesData::from_stream(stream)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReceiverToSenderFrontCommands {
     AckChunk(AckChunkFrontData),
     AckStart(u16),
+    /// Sent once when the receiver has received the complete blob for the transfer.
+    /// The sender can release everything related to the transfer.
+    Completed(u16),
+    AckBytes(AckBytesFrontData),
+    /// Acknowledges that the receiver has dropped an aborted transfer.
+    AckAbort(u16),
+    /// Asks the sender to stop sending chunks for the transfer.
+    Pause(PauseTransferData),
+    /// Asks the sender to continue sending chunks for a paused transfer.
+    Resume(ResumeTransferData),
+    /// Asks the sender to resend `StartTransfer`, since chunks arrived for a transfer that the
+    /// receiver does not know about.
+    NeedStart(u16),
+    /// Same as `AckChunk`, but also tells the sender how many more chunks it may send.
+    AckChunkCredit(AckChunkCreditFrontData),
+    /// Same as `AckChunk`, but with a receive mask that can cover more than 64 chunks.
+    AckChunkMask(AckChunkMaskFrontData),
+    /// Asks the sender to send the listed chunks again right away, since the receiver knows
+    /// that they are missing.
+    RequestChunks(RequestChunksFrontData),
 }
 
 impl ReceiverToSenderFrontCommands {
+    /// Returns the id of the transfer that the command is for.
+    #[must_use]
+    pub const fn transfer_id(&self) -> u16 {
+        match self {
+            Self::AckChunk(ack) => ack.transfer_id.0,
+            Self::AckBytes(ack_bytes) => ack_bytes.transfer_id.0,
+            Self::AckChunkCredit(ack_credit) => ack_credit.transfer_id.0,
+            Self::AckChunkMask(ack_mask) => ack_mask.transfer_id.0,
+            Self::RequestChunks(request) => request.transfer_id.0,
+            Self::Pause(pause_data) => pause_data.transfer_id,
+            Self::Resume(resume_data) => resume_data.transfer_id,
+            Self::AckStart(transfer_id)
+            | Self::Completed(transfer_id)
+            | Self::AckAbort(transfer_id)
+            | Self::NeedStart(transfer_id) => *transfer_id,
+        }
+    }
+
     #[must_use]
     pub const fn to_octet(&self) -> u8 {
         match self {
             Self::AckChunk(_) => ReceiverToSenderFrontCommand::AckChunk as u8,
             Self::AckStart(_) => ReceiverToSenderFrontCommand::AckStart as u8,
+            Self::Completed(_) => ReceiverToSenderFrontCommand::Completed as u8,
+            Self::AckBytes(_) => ReceiverToSenderFrontCommand::AckBytes as u8,
+            Self::AckAbort(_) => ReceiverToSenderFrontCommand::AckAbort as u8,
+            Self::Pause(_) => ReceiverToSenderFrontCommand::Pause as u8,
+            Self::Resume(_) => ReceiverToSenderFrontCommand::Resume as u8,
+            Self::NeedStart(_) => ReceiverToSenderFrontCommand::NeedStart as u8,
+            Self::AckChunkCredit(_) => ReceiverToSenderFrontCommand::AckChunkCredit as u8,
+            Self::AckChunkMask(_) => ReceiverToSenderFrontCommand::AckChunkMask as u8,
+            Self::RequestChunks(_) => ReceiverToSenderFrontCommand::RequestChunks as u8,
         }
     }
 
+    /// Writes the [`PROTOCOL_VERSION`], the command octet and the command.
+    ///
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
     pub fn to_stream(&self, stream: &mut dyn WriteOctetStream) -> io::Result<()> {
+        write_protocol_version(stream)?;
         stream.write_u8(self.to_octet())?;
         match self {
             Self::AckChunk(set_chunk_header) => set_chunk_header.to_stream(stream),
-            Self::AckStart(transfer_id) => stream.write_u16(*transfer_id),
+            Self::AckStart(transfer_id)
+            | Self::Completed(transfer_id)
+            | Self::AckAbort(transfer_id)
+            | Self::NeedStart(transfer_id) => stream.write_u16(*transfer_id),
+            Self::AckBytes(ack_bytes) => ack_bytes.to_stream(stream),
+            Self::Pause(pause_data) => pause_data.to_stream(stream),
+            Self::Resume(resume_data) => resume_data.to_stream(stream),
+            Self::AckChunkCredit(ack_credit) => ack_credit.to_stream(stream),
+            Self::AckChunkMask(ack_mask) => ack_mask.to_stream(stream),
+            Self::RequestChunks(request) => request.to_stream(stream),
         }
     }
 
+    /// Serializes the command into `octets`, e.g. a buffer that is reused for every datagram,
+    /// without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `octets` - The buffer to write the command into, from the start.
+    ///
+    /// # Returns
+    ///
+    /// The number of octets written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` with `ErrorKind::WriteZero` if `octets` is too small for the
+    /// command, in which case the contents of `octets` are unspecified. Also returns the same
+    /// errors as `to_stream`.
+    pub fn write_to_slice(&self, octets: &mut [u8]) -> io::Result<usize> {
+        let mut stream = SliceOctetStream::new(octets);
+        self.to_stream(&mut stream)?;
+        Ok(stream.position)
+    }
+
+    /// Serializes the command into a new `Vec`, like `to_stream` into an [`OutOctetStream`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `to_stream`, e.g. for a command that exceeds a protocol limit.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut stream = OutOctetStream::new();
+        self.to_stream(&mut stream)?;
+        Ok(stream.octets())
+    }
+
+    /// Reads a command written by `to_bytes` or `to_stream` from the start of `octets`. Any
+    /// octets after the command are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `from_stream`.
+    pub fn from_bytes(octets: &[u8]) -> io::Result<Self> {
+        Self::from_stream(&mut InOctetStream::new(octets))
+    }
+
+    /// Reads a command written by `to_stream`.
+    ///
     /// # Errors
     ///
     /// This function will return an `io::Error` if there is an issue with writing to the stream.
     /// This could happen if the stream is closed or if there are underlying I/O errors during the write operation.
+    /// It also returns an error with `ErrorKind::InvalidData` if the command was written with
+    /// another [`PROTOCOL_VERSION`].
     pub fn from_stream(stream: &mut dyn ReadOctetStream) -> io::Result<Self> {
+        read_protocol_version(stream)?;
         let command_value = stream.read_u8()?;
         let command = ReceiverToSenderFrontCommand::try_from(command_value)?;
         let x = match command {
@@ -195,6 +745,27 @@ impl ReceiverToSenderFrontCommands {
                 data: AckChunkData::from_stream(stream)?,
             }),
             ReceiverToSenderFrontCommand::AckStart => Self::AckStart(stream.read_u16()?),
+            ReceiverToSenderFrontCommand::Completed => Self::Completed(stream.read_u16()?),
+            ReceiverToSenderFrontCommand::AckBytes => {
+                Self::AckBytes(AckBytesFrontData::from_stream(stream)?)
+            }
+            ReceiverToSenderFrontCommand::AckAbort => Self::AckAbort(stream.read_u16()?),
+            ReceiverToSenderFrontCommand::NeedStart => Self::NeedStart(stream.read_u16()?),
+            ReceiverToSenderFrontCommand::AckChunkCredit => {
+                Self::AckChunkCredit(AckChunkCreditFrontData::from_stream(stream)?)
+            }
+            ReceiverToSenderFrontCommand::AckChunkMask => {
+                Self::AckChunkMask(AckChunkMaskFrontData::from_stream(stream)?)
+            }
+            ReceiverToSenderFrontCommand::RequestChunks => {
+                Self::RequestChunks(RequestChunksFrontData::from_stream(stream)?)
+            }
+            ReceiverToSenderFrontCommand::Pause => {
+                Self::Pause(PauseTransferData::from_stream(stream)?)
+            }
+            ReceiverToSenderFrontCommand::Resume => {
+                Self::Resume(ResumeTransferData::from_stream(stream)?)
+            }
         };
         Ok(x)
     }
@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! A compact encoding of the complete set of received chunks.
+//!
+//! The first octet tells how the set is encoded, and the smaller of the two encodings is used:
+//!
+//! * `0x00` - Packed: bit `n % 8` of octet `n / 8` is set if chunk `n` has been received.
+//! * `0x01` - Runs: the lengths of alternating runs of received and missing chunks, starting
+//!   with a (possibly empty) run of received chunks. Each length is a LEB128 varint and the
+//!   lengths add up to the chunk count.
+use bit_array_rs::BitArray;
+use std::io;
+use std::io::ErrorKind;
+
+const PACKED: u8 = 0x00;
+const RUNS: u8 = 0x01;
+
+/// Encodes the chunks that are set in `received`.
+///
+/// # Arguments
+///
+/// * `received` - One bit per chunk, set if the chunk has been received.
+///
+/// # Returns
+///
+/// The encoded set, which can be decoded with [`decode`].
+#[must_use]
+pub fn encode(received: &BitArray) -> Vec<u8> {
+    let chunk_count = received.bit_count();
+
+    let mut packed = vec![PACKED];
+    packed.resize(1 + chunk_count.div_ceil(8), 0);
+    for chunk_index in (0..chunk_count).filter(|&chunk_index| received.get(chunk_index)) {
+        packed[1 + chunk_index / 8] |= 1 << (chunk_index % 8);
+    }
+
+    let mut runs = vec![RUNS];
+    let mut run_is_received = true;
+    let mut chunk_index = 0;
+    while chunk_index < chunk_count {
+        let run_length = (chunk_index..chunk_count)
+            .take_while(|&index| received.get(index) == run_is_received)
+            .count();
+        write_varint(&mut runs, run_length);
+        chunk_index += run_length;
+        run_is_received = !run_is_received;
+    }
+
+    if runs.len() < packed.len() {
+        runs
+    } else {
+        packed
+    }
+}
+
+/// Decodes a set of received chunks that was encoded with [`encode`].
+///
+/// # Arguments
+///
+/// * `octets` - The encoded set.
+/// * `chunk_count` - The number of chunks in the blob.
+///
+/// # Errors
+///
+/// Returns an `io::Error` with `ErrorKind::InvalidData` if the encoding is unknown or does not
+/// describe exactly `chunk_count` chunks.
+pub fn decode(octets: &[u8], chunk_count: usize) -> io::Result<BitArray> {
+    let mut received = BitArray::new(chunk_count);
+    match octets.split_first() {
+        Some((&PACKED, packed)) => {
+            if packed.len() != chunk_count.div_ceil(8) {
+                return Err(invalid_data(format!(
+                    "packed received set of {} octets does not match {chunk_count} chunks",
+                    packed.len()
+                )));
+            }
+            for chunk_index in 0..chunk_count {
+                if packed[chunk_index / 8] & (1 << (chunk_index % 8)) != 0 {
+                    received.set(chunk_index);
+                }
+            }
+            if !chunk_count.is_multiple_of(8) && packed[chunk_count / 8] >> (chunk_count % 8) != 0 {
+                return Err(invalid_data(format!(
+                    "packed received set has chunks past the chunk count {chunk_count}"
+                )));
+            }
+        }
+        Some((&RUNS, mut runs)) => {
+            let mut run_is_received = true;
+            let mut chunk_index: usize = 0;
+            while !runs.is_empty() {
+                let run_length = read_varint(&mut runs)?;
+                let end_chunk_index = chunk_index.saturating_add(run_length);
+                if end_chunk_index > chunk_count {
+                    return Err(invalid_data(format!(
+                        "received set runs are longer than the chunk count {chunk_count}"
+                    )));
+                }
+                if run_is_received {
+                    for index in chunk_index..end_chunk_index {
+                        received.set(index);
+                    }
+                }
+                chunk_index = end_chunk_index;
+                run_is_received = !run_is_received;
+            }
+            if chunk_index != chunk_count {
+                return Err(invalid_data(format!(
+                    "received set runs cover {chunk_index} chunks, expected {chunk_count}"
+                )));
+            }
+        }
+        Some((tag, _)) => return Err(invalid_data(format!("unknown received set encoding {tag}"))),
+        None => return Err(invalid_data("received set is empty".to_string())),
+    }
+
+    Ok(received)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn write_varint(octets: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        octets.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    octets.push(value as u8);
+}
+
+fn read_varint(octets: &mut &[u8]) -> io::Result<usize> {
+    let mut value = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let Some((&octet, rest)) = octets.split_first() else {
+            return Err(invalid_data("received set run is truncated".to_string()));
+        };
+        *octets = rest;
+        value |= usize::from(octet & 0x7f)
+            .checked_shl(shift)
+            .filter(|shifted| shifted >> shift == usize::from(octet & 0x7f))
+            .ok_or_else(|| invalid_data("received set run is too long".to_string()))?;
+        if octet & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("received set run is too long".to_string()))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,181 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::err::BlobError;
+use alloc::vec::Vec;
+
+/// Memory that holds the octets of a blob while it is being received.
+///
+/// The length of the storage is the total octet count of the blob.
+pub trait BlobStorage {
+    /// Returns all octets in the storage.
+    fn octets(&self) -> &[u8];
+
+    /// Returns all octets in the storage for writing.
+    fn octets_mut(&mut self) -> &mut [u8];
+
+    /// Returns the total octet count of the blob.
+    fn octet_count(&self) -> usize {
+        self.octets().len()
+    }
+
+    /// Checks if the written octets can be read back with [`Self::octets`]. A storage that
+    /// passes the octets on, like `WriterStorage`, can not.
+    fn is_readable(&self) -> bool {
+        true
+    }
+
+    /// Writes `octets` into the storage, starting at `octet_offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BlobError` if the octets could not be written.
+    fn write_at(&mut self, octet_offset: usize, octets: &[u8]) -> Result<(), BlobError> {
+        self.octets_mut()[octet_offset..octet_offset + octets.len()].copy_from_slice(octets);
+        Ok(())
+    }
+}
+
+impl BlobStorage for Vec<u8> {
+    fn octets(&self) -> &[u8] {
+        self
+    }
+
+    fn octets_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+pub use writer::WriterStorage;
+
+#[cfg(feature = "std")]
+mod writer {
+    use crate::err::BlobError;
+    use crate::storage::BlobStorage;
+    use core::fmt;
+    use std::io::{Seek, SeekFrom, Write};
+
+    /// A [`BlobStorage`] that writes each received chunk to a [`Write`] + [`Seek`] sink, e.g.
+    /// a file, instead of keeping the blob in memory.
+    ///
+    /// The octets can not be read back, so a `BlobStreamIn` with this storage tracks which
+    /// chunks have been received, but does not return the blob or the received chunks.
+    pub struct WriterStorage<W> {
+        writer: W,
+        octet_count: usize,
+    }
+
+    impl<W: Write + Seek> WriterStorage<W> {
+        /// Creates a storage for a blob of `octet_count` octets that is written to `writer`.
+        #[must_use]
+        pub const fn new(writer: W, octet_count: usize) -> Self {
+            Self {
+                writer,
+                octet_count,
+            }
+        }
+
+        /// Returns the sink.
+        #[must_use]
+        pub const fn writer(&self) -> &W {
+            &self.writer
+        }
+
+        /// Returns the sink, e.g. to flush it after the blob is complete.
+        pub fn into_writer(self) -> W {
+            self.writer
+        }
+    }
+
+    impl<W> fmt::Debug for WriterStorage<W> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("WriterStorage")
+                .field("octet_count", &self.octet_count)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<W: Write + Seek> BlobStorage for WriterStorage<W> {
+        fn octets(&self) -> &[u8] {
+            &[]
+        }
+
+        fn octets_mut(&mut self) -> &mut [u8] {
+            &mut []
+        }
+
+        fn octet_count(&self) -> usize {
+            self.octet_count
+        }
+
+        fn is_readable(&self) -> bool {
+            false
+        }
+
+        fn write_at(&mut self, octet_offset: usize, octets: &[u8]) -> Result<(), BlobError> {
+            self.writer
+                .seek(SeekFrom::Start(octet_offset as u64))
+                .and_then(|_| self.writer.write_all(octets))
+                .map_err(BlobError::Io)
+        }
+    }
+}
+
+#[cfg(feature = "memmap")]
+pub use mmap::MmapStorage;
+
+#[cfg(feature = "memmap")]
+mod mmap {
+    use crate::storage::BlobStorage;
+    use memmap2::MmapMut;
+    use std::fs::File;
+    use std::io;
+
+    /// A [`BlobStorage`] backed by a memory-mapped file.
+    ///
+    /// Received chunks are written directly into the mapping, so the completed
+    /// blob ends up in the file without any intermediate copy.
+    #[derive(Debug)]
+    pub struct MmapStorage {
+        mmap: MmapMut,
+    }
+
+    impl MmapStorage {
+        /// Resizes `file` to `octet_count` octets and maps it into memory.
+        ///
+        /// The file must be opened for both reading and writing, and must not be
+        /// modified by anyone else while it is mapped.
+        ///
+        /// # Errors
+        ///
+        /// Returns an `io::Error` if the file could not be resized or mapped.
+        pub fn new(file: &File, octet_count: usize) -> io::Result<Self> {
+            file.set_len(octet_count as u64)?;
+            // SAFETY: The caller guarantees that the file is not modified externally
+            // while the mapping is alive, as documented above.
+            let mmap = unsafe { MmapMut::map_mut(file)? };
+            Ok(Self { mmap })
+        }
+
+        /// Flushes outstanding changes in the mapping to the file.
+        ///
+        /// # Errors
+        ///
+        /// Returns an `io::Error` if the changes could not be written to the file.
+        pub fn flush(&self) -> io::Result<()> {
+            self.mmap.flush()
+        }
+    }
+
+    impl BlobStorage for MmapStorage {
+        fn octets(&self) -> &[u8] {
+            &self.mmap
+        }
+
+        fn octets_mut(&mut self) -> &mut [u8] {
+            &mut self.mmap
+        }
+    }
+}
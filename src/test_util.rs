@@ -0,0 +1,70 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! Helpers for testing a transfer without a network, enabled with the `test-util` feature.
+use crate::millis::Millis;
+use crate::{chunk_index_from_wire, in_logic, out_logic, ChunkIndex};
+use std::io;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// How far the simulated clock advances between two rounds of [`drive_to_completion`]. It is
+/// longer than the default resend interval, so chunks that were lost are resent every round.
+pub const DRIVE_ROUND_DURATION: Duration = Duration::from_secs(1);
+
+/// The number of rounds after which [`drive_to_completion`] gives up.
+pub const MAX_DRIVE_ROUND_COUNT: usize = 10_000;
+
+/// Pumps chunks from `out_logic` to `in_logic` and acknowledgements back, until both sides
+/// are complete.
+///
+/// Each round advances a simulated clock by [`DRIVE_ROUND_DURATION`], sends the chunks that are
+/// due and acknowledges the chunks that arrived, so the result does not depend on the wall
+/// clock.
+///
+/// # Arguments
+///
+/// * `out_logic` - The sending side.
+/// * `in_logic` - The receiving side, for a blob of the same size and chunk size.
+/// * `loss` - Called with the index of each sent chunk, and returns `true` to drop it.
+///
+/// # Returns
+///
+/// The number of rounds it took to complete the transfer.
+///
+/// # Errors
+///
+/// Returns the errors of the receiving or sending side, or an `io::Error` with
+/// `ErrorKind::TimedOut` if the transfer is not complete after [`MAX_DRIVE_ROUND_COUNT`] rounds.
+pub fn drive_to_completion(
+    out_logic: &mut out_logic::Logic,
+    in_logic: &mut in_logic::Logic,
+    mut loss: impl FnMut(ChunkIndex) -> bool,
+) -> io::Result<usize> {
+    let mut now = Millis::default();
+    for round_count in 0..MAX_DRIVE_ROUND_COUNT {
+        if out_logic.is_complete() && in_logic.is_complete() {
+            return Ok(round_count);
+        }
+        now += DRIVE_ROUND_DURATION;
+
+        let chunks = out_logic.send(now, out_logic.chunk_count());
+        let mut is_acked = false;
+        for chunk in chunks
+            .iter()
+            .filter(|chunk| !loss(chunk_index_from_wire(chunk.chunk_index)))
+        {
+            in_logic.update_with_outcome(chunk)?;
+            is_acked = true;
+        }
+        if is_acked {
+            out_logic.receive(&in_logic.ack())?;
+        }
+    }
+    Err(io::Error::new(
+        ErrorKind::TimedOut,
+        format!("transfer is not complete after {MAX_DRIVE_ROUND_COUNT} rounds"),
+    ))
+}
@@ -0,0 +1,53 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use core::time::Duration;
+
+/// Calculates the number of chunks that needs to be in flight to fill the link,
+/// based on the bandwidth-delay product.
+///
+/// # Arguments
+///
+/// * `rtt` - The round-trip time of the link.
+/// * `bandwidth_bytes_per_sec` - The bandwidth of the link in octets per second.
+/// * `chunk_size` - The size of each chunk in octets.
+///
+/// # Panics
+///
+/// Will panic if `chunk_size` is zero.
+///
+/// # Returns
+///
+/// The window size in chunks, rounded up so the link is filled. It is always at least one.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use blob_stream::window::window_for_bdp;
+///
+/// let window = window_for_bdp(Duration::from_millis(100), 1_000_000.0, 1000);
+/// assert_eq!(window, 100);
+/// ```
+#[must_use]
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+pub fn window_for_bdp(rtt: Duration, bandwidth_bytes_per_sec: f64, chunk_size: usize) -> usize {
+    assert!(chunk_size > 0, "chunk_size must be greater than zero");
+
+    let bdp_octet_count = rtt.as_secs_f64() * bandwidth_bytes_per_sec;
+    let chunk_count = bdp_octet_count / chunk_size as f64;
+
+    let whole_chunk_count = chunk_count as usize;
+    let rounded_up_chunk_count = if (whole_chunk_count as f64) < chunk_count {
+        whole_chunk_count + 1
+    } else {
+        whole_chunk_count
+    };
+
+    rounded_up_chunk_count.max(1)
+}
@@ -0,0 +1,61 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(all(feature = "std", feature = "bytes"))]
+
+use blob_stream::in_stream::BlobStreamIn;
+use blob_stream::prelude::*;
+use blob_stream::protocol::SetChunkBytesData;
+use bytes::Bytes;
+use flood_rs::OutOctetStream;
+
+#[test]
+fn set_chunk_bytes_matches_slice_path() {
+    let mut slice_stream = BlobStreamIn::new(9, 4);
+    let mut bytes_stream = BlobStreamIn::new(9, 4);
+
+    for (chunk_index, payload) in [(2, &[0x42][..]), (0, &[1, 2, 3, 4]), (1, &[5, 6, 7, 8])] {
+        slice_stream
+            .set_chunk(chunk_index, payload)
+            .expect("slice chunk should be accepted");
+        bytes_stream
+            .set_chunk_bytes(chunk_index, Bytes::copy_from_slice(payload))
+            .expect("bytes chunk should be accepted");
+    }
+
+    assert_eq!(bytes_stream.blob(), slice_stream.blob());
+    assert!(matches!(
+        bytes_stream.set_chunk_bytes(0, Bytes::from_static(&[1, 2, 3, 4])),
+        Err(BlobError::RedundantSameContents(0))
+    ));
+}
+
+#[test]
+fn decode_shares_the_received_buffer() {
+    let set_chunk = SetChunkData {
+        chunk_index: 3,
+        codec: ChunkCodec::Stored,
+        payload: vec![0xaa, 0xbb, 0xcc],
+    };
+    let mut out_stream = OutOctetStream::new();
+    set_chunk
+        .to_stream(&mut out_stream)
+        .expect("should be able to write chunk");
+
+    let received = Bytes::from(out_stream.octets());
+    let mut octets = received.clone();
+    let decoded = SetChunkBytesData::from_bytes(&mut octets).expect("should decode chunk");
+
+    assert!(octets.is_empty());
+    assert_eq!(decoded.chunk_index, 3);
+    assert_eq!(decoded.payload.as_ptr(), received[13..].as_ptr());
+    assert_eq!(SetChunkData::from(decoded), set_chunk);
+}
+
+#[test]
+fn decode_truncated_chunk() {
+    let mut octets = Bytes::from_static(&[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 5, 0xaa]);
+    let err = SetChunkBytesData::from_bytes(&mut octets).expect_err("payload is truncated");
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
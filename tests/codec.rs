@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "std")]
+
+use blob_stream::in_logic;
+use blob_stream::out_logic;
+use blob_stream::prelude::*;
+use flood_rs::{InOctetStream, OutOctetStream};
+
+#[test]
+fn run_length_round_trip() {
+    let octets: Vec<u8> = [vec![0; 300], vec![7, 7, 8], vec![0xff; 2]].concat();
+
+    let encoded = ChunkCodec::RunLength.encode(&octets);
+
+    assert_eq!(encoded, [255, 0, 45, 0, 2, 7, 1, 8, 2, 0xff]);
+    assert_eq!(
+        ChunkCodec::RunLength
+            .decode(&encoded, octets.len())
+            .expect("should decode"),
+        &octets[..]
+    );
+}
+
+#[test]
+fn run_length_payload_larger_than_chunk_is_rejected() {
+    let encoded = ChunkCodec::RunLength.encode(&[0; 300]);
+
+    ChunkCodec::RunLength
+        .decode(&encoded, 299)
+        .expect_err("payload decodes to more octets than the chunk");
+    ChunkCodec::RunLength
+        .decode(&[255, 0].repeat(1000), 1024)
+        .expect_err("decoding must stop at the limit");
+}
+
+#[test]
+fn invalid_run_length_payload() {
+    ChunkCodec::RunLength
+        .decode(&[3, 1, 2], 16)
+        .expect_err("payload must be pairs");
+    ChunkCodec::RunLength
+        .decode(&[0, 1], 16)
+        .expect_err("runs can not be empty");
+}
+
+#[test]
+fn incompressible_chunk_is_stored() {
+    let octets = [1, 2, 3, 4];
+
+    let chunk = SetChunkData::encode(5, &octets, ChunkCodec::RunLength);
+
+    assert_eq!(chunk.codec, ChunkCodec::Stored);
+    assert_eq!(chunk.payload, octets);
+}
+
+#[test]
+fn transfer_mixes_compressed_and_stored_chunks() {
+    let blob: Vec<u8> = [vec![0; 64], (0..64).collect()].concat();
+    let mut sender =
+        out_logic::Logic::new(blob.clone(), 32).with_chunk_codec(ChunkCodec::RunLength);
+    let mut receiver = in_logic::Logic::new(blob.len(), 32);
+
+    let chunks = sender.send(Millis::new(0), 10);
+    let codecs: Vec<ChunkCodec> = chunks.iter().map(|chunk| chunk.codec).collect();
+    assert_eq!(
+        codecs,
+        [
+            ChunkCodec::RunLength,
+            ChunkCodec::RunLength,
+            ChunkCodec::Stored,
+            ChunkCodec::Stored
+        ]
+    );
+
+    for chunk in &chunks {
+        let mut out_stream = OutOctetStream::new();
+        chunk
+            .to_stream(&mut out_stream)
+            .expect("should be able to write chunk");
+        let serialized = out_stream.octets();
+        let decoded = SetChunkData::from_stream(&mut InOctetStream::new(&serialized))
+            .expect("should read chunk");
+        assert_eq!(&decoded, chunk);
+
+        receiver.update(&decoded).expect("chunk should be accepted");
+    }
+
+    assert_eq!(receiver.blob(), Some(&blob[..]));
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn lz4_compressible_transfer_round_trip() {
+    let blob: Vec<u8> = b"blob-stream ".repeat(100);
+    let mut sender = out_logic::Logic::new(blob.clone(), 400).with_chunk_codec(ChunkCodec::Lz4);
+    let mut receiver = in_logic::Logic::new(blob.len(), 400);
+
+    for chunk in &sender.send(Millis::new(0), 10) {
+        assert_eq!(chunk.codec, ChunkCodec::Lz4);
+        assert!(chunk.payload.len() < 400);
+
+        let mut out_stream = OutOctetStream::new();
+        chunk
+            .to_stream(&mut out_stream)
+            .expect("should be able to write chunk");
+        let serialized = out_stream.octets();
+        let decoded = SetChunkData::from_stream(&mut InOctetStream::new(&serialized))
+            .expect("should read chunk");
+
+        receiver.update(&decoded).expect("chunk should be accepted");
+    }
+
+    assert_eq!(receiver.blob(), Some(&blob[..]));
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn lz4_incompressible_chunk_is_stored() {
+    let octets: Vec<u8> = (0..=255).collect();
+
+    let chunk = SetChunkData::encode(0, &octets, ChunkCodec::Lz4);
+
+    assert_eq!(chunk.codec, ChunkCodec::Stored);
+    assert_eq!(
+        chunk.decoded_payload(octets.len()).expect("should decode"),
+        &octets[..]
+    );
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn lz4_payload_with_wrong_length_is_rejected() {
+    let mut payload = ChunkCodec::Lz4.encode(&[0x42; 100]);
+    assert_eq!(
+        ChunkCodec::Lz4
+            .decode(&payload, 100)
+            .expect("should decode"),
+        &[0x42; 100][..]
+    );
+    ChunkCodec::Lz4
+        .decode(&payload, 99)
+        .expect_err("decompressed length is larger than the chunk");
+
+    payload[0] = 99;
+    ChunkCodec::Lz4
+        .decode(&payload, 100)
+        .expect_err("decompressed length must match the prefix");
+    ChunkCodec::Lz4
+        .decode(&[0xff, 0xff, 0xff, 0xff, 0x00], usize::MAX)
+        .expect_err("length prefix is larger than any block could decompress to");
+}
@@ -0,0 +1,48 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "crc32")]
+
+use blob_stream::prelude::*;
+use flood_rs::{InOctetStream, OutOctetStream};
+use std::io::ErrorKind;
+
+fn set_chunk_octets(set_chunk: &SetChunkData) -> Vec<u8> {
+    let mut out_stream = OutOctetStream::new();
+    set_chunk.to_stream(&mut out_stream).unwrap();
+    out_stream.octets()
+}
+
+#[test]
+fn set_chunk_with_crc32_round_trip() {
+    let set_chunk = SetChunkData {
+        chunk_index: 7,
+        codec: ChunkCodec::Stored,
+        payload: vec![0x10, 0x20, 0x30, 0x40],
+    };
+
+    let octets = set_chunk_octets(&set_chunk);
+    assert_eq!(octets.len(), set_chunk.octet_size());
+    assert_eq!(octets.len(), 8 + 1 + 4 + 4 + 4);
+
+    let mut in_stream = InOctetStream::new(&octets);
+    let decoded = SetChunkData::from_stream(&mut in_stream).expect("crc32 should match");
+    assert_eq!(decoded, set_chunk);
+}
+
+#[test]
+fn flipped_payload_octet_fails_crc32() {
+    let set_chunk = SetChunkData {
+        chunk_index: 7,
+        codec: ChunkCodec::Stored,
+        payload: vec![0x10, 0x20, 0x30, 0x40],
+    };
+
+    let mut octets = set_chunk_octets(&set_chunk);
+    octets[14] ^= 0x01;
+
+    let mut in_stream = InOctetStream::new(&octets);
+    let err = SetChunkData::from_stream(&mut in_stream).expect_err("corrupted payload");
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
@@ -0,0 +1,85 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "std")]
+
+use blob_stream::datagram::DatagramBatcher;
+use blob_stream::prelude::*;
+use flood_rs::OutOctetStream;
+
+fn chunks(count: u64, payload_size: usize) -> Vec<SetChunkFrontData> {
+    (0..count)
+        .map(|chunk_index| SetChunkFrontData {
+            transfer_id: TransferId(1),
+            data: SetChunkData {
+                chunk_index,
+                codec: ChunkCodec::Stored,
+                payload: vec![0xfe; payload_size],
+            },
+        })
+        .collect()
+}
+
+#[test]
+fn command_octet_size_matches_serialized_size() {
+    let chunk = chunks(1, 17).remove(0);
+    let mut out_stream = OutOctetStream::new();
+    SenderToReceiverFrontCommands::SetChunk(chunk.clone())
+        .to_stream(&mut out_stream)
+        .expect("should be able to write command");
+
+    assert_eq!(chunk.command_octet_size(), out_stream.octets().len());
+}
+
+#[test]
+fn batches_by_octet_size() {
+    // Each command is 1 + 1 + 2 + 8 + 1 + 4 + 10 = 27 octets (31 with the crc32 trailer), so three fit.
+    let command_octet_size = SetChunkFrontData::command_octet_size_for_payload(10);
+    let datagrams = DatagramBatcher::new(3 * command_octet_size).batch(chunks(7, 10));
+
+    let sizes: Vec<usize> = datagrams.iter().map(Vec::len).collect();
+    assert_eq!(sizes, [3, 3, 1]);
+}
+
+#[test]
+fn batches_never_exceed_chunk_count_cap() {
+    // All chunks would fit in a single datagram by octets.
+    let batcher = DatagramBatcher::new(1200).with_max_chunks_per_datagram(2);
+    let datagrams = batcher.batch(chunks(5, 10));
+
+    assert!(datagrams.iter().all(|datagram| datagram.len() <= 2));
+    let sizes: Vec<usize> = datagrams.iter().map(Vec::len).collect();
+    assert_eq!(sizes, [2, 2, 1]);
+
+    let chunk_indices: Vec<u64> = datagrams
+        .iter()
+        .flatten()
+        .map(|chunk| chunk.data.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn oversized_chunk_gets_its_own_datagram() {
+    let datagrams = DatagramBatcher::new(10).batch(chunks(2, 20));
+
+    let sizes: Vec<usize> = datagrams.iter().map(Vec::len).collect();
+    assert_eq!(sizes, [1, 1]);
+}
+
+#[test]
+fn datagram_count_matches_batch() {
+    let batcher = DatagramBatcher::new(64).with_max_chunks_per_datagram(2);
+    let chunks = chunks(7, 10);
+    let command_octet_sizes: Vec<usize> = chunks
+        .iter()
+        .map(SetChunkFrontData::command_octet_size)
+        .collect();
+
+    assert_eq!(
+        batcher.datagram_count(command_octet_sizes),
+        batcher.batch(chunks).len()
+    );
+    assert_eq!(batcher.datagram_count([]), 0);
+}
@@ -0,0 +1,83 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "tokio")]
+
+use blob_stream::framed::{
+    read_receiver_command, read_sender_command, write_receiver_command, write_sender_command,
+    MAX_FRAME_OCTET_SIZE,
+};
+use blob_stream::prelude::*;
+use std::io::ErrorKind;
+use tokio::io::{duplex, AsyncWriteExt};
+
+const fn set_chunk(chunk_index: u64, payload: Vec<u8>) -> SenderToReceiverFrontCommands {
+    SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(7),
+        data: SetChunkData {
+            chunk_index,
+            codec: ChunkCodec::Stored,
+            payload,
+        },
+    })
+}
+
+#[tokio::test]
+async fn sender_commands_arrive_whole_through_small_pipe() {
+    // The pipe buffers only a few octets, so every frame is split into partial reads.
+    let (mut client, mut server) = duplex(5);
+    let commands = vec![
+        set_chunk(0, vec![1; 100]),
+        set_chunk(1, vec![2; 3]),
+        set_chunk(2, vec![]),
+    ];
+
+    let writes = async {
+        for command in &commands {
+            write_sender_command(&mut client, command).await.unwrap();
+        }
+    };
+    let reads = async {
+        let mut received = Vec::new();
+        for _ in 0..commands.len() {
+            received.push(read_sender_command(&mut server).await.unwrap());
+        }
+        received
+    };
+    let ((), received) = tokio::join!(writes, reads);
+
+    assert_eq!(received, commands);
+}
+
+#[tokio::test]
+async fn receiver_command_round_trip() {
+    let (mut client, mut server) = duplex(64);
+
+    write_receiver_command(&mut client, &ReceiverToSenderFrontCommands::AckStart(7))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        read_receiver_command(&mut server).await.unwrap(),
+        ReceiverToSenderFrontCommands::AckStart(7)
+    );
+}
+
+#[tokio::test]
+async fn oversized_and_truncated_frames_are_rejected() {
+    let (mut client, mut server) = duplex(64);
+    let octet_size = u32::try_from(MAX_FRAME_OCTET_SIZE + 1).unwrap();
+    client.write_u32(octet_size).await.unwrap();
+
+    let err = read_sender_command(&mut server).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let (mut client, mut server) = duplex(64);
+    client.write_u32(10).await.unwrap();
+    client.write_all(&[2, 1]).await.unwrap();
+    drop(client);
+
+    let err = read_receiver_command(&mut server).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
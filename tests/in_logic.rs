@@ -2,8 +2,17 @@
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
-use blob_stream::in_logic::Logic;
+#![cfg(feature = "std")]
+
+use bit_array_rs::BitArray;
+use blob_stream::in_logic::{ack_after_single, Logic};
+use blob_stream::out_logic;
 use blob_stream::prelude::*;
+use blob_stream::protocol::{AckChunkData, MAX_RECEIVE_MASK_OCTET_SIZE};
+use blob_stream::received_set;
+use flood_rs::{InOctetStream, OutOctetStream, WriteOctetStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[test]
 fn check_receive() {
@@ -11,6 +20,7 @@ fn check_receive() {
 
     let set_chunk_data = SetChunkData {
         chunk_index: 1,
+        codec: ChunkCodec::Stored,
         payload: [0x8f, 0x23, 0x98, 0xfa, 0x99].into(),
     };
 
@@ -28,6 +38,7 @@ fn multiple_not_received() {
 
     let set_chunk_data = SetChunkData {
         chunk_index: 2,
+        codec: ChunkCodec::Stored,
         payload: [0x8f].into(),
     };
 
@@ -41,13 +52,14 @@ fn multiple_not_received() {
 
 fn set_chunk_and_check(
     logic: &mut Logic,
-    chunk_index: u32,
+    chunk_index: u64,
     payload: &[u8],
-    waiting: u32,
+    waiting: u64,
     receive_mask: u64,
 ) {
     let set_chunk_data = SetChunkData {
         chunk_index,
+        codec: ChunkCodec::Stored,
         payload: payload.to_vec(),
     };
     let ack = logic.update(&set_chunk_data).expect("update should work");
@@ -66,5 +78,574 @@ fn all_received() {
     assert_eq!(
         logic.blob().expect("Blob slice should be complete"),
         &[0x33, 0x33, 0x33, 0x33, 0x33, 0xff, 0xff, 0xff, 0xff, 0xff, 0x8f]
-    )
+    );
+}
+
+#[test]
+fn getters_match_construction() {
+    let logic = Logic::new(11, 5);
+
+    assert_eq!(logic.octet_count(), 11);
+    assert_eq!(logic.chunk_size(), 5);
+    assert_eq!(logic.chunk_count(), 3);
+}
+
+#[test]
+fn ack_bytes_is_contiguous_prefix() {
+    let mut logic = Logic::new(11, 5);
+    assert_eq!(logic.ack_bytes().received_up_to_byte, 0);
+
+    set_chunk_and_check(&mut logic, 1, &[0xff; 5], 0, 0b1);
+    assert_eq!(logic.ack_bytes().received_up_to_byte, 0);
+
+    set_chunk_and_check(&mut logic, 0, &[0x33; 5], 2, 0b0);
+    assert_eq!(logic.ack_bytes().received_up_to_byte, 10);
+
+    set_chunk_and_check(&mut logic, 2, &[0x8f], 3, 0b0);
+    assert_eq!(logic.ack_bytes().received_up_to_byte, 11);
+}
+
+#[test]
+fn duplicates_are_wasted_bytes() {
+    let mut logic = Logic::new(11, 5);
+
+    set_chunk_and_check(&mut logic, 0, &[0x33; 5], 1, 0b0);
+    assert_eq!(logic.wasted_bytes(), 0);
+
+    let same = SetChunkData {
+        chunk_index: 0,
+        codec: ChunkCodec::Stored,
+        payload: [0x33; 5].into(),
+    };
+    assert!(logic.update(&same).is_err());
+
+    let last = SetChunkData {
+        chunk_index: 2,
+        codec: ChunkCodec::Stored,
+        payload: [0x8f].into(),
+    };
+    logic.update(&last).expect("last chunk should be accepted");
+    let differs = SetChunkData {
+        chunk_index: 2,
+        codec: ChunkCodec::Stored,
+        payload: [0x10].into(),
+    };
+    assert!(logic.update(&differs).is_err());
+
+    assert_eq!(logic.wasted_bytes(), 6);
+}
+
+#[test]
+fn ack_after_single_first_chunk_advances_frontier() {
+    let ack = ack_after_single(3, 0);
+    assert_eq!(ack.waiting_for_chunk_index, 1);
+    assert_eq!(ack.receive_mask_after_last, 0);
+
+    let mut logic = Logic::new(11, 5);
+    let received = logic
+        .update(&SetChunkData {
+            chunk_index: 0,
+            codec: ChunkCodec::Stored,
+            payload: [0x33; 5].into(),
+        })
+        .expect("update should work");
+    assert_eq!(received, ack);
+}
+
+#[test]
+fn ack_after_single_later_chunk_sets_mask_bit() {
+    let ack = ack_after_single(3, 2);
+    assert_eq!(ack.waiting_for_chunk_index, 0);
+    assert_eq!(ack.receive_mask_after_last, 0b10);
+
+    let mut logic = Logic::new(11, 5);
+    let received = logic
+        .update(&SetChunkData {
+            chunk_index: 2,
+            codec: ChunkCodec::Stored,
+            payload: [0x8f].into(),
+        })
+        .expect("update should work");
+    assert_eq!(received, ack);
+}
+
+#[test]
+fn acceptance_window_follows_frontier() {
+    let mut logic = Logic::new(20, 4).with_receive_window(2);
+    assert_eq!(logic.acceptance_window(), 0..2);
+
+    set_chunk_and_check(&mut logic, 1, &[0x11; 4], 0, 0b1);
+    assert_eq!(logic.acceptance_window(), 0..2);
+
+    let err = logic
+        .update(&SetChunkData {
+            chunk_index: 2,
+            codec: ChunkCodec::Stored,
+            payload: [0x22; 4].into(),
+        })
+        .expect_err("chunk 2 is outside of the window");
+    assert!(
+        matches!(err, BlobError::OutOfWindow(2, ref window) if *window == (0..2)),
+        "{err:?}"
+    );
+
+    set_chunk_and_check(&mut logic, 0, &[0x00; 4], 2, 0b0);
+    assert_eq!(logic.acceptance_window(), 2..4);
+
+    set_chunk_and_check(&mut logic, 2, &[0x22; 4], 3, 0b0);
+    assert_eq!(logic.acceptance_window(), 3..5);
+}
+
+#[test]
+fn credit_counts_missing_chunks_in_window() {
+    let mut logic = Logic::new(20, 4);
+    set_chunk_and_check(&mut logic, 3, &[0x33; 4], 0, 0b100);
+    assert_eq!(logic.credit(), 4);
+
+    let mut logic = Logic::new(20, 4).with_receive_window(4);
+    set_chunk_and_check(&mut logic, 1, &[0x11; 4], 0, 0b1);
+    set_chunk_and_check(&mut logic, 3, &[0x33; 4], 0, 0b101);
+    assert_eq!(logic.credit(), 2);
+}
+
+#[test]
+fn acceptance_window_without_limit_covers_rest_of_blob() {
+    let mut logic = Logic::new(11, 5);
+    assert_eq!(logic.acceptance_window(), 0..3);
+
+    set_chunk_and_check(&mut logic, 0, &[0x33; 5], 1, 0b0);
+    assert_eq!(logic.acceptance_window(), 1..3);
+}
+
+/// Receives one-octet chunks at `chunk_indices` of a blob with `chunk_count` chunks.
+fn logic_with_received(chunk_count: usize, chunk_indices: &[u64]) -> Logic {
+    let mut logic = Logic::new(chunk_count, 1);
+    for &chunk_index in chunk_indices {
+        logic
+            .update(&SetChunkData {
+                chunk_index,
+                codec: ChunkCodec::Stored,
+                payload: vec![0x42],
+            })
+            .expect("chunk should be accepted");
+    }
+    logic
+}
+
+fn decoded_indices(full_received: &[u8], chunk_count: usize) -> Vec<usize> {
+    let received = received_set::decode(full_received, chunk_count).expect("should decode");
+    (0..chunk_count)
+        .filter(|&chunk_index| received.get(chunk_index))
+        .collect()
+}
+
+#[test]
+fn full_received_ack_round_trip_sparse() {
+    let logic = logic_with_received(1000, &[3, 200, 999]);
+
+    let full_received = logic.full_received_ack();
+
+    assert!(full_received.len() < 1000 / 8);
+    assert_eq!(decoded_indices(&full_received, 1000), [3, 200, 999]);
+}
+
+#[test]
+fn full_received_ack_round_trip_dense() {
+    let chunk_indices: Vec<u64> = (0..1000)
+        .filter(|chunk_index| chunk_index % 3 != 0)
+        .collect();
+    let logic = logic_with_received(1000, &chunk_indices);
+
+    let full_received = logic.full_received_ack();
+
+    assert_eq!(full_received.len(), 1 + 1000 / 8);
+    let expected: Vec<usize> = (0..1000)
+        .filter(|chunk_index| chunk_index % 3 != 0)
+        .collect();
+    assert_eq!(decoded_indices(&full_received, 1000), expected);
+}
+
+#[test]
+fn full_received_ack_rejects_other_chunk_count() {
+    let full_received = logic_with_received(100, &[1, 2, 50]).full_received_ack();
+
+    assert!(received_set::decode(&full_received, 99).is_err());
+    assert!(received_set::decode(&full_received, 101).is_err());
+}
+
+#[test]
+fn full_received_ack_skips_received_chunks_in_fresh_sender() {
+    let chunk_indices = [0, 1, 2, 7, 70, 71];
+    let logic = logic_with_received(80, &chunk_indices);
+    let mut sender = out_logic::Logic::new(vec![0x42; 80], 1);
+
+    sender
+        .apply_full_received(&logic.full_received_ack())
+        .expect("full received ack should apply");
+
+    assert_eq!(sender.acked_chunk_count(), chunk_indices.len());
+    let sent: Vec<u64> = sender
+        .send(Millis::new(0), 100)
+        .iter()
+        .map(|chunk| chunk.chunk_index)
+        .collect();
+    let expected: Vec<u64> = (0..80)
+        .filter(|chunk_index| !chunk_indices.contains(chunk_index))
+        .collect();
+    assert_eq!(sent, expected);
+}
+
+#[test]
+fn cloned_logic_converges() {
+    let mut logic = logic_with_received(4, &[2]);
+    let mut cloned = logic.clone();
+    assert_eq!(cloned, logic);
+
+    for chunk_index in [0, 1, 3] {
+        let set_chunk_data = SetChunkData {
+            chunk_index,
+            codec: ChunkCodec::Stored,
+            payload: vec![0x42],
+        };
+        logic.update(&set_chunk_data).unwrap();
+    }
+    assert_ne!(cloned, logic);
+
+    for chunk_index in [3, 1, 0] {
+        let set_chunk_data = SetChunkData {
+            chunk_index,
+            codec: ChunkCodec::Stored,
+            payload: vec![0x42],
+        };
+        cloned.update(&set_chunk_data).unwrap();
+    }
+    assert_eq!(cloned, logic);
+    assert_eq!(cloned.blob(), Some(&[0x42; 4][..]));
+}
+
+fn stored_chunk(chunk_index: u64, payload: &[u8]) -> SetChunkData {
+    SetChunkData {
+        chunk_index,
+        codec: ChunkCodec::Stored,
+        payload: payload.to_vec(),
+    }
+}
+
+#[test]
+fn stats_count_duplicate_and_conflicting_chunks() {
+    let mut logic = Logic::new(8, 4);
+    logic.update(&stored_chunk(1, &[2; 4])).unwrap();
+    assert!(logic.update(&stored_chunk(1, &[2; 4])).is_err());
+    assert!(logic.update(&stored_chunk(1, &[3; 4])).is_err());
+    logic.update(&stored_chunk(0, &[1; 4])).unwrap();
+
+    assert_eq!(
+        *logic.stats(),
+        ReceiveStats {
+            accepted_chunk_count: 2,
+            accepted_octet_count: 8,
+            duplicate_chunk_count: 1,
+            conflicting_chunk_count: 1,
+            out_of_order_chunk_count: 1,
+        }
+    );
+}
+
+#[test]
+fn snapshot_round_trip_resumes_transfer() {
+    let mut logic = Logic::new(10, 4);
+    logic.update(&stored_chunk(0, &[1; 4])).unwrap();
+    logic.update(&stored_chunk(2, &[3; 2])).unwrap();
+
+    let mut out_stream = OutOctetStream::new();
+    logic.snapshot().to_stream(&mut out_stream).unwrap();
+    let mut in_stream = InOctetStream::new(out_stream.octets_ref());
+    let snapshot = ReceiveSnapshot::from_stream(&mut in_stream).unwrap();
+    assert_eq!(snapshot, logic.snapshot());
+    assert_eq!(snapshot.received_chunks, [0, 2]);
+
+    let mut restored = Logic::restore(snapshot).unwrap();
+    assert_eq!(restored, logic);
+    assert!(!restored.is_complete());
+
+    let conflict = restored.update(&stored_chunk(2, &[4; 2])).unwrap_err();
+    assert!(
+        matches!(conflict, BlobError::RedundantContentDiffers(2)),
+        "{conflict:?}"
+    );
+    restored.update(&stored_chunk(1, &[2; 4])).unwrap();
+    assert!(restored.is_complete());
+    assert_eq!(restored.blob(), Some(&[1, 1, 1, 1, 2, 2, 2, 2, 3, 3][..]));
+}
+
+#[test]
+fn restore_rejects_inconsistent_snapshot() {
+    let snapshot = ReceiveSnapshot {
+        octet_count: 10,
+        fixed_chunk_size: 4,
+        received_chunks: vec![3],
+        blob: vec![0; 10],
+    };
+    assert!(matches!(
+        Logic::restore(snapshot.clone()),
+        Err(BlobError::InvalidChunkIndex(3, 3))
+    ));
+    assert!(matches!(
+        Logic::restore(ReceiveSnapshot {
+            blob: vec![0; 8],
+            received_chunks: vec![],
+            ..snapshot
+        }),
+        Err(BlobError::InconsistentOctetCount(10, 8))
+    ));
+    assert!(matches!(
+        Logic::restore(ReceiveSnapshot {
+            fixed_chunk_size: 0,
+            received_chunks: vec![],
+            ..snapshot
+        }),
+        Err(BlobError::InvalidChunkSize(0))
+    ));
+}
+
+#[test]
+fn snapshot_with_corrupt_lengths_is_rejected() {
+    let mut out_stream = OutOctetStream::new();
+    out_stream.write_u32(u32::MAX).unwrap();
+    out_stream.write_u32(4).unwrap();
+    out_stream.write_u32(u32::MAX).unwrap();
+    let mut in_stream = InOctetStream::new(out_stream.octets_ref());
+    let err = ReceiveSnapshot::from_stream(&mut in_stream).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let mut out_stream = OutOctetStream::new();
+    out_stream.write_u32(u32::MAX).unwrap();
+    out_stream.write_u32(1).unwrap();
+    out_stream.write_u32(1).unwrap();
+    out_stream.write(&[0x01]).unwrap();
+    out_stream.write(&[0; 16]).unwrap();
+    let mut in_stream = InOctetStream::new(out_stream.octets_ref());
+    let err = ReceiveSnapshot::from_stream(&mut in_stream).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn request_chunks_lists_gaps_before_last_received_chunk() {
+    let mut logic = Logic::new(40, 4);
+    assert!(logic.request_chunks().chunk_indices.is_empty());
+
+    for chunk_index in [0, 2, 3, 6] {
+        logic.update(&stored_chunk(chunk_index, &[0; 4])).unwrap();
+    }
+
+    // Chunks 7, 8 and 9 might not have been sent yet.
+    assert_eq!(logic.request_chunks().chunk_indices, [1, 4, 5]);
+}
+
+#[test]
+fn receive_bitmask_matches_ack() {
+    let mut logic = Logic::new(40, 4);
+    let mut ack = logic.ack();
+    for chunk_index in [0, 1, 3, 6, 9] {
+        ack = logic.update(&stored_chunk(chunk_index, &[0; 4])).unwrap();
+    }
+
+    assert_eq!(logic.waiting_for_chunk_index(), ack.waiting_for_chunk_index);
+    assert_eq!(logic.waiting_for_chunk_index(), 2);
+
+    let receive_bitmask = logic.receive_bitmask();
+    assert_eq!(receive_bitmask.bit_count(), 10);
+    for chunk_index in 0..receive_bitmask.bit_count() {
+        let is_acked = chunk_index < 2
+            || (chunk_index > 2 && ack.receive_mask_after_last & (1 << (chunk_index - 3)) != 0);
+        assert_eq!(
+            receive_bitmask.get(chunk_index),
+            is_acked,
+            "chunk {chunk_index}"
+        );
+    }
+}
+
+#[test]
+fn chunk_index_beyond_blob_is_rejected() {
+    let mut logic = Logic::new(8, 4);
+
+    let err = logic
+        .update(&stored_chunk(u64::from(u32::MAX) + 1, &[0; 4]))
+        .unwrap_err();
+
+    assert!(matches!(err, BlobError::InvalidChunkIndex(_, 2)), "{err:?}");
+    assert_eq!(err.io_error_kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn undecodable_payload_is_rejected() {
+    let mut logic = Logic::new(8, 4);
+
+    let err = logic
+        .update(&SetChunkData {
+            chunk_index: 1,
+            codec: ChunkCodec::RunLength,
+            payload: vec![4],
+        })
+        .unwrap_err();
+
+    assert!(matches!(err, BlobError::InvalidPayload(1, _)), "{err:?}");
+    assert_eq!(err.io_error_kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(logic.info().chunk_count_received, 0);
+}
+
+#[test]
+fn update_with_outcome_reports_completing_chunk() {
+    let mut logic = Logic::new(10, 4);
+
+    let (_, outcome) = logic
+        .update_with_outcome(&stored_chunk(2, &[3; 2]))
+        .unwrap();
+    assert_eq!(outcome, ReceiveOutcome::Accepted);
+    let (_, outcome) = logic
+        .update_with_outcome(&stored_chunk(0, &[1; 4]))
+        .unwrap();
+    assert_eq!(outcome, ReceiveOutcome::Accepted);
+
+    let (ack, outcome) = logic
+        .update_with_outcome(&stored_chunk(2, &[3; 2]))
+        .unwrap();
+    assert_eq!(outcome, ReceiveOutcome::Duplicate);
+    assert_eq!(ack, logic.ack());
+
+    let (ack, outcome) = logic
+        .update_with_outcome(&stored_chunk(1, &[2; 4]))
+        .unwrap();
+    assert_eq!(outcome, ReceiveOutcome::Completed);
+    assert_eq!(ack.waiting_for_chunk_index, 3);
+    assert_eq!(logic.blob(), Some(&[1, 1, 1, 1, 2, 2, 2, 2, 3, 3][..]));
+
+    assert!(logic
+        .update_with_outcome(&stored_chunk(1, &[9; 4]))
+        .is_err());
+}
+
+fn bit_array_with(bit_count: usize, set_indices: &[usize]) -> BitArray {
+    let mut bits = BitArray::new(bit_count);
+    for &index in set_indices {
+        bits.set(index);
+    }
+    bits
+}
+
+#[test]
+fn ack_from_bit_array() {
+    let ack = AckChunkData::from_bit_array(&bit_array_with(2, &[1]));
+    assert_eq!(ack.waiting_for_chunk_index, 0);
+    assert_eq!(ack.receive_mask_after_last, 0b1);
+
+    let ack = AckChunkData::from_bit_array(&bit_array_with(3, &[2]));
+    assert_eq!(ack.waiting_for_chunk_index, 0);
+    assert_eq!(ack.receive_mask_after_last, 0b10);
+
+    let ack = AckChunkData::from_bit_array(&bit_array_with(8, &[0, 1, 3, 7]));
+    assert_eq!(ack.waiting_for_chunk_index, 2);
+    assert_eq!(ack.receive_mask_after_last, 0b1_0001);
+
+    let ack = AckChunkData::from_bit_array(&bit_array_with(2, &[0, 1]));
+    assert_eq!(ack.waiting_for_chunk_index, 2);
+    assert_eq!(ack.receive_mask_after_last, 0);
+}
+
+#[test]
+fn ack_from_bit_array_matches_logic() {
+    let logic = logic_with_received(100, &[0, 3, 64, 70]);
+
+    assert_eq!(
+        AckChunkData::from_bit_array(logic.receive_bitmask()),
+        logic.ack()
+    );
+}
+
+/// XORs the blob into a single octet and counts the octets it has been fed.
+#[derive(Debug, Default)]
+struct CountingHasher {
+    fed_octet_count: Arc<AtomicUsize>,
+}
+
+#[derive(Debug)]
+struct CountingState {
+    digest: u8,
+    fed_octet_count: Arc<AtomicUsize>,
+}
+
+impl BlobHasher for CountingHasher {
+    fn digest_octet_size(&self) -> usize {
+        1
+    }
+
+    fn hash(&self, blob: &[u8]) -> Vec<u8> {
+        let mut state = self.start();
+        state.update(blob);
+        state.digest()
+    }
+
+    fn start(&self) -> Box<dyn HashState> {
+        Box::new(CountingState {
+            digest: 0,
+            fed_octet_count: Arc::clone(&self.fed_octet_count),
+        })
+    }
+}
+
+impl HashState for CountingState {
+    fn update(&mut self, octets: &[u8]) {
+        self.fed_octet_count
+            .fetch_add(octets.len(), Ordering::Relaxed);
+        self.digest = octets
+            .iter()
+            .fold(self.digest, |digest, octet| digest ^ octet);
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        vec![self.digest]
+    }
+}
+
+#[test]
+fn checkpoints_only_hash_the_chunks_after_the_previous_one() {
+    let hasher = CountingHasher::default();
+    let mut logic = Logic::new(12, 4);
+    logic.update(&stored_chunk(0, &[1; 4])).unwrap();
+    logic.update(&stored_chunk(1, &[2; 4])).unwrap();
+    logic.update(&stored_chunk(2, &[7; 4])).unwrap();
+
+    assert!(logic.verify_checkpoint(&hasher, 1, &[0]).unwrap());
+    assert!(logic.verify_checkpoint(&hasher, 2, &[0]).unwrap());
+    let err = logic.verify_checkpoint(&hasher, 3, &[1]).unwrap_err();
+
+    assert!(matches!(err, BlobError::CheckpointMismatch(3)), "{err:?}");
+    assert_eq!(hasher.fed_octet_count.load(Ordering::Relaxed), 12);
+
+    // An earlier checkpoint starts the hash over.
+    assert!(logic.verify_checkpoint(&hasher, 1, &[0]).unwrap());
+    assert_eq!(hasher.fed_octet_count.load(Ordering::Relaxed), 16);
+}
+
+#[test]
+fn ack_mask_is_clamped_to_maximum_size() {
+    let mask_chunk_count = MAX_RECEIVE_MASK_OCTET_SIZE * 8;
+    let mut logic = Logic::new(mask_chunk_count + 2, 1);
+    let last_in_mask = u64::try_from(mask_chunk_count).unwrap();
+    logic.update(&stored_chunk(last_in_mask, &[1])).unwrap();
+    logic.update(&stored_chunk(last_in_mask + 1, &[2])).unwrap();
+
+    let ack_mask = logic.ack_mask();
+
+    assert!(logic.needs_ack_mask());
+    assert_eq!(
+        ack_mask.receive_mask_after_last.len(),
+        MAX_RECEIVE_MASK_OCTET_SIZE
+    );
+    assert!(ack_mask.is_received(last_in_mask));
+    assert!(!ack_mask.is_received(last_in_mask + 1));
+    ack_mask
+        .to_stream(&mut OutOctetStream::new())
+        .expect("a clamped mask should be written");
 }
@@ -2,7 +2,15 @@
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
+#![cfg(feature = "std")]
+
 use blob_stream::prelude::*;
+use blob_stream::protocol::{
+    AbortTransferData, CompleteTransferData, KeepaliveData, PauseTransferData, ResumeTransferData,
+};
+use flood_rs::OutOctetStream;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[test]
 fn start_transfer() {
@@ -10,12 +18,14 @@ fn start_transfer() {
         transfer_id: 1,
         total_octet_size: 8,
         chunk_size: 2,
+        schedule: vec![],
+        blob_hash: None,
     });
 
     let mut logic = FrontLogic::new();
 
     let answer = logic
-        .update(&start_transfer)
+        .update(Millis::new(0), &start_transfer)
         .expect("start transfer should work");
 
     let expected_answer = ReceiverToSenderFrontCommands::AckStart(1);
@@ -29,13 +39,15 @@ fn drop_previous_transfer() {
         transfer_id: 1,
         total_octet_size: 8,
         chunk_size: 2,
+        schedule: vec![],
+        blob_hash: None,
     });
 
     let mut logic = FrontLogic::new();
 
     {
         let answer = logic
-            .update(&start_transfer)
+            .update(Millis::new(0), &start_transfer)
             .expect("start transfer should work");
 
         let expected_answer = ReceiverToSenderFrontCommands::AckStart(1);
@@ -48,10 +60,12 @@ fn drop_previous_transfer() {
             transfer_id: 2,
             total_octet_size: 8,
             chunk_size: 2,
+            schedule: vec![],
+            blob_hash: None,
         });
 
         let answer = logic
-            .update(&new_transfer)
+            .update(Millis::new(0), &new_transfer)
             .expect("it should accept new transfer");
 
         let expected_answer = ReceiverToSenderFrontCommands::AckStart(2);
@@ -63,13 +77,14 @@ fn drop_previous_transfer() {
 fn set_chunk_and_check(
     logic: &mut FrontLogic,
     transfer_id: u16,
-    chunk_index: u32,
+    chunk_index: u64,
     payload: &[u8],
-    waiting: u32,
+    waiting: u64,
     receive_mask: u64,
 ) {
     let set_chunk_data = SetChunkData {
         chunk_index,
+        codec: ChunkCodec::Stored,
         payload: payload.to_vec(),
     };
     let set_chunk_front = SetChunkFrontData {
@@ -79,7 +94,7 @@ fn set_chunk_and_check(
     let set_chunk_command = SenderToReceiverFrontCommands::SetChunk(set_chunk_front);
 
     let ack = logic
-        .update(&set_chunk_command)
+        .update(Millis::new(0), &set_chunk_command)
         .expect("update should work");
     match ack {
         ReceiverToSenderFrontCommands::AckChunk(ack) => {
@@ -98,13 +113,15 @@ fn complete_transfer() {
         transfer_id: TRANSFER_ID.0,
         total_octet_size: 9,
         chunk_size: 4,
+        schedule: vec![],
+        blob_hash: None,
     });
 
     let mut logic = FrontLogic::new();
 
     {
         let answer = logic
-            .update(&start_transfer)
+            .update(Millis::new(0), &start_transfer)
             .expect("start transfer should work");
 
         let expected_answer = ReceiverToSenderFrontCommands::AckStart(TRANSFER_ID_VALUE);
@@ -148,7 +165,23 @@ fn complete_transfer() {
     assert_eq!(info_after_0.chunk_count_received, 2);
     assert_eq!(info_after_0.waiting_for_chunk_index, 2);
 
-    set_chunk_and_check(&mut logic, TRANSFER_ID_VALUE, 2, &[0x42], 3, 0b0);
+    let completed = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                transfer_id: TRANSFER_ID,
+                data: SetChunkData {
+                    chunk_index: 2,
+                    codec: ChunkCodec::Stored,
+                    payload: [0x42].into(),
+                },
+            }),
+        )
+        .expect("last chunk should be accepted");
+    assert_eq!(
+        completed,
+        ReceiverToSenderFrontCommands::Completed(TRANSFER_ID_VALUE)
+    );
 
     assert_eq!(
         logic
@@ -166,3 +199,1765 @@ fn complete_transfer() {
     assert_eq!(info_after_complete.chunk_count_received, 3);
     assert_eq!(info_after_complete.waiting_for_chunk_index, 3);
 }
+
+#[test]
+fn completed_is_reported_again_for_resent_chunk() {
+    const TRANSFER_ID_VALUE: u16 = 0x0042;
+    let start_transfer = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: TRANSFER_ID_VALUE,
+        total_octet_size: 5,
+        chunk_size: 4,
+        schedule: vec![],
+        blob_hash: None,
+    });
+
+    let mut logic = FrontLogic::new();
+    logic
+        .update(Millis::new(0), &start_transfer)
+        .expect("start transfer should work");
+
+    set_chunk_and_check(&mut logic, TRANSFER_ID_VALUE, 1, &[0x10], 0, 0b1);
+
+    let last_chunk = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(TRANSFER_ID_VALUE),
+        data: SetChunkData {
+            chunk_index: 0,
+            codec: ChunkCodec::Stored,
+            payload: [0x01, 0x02, 0x03, 0x04].into(),
+        },
+    });
+
+    let answer = logic
+        .update(Millis::new(0), &last_chunk)
+        .expect("completing chunk should be accepted");
+    assert_eq!(
+        answer,
+        ReceiverToSenderFrontCommands::Completed(TRANSFER_ID_VALUE)
+    );
+
+    // The `Completed` was lost, so the sender resends the chunk.
+    let answer = logic
+        .update(Millis::new(0), &last_chunk)
+        .expect("a resent chunk should be answered");
+    assert_eq!(
+        answer,
+        ReceiverToSenderFrontCommands::Completed(TRANSFER_ID_VALUE)
+    );
+    assert_eq!(logic.stats().duplicate_chunk_count, 1);
+}
+
+#[test]
+fn replay_captured_commands() {
+    let commands = [
+        SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+            transfer_id: 7,
+            total_octet_size: 6,
+            chunk_size: 4,
+            schedule: vec![],
+            blob_hash: None,
+        }),
+        SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+            transfer_id: TransferId(7),
+            data: SetChunkData {
+                chunk_index: 1,
+                codec: ChunkCodec::Stored,
+                payload: [0x05, 0x06].into(),
+            },
+        }),
+        SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+            transfer_id: TransferId(7),
+            data: SetChunkData {
+                chunk_index: 1,
+                codec: ChunkCodec::Stored,
+                payload: [0x05, 0x06].into(),
+            },
+        }),
+        SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+            transfer_id: TransferId(7),
+            data: SetChunkData {
+                chunk_index: 0,
+                codec: ChunkCodec::Stored,
+                payload: [0x01, 0x02, 0x03, 0x04].into(),
+            },
+        }),
+        start_transfer_with(8, 12, 4),
+    ];
+
+    let mut live = FrontLogic::new().with_max_chunk_count(2);
+    let mut captured = Vec::new();
+    for command in &commands {
+        let mut out_stream = OutOctetStream::new();
+        command
+            .to_stream(&mut out_stream)
+            .expect("should be able to write command");
+        let now = Millis::new(0);
+        captured.push((now, out_stream.octets()));
+        let _ = live.update(now, command);
+    }
+
+    let replayed = FrontLogic::new()
+        .with_max_chunk_count(2)
+        .replay(&captured)
+        .expect("replay should work");
+
+    assert!(replayed.transfer_status(8).is_none());
+    assert_eq!(replayed.stats(), live.stats());
+    assert_eq!(replayed.stats().duplicate_chunk_count, 1);
+
+    assert_eq!(
+        replayed.blob().expect("replayed blob should be complete"),
+        live.blob().expect("live blob should be complete")
+    );
+    assert_eq!(
+        replayed.blob().expect("replayed blob should be complete"),
+        &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06]
+    );
+}
+
+#[test]
+fn ack_bytes_for_current_transfer() {
+    let mut logic = FrontLogic::new();
+    assert!(logic.ack_bytes().is_none());
+
+    logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 3,
+                total_octet_size: 9,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    set_chunk_and_check(&mut logic, 3, 0, &[0x01, 0x02, 0x03, 0x04], 1, 0b0);
+
+    match logic.ack_bytes().expect("transfer has been started") {
+        ReceiverToSenderFrontCommands::AckBytes(ack) => {
+            assert_eq!(ack.transfer_id, TransferId(3));
+            assert_eq!(ack.data.received_up_to_byte, 4);
+        }
+        _ => panic!("unexpected command"),
+    }
+}
+
+fn complete_single_chunk_transfer(logic: &mut FrontLogic, transfer_id: u16) {
+    let start_transfer = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id,
+        total_octet_size: 3,
+        chunk_size: 4,
+        schedule: vec![],
+        blob_hash: None,
+    });
+    logic
+        .update(Millis::new(0), &start_transfer)
+        .expect("start transfer should work");
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                transfer_id: TransferId(transfer_id),
+                data: SetChunkData {
+                    chunk_index: 0,
+                    codec: ChunkCodec::Stored,
+                    payload: [0x01, 0x02, 0x03].into(),
+                },
+            }),
+        )
+        .expect("chunk should be accepted");
+    assert_eq!(
+        answer,
+        ReceiverToSenderFrontCommands::Completed(transfer_id)
+    );
+}
+
+#[test]
+fn start_for_completed_transfer_acks_complete() {
+    let mut logic = FrontLogic::new();
+    complete_single_chunk_transfer(&mut logic, 9);
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 9,
+                total_octet_size: 3,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(9));
+    assert_eq!(
+        logic.blob().expect("blob should still be complete"),
+        &[0x01, 0x02, 0x03]
+    );
+}
+
+#[test]
+fn start_for_completed_transfer_restarts() {
+    let mut logic = FrontLogic::new().with_completed_start_policy(CompletedStartPolicy::Restart);
+    complete_single_chunk_transfer(&mut logic, 9);
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 9,
+                total_octet_size: 3,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(9));
+    assert!(logic.blob().is_none());
+    let info = logic.info().expect("transfer should have been restarted");
+    assert_eq!(info.chunk_count_received, 0);
+}
+
+#[test]
+fn start_transfer_exposes_schedule() {
+    let mut logic = FrontLogic::new();
+    assert!(logic.schedule().is_none());
+
+    logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 9,
+                total_octet_size: 30,
+                chunk_size: 10,
+                schedule: vec![2, 0, 1],
+                blob_hash: None,
+            }),
+        )
+        .expect("should accept a start transfer with a schedule");
+
+    assert_eq!(logic.schedule(), Some([2, 0, 1].as_slice()));
+}
+
+#[test]
+fn abort_removes_transfer() {
+    let mut logic = FrontLogic::new();
+    logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 5,
+                total_octet_size: 8,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::AbortTransfer(AbortTransferData { transfer_id: 6 }),
+        )
+        .expect("abort for another transfer should be acknowledged");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckAbort(6));
+    assert!(logic.info().is_some());
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::AbortTransfer(AbortTransferData { transfer_id: 5 }),
+        )
+        .expect("abort should be acknowledged");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckAbort(5));
+    assert!(logic.info().is_none());
+
+    logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                transfer_id: TransferId(5),
+                data: SetChunkData {
+                    chunk_index: 0,
+                    codec: ChunkCodec::Stored,
+                    payload: [0x01, 0x02, 0x03, 0x04].into(),
+                },
+            }),
+        )
+        .expect_err("chunks for an aborted transfer should be rejected");
+}
+
+#[test]
+fn abort_drops_kept_chunks_of_transfer() {
+    let now = Millis::new(0);
+    let mut logic =
+        FrontLogic::new().with_unknown_transfer_policy(UnknownTransferPolicy::NeedStart);
+    let answer = logic
+        .update(
+            now,
+            &SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                transfer_id: TransferId(5),
+                data: SetChunkData {
+                    chunk_index: 0,
+                    codec: ChunkCodec::Stored,
+                    payload: [0x01, 0x02, 0x03, 0x04].into(),
+                },
+            }),
+        )
+        .expect("chunk for an unknown transfer should be kept");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::NeedStart(5));
+
+    logic
+        .update(
+            now,
+            &SenderToReceiverFrontCommands::AbortTransfer(AbortTransferData { transfer_id: 5 }),
+        )
+        .expect("abort should be acknowledged");
+
+    logic
+        .update(now, &start_transfer_with(5, 8, 4))
+        .expect("start transfer should work");
+    assert_eq!(
+        logic
+            .info()
+            .expect("transfer has been started")
+            .chunk_count_received,
+        0
+    );
+}
+
+#[test]
+fn completed_blobs_are_sent_on_channel() {
+    let (sender, receiver) = std::sync::mpsc::sync_channel(2);
+    let mut logic = FrontLogic::new().with_completion_sender(sender);
+
+    complete_single_chunk_transfer(&mut logic, 1);
+    assert!(logic.info().is_none());
+
+    complete_single_chunk_transfer(&mut logic, 2);
+    assert!(logic.info().is_none());
+
+    assert_eq!(
+        receiver.try_recv().expect("first blob should be delivered"),
+        (TransferId(1), vec![0x01, 0x02, 0x03])
+    );
+    assert_eq!(
+        receiver
+            .try_recv()
+            .expect("second blob should be delivered"),
+        (TransferId(2), vec![0x01, 0x02, 0x03])
+    );
+    assert!(receiver.try_recv().is_err());
+
+    // A resent start for a delivered transfer is answered with `Completed`.
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 2,
+                total_octet_size: 3,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(2));
+    assert!(logic.info().is_none());
+}
+
+#[test]
+fn pause_and_resume_current_transfer() {
+    let mut logic = FrontLogic::new();
+    assert!(logic.pause().is_none());
+
+    complete_single_chunk_transfer(&mut logic, 4);
+
+    assert_eq!(
+        logic.pause(),
+        Some(ReceiverToSenderFrontCommands::Pause(PauseTransferData {
+            transfer_id: 4
+        }))
+    );
+    assert_eq!(
+        logic.resume(),
+        Some(ReceiverToSenderFrontCommands::Resume(ResumeTransferData {
+            transfer_id: 4
+        }))
+    );
+}
+
+#[test]
+fn chunk_for_unknown_transfer_is_not_found() {
+    let mut logic = FrontLogic::new();
+    let chunk = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(9),
+        data: SetChunkData {
+            chunk_index: 0,
+            codec: ChunkCodec::Stored,
+            payload: vec![1, 2, 3],
+        },
+    });
+
+    let err = logic.update(Millis::new(0), &chunk).unwrap_err();
+
+    assert!(matches!(err, FrontError::TransferNotFound(9)), "{err:?}");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        std::io::Error::from(err).kind(),
+        std::io::ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn chunk_for_unknown_transfer_needs_start() {
+    let mut logic =
+        FrontLogic::new().with_unknown_transfer_policy(UnknownTransferPolicy::NeedStart);
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                transfer_id: TransferId(8),
+                data: SetChunkData {
+                    chunk_index: 1,
+                    codec: ChunkCodec::Stored,
+                    payload: [0x05, 0x06].into(),
+                },
+            }),
+        )
+        .expect("chunk for an unknown transfer should be kept");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::NeedStart(8));
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 8,
+                total_octet_size: 6,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(8));
+    assert_eq!(
+        logic
+            .info()
+            .expect("transfer has been started")
+            .chunk_count_received,
+        1
+    );
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                transfer_id: TransferId(8),
+                data: SetChunkData {
+                    chunk_index: 0,
+                    codec: ChunkCodec::Stored,
+                    payload: [0x01, 0x02, 0x03, 0x04].into(),
+                },
+            }),
+        )
+        .expect("chunk should be accepted");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(8));
+    assert_eq!(
+        logic.blob().expect("blob should be complete"),
+        &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06]
+    );
+}
+
+/// Spreads a running sum of the blob over `digest_octet_size` octets.
+#[derive(Debug)]
+struct SumHasher {
+    digest_octet_size: usize,
+}
+
+impl BlobHasher for SumHasher {
+    fn digest_octet_size(&self) -> usize {
+        self.digest_octet_size
+    }
+
+    fn hash(&self, blob: &[u8]) -> Vec<u8> {
+        let mut state = self.start();
+        state.update(blob);
+        state.digest()
+    }
+
+    fn start(&self) -> Box<dyn HashState> {
+        Box::new(SumState {
+            digest: vec![0u8; self.digest_octet_size],
+            octet_count: 0,
+        })
+    }
+}
+
+/// The running sum of [`SumHasher`].
+#[derive(Debug)]
+struct SumState {
+    digest: Vec<u8>,
+    octet_count: usize,
+}
+
+impl HashState for SumState {
+    fn update(&mut self, octets: &[u8]) {
+        for octet in octets {
+            let slot_index = self.octet_count % self.digest.len();
+            let slot = &mut self.digest[slot_index];
+            *slot = slot.wrapping_mul(31).wrapping_add(*octet);
+            self.octet_count += 1;
+        }
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        self.digest.clone()
+    }
+}
+
+const fn complete_transfer_command(
+    transfer_id: u16,
+    hash: Vec<u8>,
+) -> SenderToReceiverFrontCommands {
+    SenderToReceiverFrontCommands::CompleteTransfer(CompleteTransferData { transfer_id, hash })
+}
+
+#[test]
+fn complete_transfer_verifies_digests_of_any_size() {
+    for digest_octet_size in [4, 16, 32, 64] {
+        let hasher = SumHasher { digest_octet_size };
+        let hash = hasher.hash(&[0x01, 0x02, 0x03]);
+        let mut logic = FrontLogic::new().with_hasher(Box::new(hasher));
+        complete_single_chunk_transfer(&mut logic, 7);
+
+        let answer = logic
+            .update(Millis::new(0), &complete_transfer_command(7, hash))
+            .expect("matching hash should be accepted");
+        assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(7));
+    }
+}
+
+#[test]
+fn complete_transfer_rejects_hash_length_that_differs_from_hasher() {
+    let mut logic = FrontLogic::new().with_hasher(Box::new(SumHasher {
+        digest_octet_size: 32,
+    }));
+    complete_single_chunk_transfer(&mut logic, 7);
+
+    let err = logic
+        .update(Millis::new(0), &complete_transfer_command(7, vec![0; 16]))
+        .expect_err("a 16 octet hash should not match a 32 octet hasher");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(matches!(
+        err,
+        FrontError::DigestSizeMismatch {
+            octet_count: 16,
+            digest_octet_size: 32
+        }
+    ));
+}
+
+#[test]
+fn complete_transfer_rejects_wrong_hash() {
+    let mut logic = FrontLogic::new().with_hasher(Box::new(SumHasher {
+        digest_octet_size: 4,
+    }));
+    complete_single_chunk_transfer(&mut logic, 7);
+
+    logic
+        .update(Millis::new(0), &complete_transfer_command(7, vec![0xff; 4]))
+        .expect_err("hash should not match the blob");
+}
+
+#[test]
+fn complete_transfer_for_incomplete_blob_acks_chunks() {
+    let mut logic = FrontLogic::new();
+    logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 3,
+                total_octet_size: 8,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    let answer = logic
+        .update(Millis::new(0), &complete_transfer_command(3, vec![0; 4]))
+        .expect("complete transfer should work");
+    match answer {
+        ReceiverToSenderFrontCommands::AckChunk(ack) => {
+            assert_eq!(ack.data.waiting_for_chunk_index, 0);
+        }
+        _ => panic!("unexpected response"),
+    }
+}
+
+#[test]
+fn remove_completed_transfer_returns_blob() {
+    let mut logic = FrontLogic::new();
+    complete_single_chunk_transfer(&mut logic, 9);
+
+    assert_eq!(logic.remove_transfer(8), None);
+    assert_eq!(logic.remove_transfer(9), Some(vec![0x01, 0x02, 0x03]));
+    assert!(logic.info().is_none());
+    assert_eq!(logic.remove_transfer(9), None);
+}
+
+#[test]
+fn remove_incomplete_transfer_and_start_it_again() {
+    let start_transfer = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 4,
+        total_octet_size: 8,
+        chunk_size: 4,
+        schedule: vec![],
+        blob_hash: None,
+    });
+    let mut logic = FrontLogic::new();
+    logic
+        .update(Millis::new(0), &start_transfer)
+        .expect("start transfer should work");
+    set_chunk_and_check(&mut logic, 4, 0, &[1, 2, 3, 4], 1, 0);
+
+    assert_eq!(logic.remove_transfer(4), None);
+    assert!(logic.info().is_none());
+
+    let answer = logic
+        .update(Millis::new(0), &start_transfer)
+        .expect("start transfer should work after removal");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(4));
+    let info = logic.info().expect("transfer should be started again");
+    assert_eq!(info.chunk_count_received, 0);
+}
+
+#[test]
+fn expire_idle_transfer() {
+    let start = Millis::new(0);
+    let max_idle = Duration::from_secs(5);
+    let mut logic = FrontLogic::new();
+    logic
+        .update(
+            start,
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 6,
+                total_octet_size: 8,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    let chunk = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(6),
+        data: SetChunkData {
+            chunk_index: 0,
+            codec: ChunkCodec::Stored,
+            payload: [1, 2, 3, 4].into(),
+        },
+    });
+    logic
+        .update(start + Duration::from_secs(3), &chunk)
+        .expect("chunk should be accepted");
+
+    // The chunk counts as activity, so the transfer is only idle for five seconds.
+    assert!(logic
+        .expire(start + Duration::from_secs(8), max_idle)
+        .is_empty());
+    assert_eq!(logic.expire(start + Duration::from_secs(9), max_idle), [6]);
+    assert!(logic.info().is_none());
+    assert!(logic
+        .expire(start + Duration::from_secs(20), max_idle)
+        .is_empty());
+}
+
+#[test]
+fn keepalive_staves_off_expiry() {
+    let start = Millis::new(0);
+    let max_idle = Duration::from_secs(5);
+    let mut logic = FrontLogic::new();
+    logic
+        .update(
+            start,
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 6,
+                total_octet_size: 8,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    let keepalive = SenderToReceiverFrontCommands::Keepalive(KeepaliveData { transfer_id: 6 });
+    let answer = logic
+        .update(start + Duration::from_secs(4), &keepalive)
+        .expect("keepalive should be accepted");
+    assert!(
+        matches!(answer, ReceiverToSenderFrontCommands::AckChunk(ack) if ack.transfer_id.0 == 6)
+    );
+
+    // Without the keepalive, the transfer would have been idle for eight seconds.
+    assert!(logic
+        .expire(start + Duration::from_secs(8), max_idle)
+        .is_empty());
+    assert_eq!(logic.expire(start + Duration::from_secs(10), max_idle), [6]);
+
+    let err = logic
+        .update(start + Duration::from_secs(11), &keepalive)
+        .expect_err("expired transfer is unknown");
+    assert!(matches!(err, FrontError::TransferNotFound(6)));
+}
+
+#[test]
+fn status_of_concurrent_transfers() {
+    let mut logic = FrontLogic::new();
+    for (transfer_id, total_octet_size) in [(1, 10), (2, 8)] {
+        logic
+            .update(
+                Millis::new(0),
+                &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                    transfer_id,
+                    total_octet_size,
+                    chunk_size: 4,
+                    schedule: vec![],
+                    blob_hash: None,
+                }),
+            )
+            .expect("start transfer should work");
+    }
+    set_chunk_and_check(&mut logic, 1, 2, &[9, 10], 0, 0b10);
+    set_chunk_and_check(&mut logic, 2, 0, &[1, 2, 3, 4], 1, 0);
+    set_chunk_and_check(&mut logic, 1, 0, &[1, 2, 3, 4], 1, 0b1);
+
+    let mut transfer_ids: Vec<u16> = logic.active_transfer_ids().collect();
+    transfer_ids.sort_unstable();
+    assert_eq!(transfer_ids, [1, 2]);
+
+    assert_eq!(
+        logic.transfer_status(1),
+        Some(TransferStatus {
+            octet_count: 10,
+            chunk_size: 4,
+            received_chunk_count: 2,
+            chunk_count: 3,
+            is_complete: false,
+        })
+    );
+    assert_eq!(
+        logic.transfer_status(2),
+        Some(TransferStatus {
+            octet_count: 8,
+            chunk_size: 4,
+            received_chunk_count: 1,
+            chunk_count: 2,
+            is_complete: false,
+        })
+    );
+    assert_eq!(logic.transfer_status(3), None);
+}
+
+#[test]
+fn conflicting_start_transfer_is_rejected() {
+    let mut logic = FrontLogic::new();
+    logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 1,
+                total_octet_size: 8,
+                chunk_size: 2,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    let err = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 1,
+                total_octet_size: 16,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect_err("the parameters differ from the started transfer");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(
+        matches!(err, FrontError::StartMismatch { transfer_id: 1, .. }),
+        "{err:?}"
+    );
+
+    let status = logic
+        .transfer_status(1)
+        .expect("the original transfer should be kept");
+    assert_eq!(status.octet_count, 8);
+    assert_eq!(status.chunk_size, 2);
+}
+
+fn start_hashed_single_chunk_transfer(logic: &mut FrontLogic, blob_hash: [u8; 32]) {
+    let start_transfer = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 9,
+        total_octet_size: 3,
+        chunk_size: 4,
+        schedule: vec![],
+        blob_hash: Some(blob_hash),
+    });
+    logic
+        .update(Millis::new(0), &start_transfer)
+        .expect("start transfer should work");
+}
+
+fn single_chunk() -> SenderToReceiverFrontCommands {
+    SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(9),
+        data: SetChunkData {
+            chunk_index: 0,
+            codec: ChunkCodec::Stored,
+            payload: [0x01, 0x02, 0x03].into(),
+        },
+    })
+}
+
+#[test]
+fn matching_blob_hash_completes_transfer() {
+    let hasher = SumHasher {
+        digest_octet_size: 32,
+    };
+    let blob_hash = hasher.hash(&[0x01, 0x02, 0x03]).try_into().unwrap();
+    let mut logic = FrontLogic::new().with_hasher(Box::new(hasher));
+    start_hashed_single_chunk_transfer(&mut logic, blob_hash);
+
+    let answer = logic
+        .update(Millis::new(0), &single_chunk())
+        .expect("chunk with matching blob hash should be accepted");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(9));
+    assert_eq!(logic.blob(), Some(&[0x01, 0x02, 0x03][..]));
+}
+
+#[test]
+fn mismatching_blob_hash_is_rejected() {
+    let mut logic = FrontLogic::new().with_hasher(Box::new(SumHasher {
+        digest_octet_size: 32,
+    }));
+    start_hashed_single_chunk_transfer(&mut logic, [0xff; 32]);
+
+    let err = logic
+        .update(Millis::new(0), &single_chunk())
+        .expect_err("blob with a different hash should be rejected");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(
+        matches!(err, FrontError::Blob(BlobError::HashMismatch)),
+        "{err:?}"
+    );
+    assert!(logic.transfer_status(9).is_none());
+}
+
+#[test]
+fn blob_hash_of_wrong_size_for_hasher_is_rejected() {
+    let mut logic = FrontLogic::new().with_hasher(Box::new(SumHasher {
+        digest_octet_size: 4,
+    }));
+    let start_transfer = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 9,
+        total_octet_size: 3,
+        chunk_size: 4,
+        schedule: vec![],
+        blob_hash: Some([0; 32]),
+    });
+
+    assert!(logic.update(Millis::new(0), &start_transfer).is_err());
+}
+
+const fn start_transfer_of_size(total_octet_size: u32) -> SenderToReceiverFrontCommands {
+    SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 5,
+        total_octet_size,
+        chunk_size: 4,
+        schedule: vec![],
+        blob_hash: None,
+    })
+}
+
+fn chunk_of_transfer_5(chunk_index: u64, payload: &[u8]) -> SenderToReceiverFrontCommands {
+    SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(5),
+        data: SetChunkData {
+            chunk_index,
+            codec: ChunkCodec::Stored,
+            payload: payload.to_vec(),
+        },
+    })
+}
+
+#[test]
+fn chunk_far_past_gap_is_acknowledged_with_mask() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    logic.update(now, &start_transfer_of_size(400)).unwrap();
+
+    let answer = logic
+        .update(now, &chunk_of_transfer_5(64, &[1; 4]))
+        .unwrap();
+    assert!(
+        matches!(answer, ReceiverToSenderFrontCommands::AckChunk(_)),
+        "{answer:?}"
+    );
+
+    let answer = logic
+        .update(now, &chunk_of_transfer_5(70, &[2; 4]))
+        .unwrap();
+    let ReceiverToSenderFrontCommands::AckChunkMask(ack_mask) = answer else {
+        panic!("chunk 70 is too far past the gap for an AckChunk: {answer:?}");
+    };
+    assert_eq!(ack_mask.data.waiting_for_chunk_index, 0);
+    assert!(ack_mask.data.is_received(64));
+    assert!(ack_mask.data.is_received(70));
+    assert!(!ack_mask.data.is_received(69));
+}
+
+#[test]
+fn compatible_shrink_keeps_received_chunks() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    logic.update(now, &start_transfer_of_size(16)).unwrap();
+    logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).unwrap();
+
+    let answer = logic
+        .update(now, &start_transfer_of_size(10))
+        .expect("received chunks fit the smaller size");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(5));
+
+    let status = logic.transfer_status(5).expect("transfer should be kept");
+    assert_eq!(status.octet_count, 10);
+    assert_eq!(status.chunk_count, 3);
+    assert_eq!(status.received_chunk_count, 1);
+
+    logic.update(now, &chunk_of_transfer_5(1, &[2; 4])).unwrap();
+    let answer = logic.update(now, &chunk_of_transfer_5(2, &[3; 2])).unwrap();
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(5));
+    assert_eq!(logic.blob(), Some(&[1, 1, 1, 1, 2, 2, 2, 2, 3, 3][..]));
+}
+
+#[test]
+fn shrink_to_received_chunks_completes_transfer() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    logic.update(now, &start_transfer_of_size(16)).unwrap();
+    logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).unwrap();
+    logic.update(now, &chunk_of_transfer_5(1, &[2; 4])).unwrap();
+
+    let answer = logic.update(now, &start_transfer_of_size(8)).unwrap();
+
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(5));
+    assert_eq!(logic.blob(), Some(&[1, 1, 1, 1, 2, 2, 2, 2][..]));
+}
+
+#[test]
+fn shrink_past_received_chunks_is_rejected() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    logic.update(now, &start_transfer_of_size(16)).unwrap();
+    logic.update(now, &chunk_of_transfer_5(3, &[4; 4])).unwrap();
+
+    let err = logic
+        .update(now, &start_transfer_of_size(12))
+        .expect_err("chunk 3 does not fit in 12 octets");
+    assert!(
+        matches!(
+            err,
+            FrontError::Blob(BlobError::ReceivedChunkBeyondSize(3, 12))
+        ),
+        "{err:?}"
+    );
+
+    let err = logic
+        .update(now, &start_transfer_of_size(14))
+        .expect_err("chunk 3 would have to be shorter");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let status = logic.transfer_status(5).expect("transfer should be kept");
+    assert_eq!(status.octet_count, 16);
+    assert_eq!(status.received_chunk_count, 1);
+}
+
+#[test]
+fn chunk_size_below_minimum_is_rejected() {
+    let start_transfer = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 2,
+        total_octet_size: 1000,
+        chunk_size: 1,
+        schedule: vec![],
+        blob_hash: None,
+    });
+
+    let mut logic = FrontLogic::new().with_min_chunk_size(64);
+    let err = logic
+        .update(Millis::new(0), &start_transfer)
+        .expect_err("a chunk size of one octet is below the minimum");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(
+        matches!(err, FrontError::Blob(BlobError::ChunkSizeTooSmall(1, 64))),
+        "{err:?}"
+    );
+    assert!(logic.transfer_status(2).is_none());
+
+    let mut logic = FrontLogic::new();
+    let answer = logic
+        .update(Millis::new(0), &start_transfer)
+        .expect("there is no minimum chunk size by default");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(2));
+}
+
+#[test]
+fn small_chunk_size_is_accepted_for_single_chunk_blob() {
+    let mut logic = FrontLogic::new().with_min_chunk_size(64);
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 2,
+                total_octet_size: 16,
+                chunk_size: 16,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("the blob fits in a single chunk");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(2));
+}
+
+const LIMITS: TransferLimits = TransferLimits {
+    max_octet_size: 1024,
+    max_chunk_count: 16,
+    max_transfers: 2,
+};
+
+const fn start_transfer_with(
+    transfer_id: u16,
+    total_octet_size: u32,
+    chunk_size: u32,
+) -> SenderToReceiverFrontCommands {
+    SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id,
+        total_octet_size,
+        chunk_size,
+        schedule: vec![],
+        blob_hash: None,
+    })
+}
+
+#[test]
+fn too_large_transfer_is_rejected() {
+    let mut logic = FrontLogic::new().with_limits(LIMITS);
+
+    let err = logic
+        .update(Millis::new(0), &start_transfer_with(1, 0xffff_ffff, 1024))
+        .expect_err("the size exceeds the limit");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(
+        matches!(err, FrontError::TransferTooLarge { transfer_id: 1, .. }),
+        "{err:?}"
+    );
+
+    logic
+        .update(Millis::new(0), &start_transfer_with(1, 1024, 8))
+        .expect_err("the chunk count exceeds the limit");
+    assert!(logic.transfer_status(1).is_none());
+
+    let answer = logic
+        .update(Millis::new(0), &start_transfer_with(1, 1024, 64))
+        .expect("the transfer is within the limits");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(1));
+}
+
+#[test]
+fn chunk_size_above_maximum_payload_is_rejected() {
+    let mut logic = FrontLogic::new();
+
+    let err = logic
+        .update(
+            Millis::new(0),
+            &start_transfer_with(1, 4 * 1024 * 1024, 2 * 1024 * 1024),
+        )
+        .expect_err("the chunks would exceed the maximum payload");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(
+        matches!(
+            err,
+            FrontError::ChunkSizeTooLarge {
+                transfer_id: 1,
+                chunk_size: 0x20_0000,
+            }
+        ),
+        "{err:?}"
+    );
+    assert!(logic.transfer_status(1).is_none());
+
+    // The chunk size is capped to the size of a smaller blob.
+    logic
+        .update(
+            Millis::new(0),
+            &start_transfer_with(1, 1024, 2 * 1024 * 1024),
+        )
+        .expect("the single chunk fits in a payload");
+}
+
+#[test]
+fn tiny_chunks_of_huge_transfer_exceed_max_chunk_count() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new().with_max_chunk_count(1024);
+
+    let err = logic
+        .update(now, &start_transfer_with(1, 0xffff_ffff, 1))
+        .expect_err("four billion chunks exceed the limit");
+    assert!(
+        matches!(err, FrontError::TransferTooLarge { transfer_id: 1, .. }),
+        "{err:?}"
+    );
+    assert!(logic.transfer_status(1).is_none());
+
+    logic
+        .update(now, &start_transfer_with(1, 1025, 1))
+        .expect_err("one chunk more than the limit");
+
+    let answer = logic
+        .update(now, &start_transfer_with(1, 1024, 1))
+        .expect("the chunk count is at the limit");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(1));
+}
+
+#[test]
+fn starting_more_than_max_transfers_fails() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new().with_limits(LIMITS);
+    logic.update(now, &start_transfer_with(1, 100, 10)).unwrap();
+    logic.update(now, &start_transfer_with(2, 100, 10)).unwrap();
+
+    let err = logic
+        .update(now, &start_transfer_with(3, 100, 10))
+        .expect_err("only two transfers are allowed at the same time");
+    assert!(
+        matches!(
+            err,
+            FrontError::TooManyTransfers {
+                transfer_id: 3,
+                max_transfers: 2
+            }
+        ),
+        "{err:?}"
+    );
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(logic.transfer_status(3).is_none());
+
+    // Repeating the start of a known transfer does not count as a new transfer.
+    logic.update(now, &start_transfer_with(2, 100, 10)).unwrap();
+
+    logic.remove_transfer(1);
+    logic
+        .update(now, &start_transfer_with(3, 100, 10))
+        .expect("a transfer slot has been freed");
+}
+
+#[test]
+fn full_receiver_evicts_least_recently_active_transfer() {
+    let start = Millis::new(0);
+    let mut logic = FrontLogic::new()
+        .with_limits(LIMITS)
+        .with_transfer_capacity_policy(TransferCapacityPolicy::EvictLeastRecentlyActive);
+    logic
+        .update(start, &start_transfer_with(1, 100, 10))
+        .unwrap();
+    logic
+        .update(
+            start + Duration::from_secs(1),
+            &start_transfer_with(2, 100, 10),
+        )
+        .unwrap();
+
+    // A chunk for transfer 1 makes transfer 2 the stalest one.
+    let chunk = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(1),
+        data: SetChunkData {
+            chunk_index: 0,
+            codec: ChunkCodec::Stored,
+            payload: vec![1; 10],
+        },
+    });
+    logic
+        .update(start + Duration::from_secs(2), &chunk)
+        .unwrap();
+
+    let answer = logic
+        .update(
+            start + Duration::from_secs(3),
+            &start_transfer_with(3, 100, 10),
+        )
+        .expect("the stalest transfer should be evicted");
+
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(3));
+    assert_eq!(logic.take_evicted_transfer_ids(), [2]);
+    assert!(logic.take_evicted_transfer_ids().is_empty());
+    assert!(logic.transfer_status(2).is_none());
+    assert_eq!(logic.transfer_status(1).unwrap().received_chunk_count, 1);
+    assert!(logic.transfer_status(3).is_some());
+}
+
+#[test]
+fn completed_transfers_are_not_evicted() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new()
+        .with_limits(LIMITS)
+        .with_transfer_capacity_policy(TransferCapacityPolicy::EvictLeastRecentlyActive);
+    complete_single_chunk_transfer(&mut logic, 1);
+    complete_single_chunk_transfer(&mut logic, 2);
+
+    logic
+        .update(now, &start_transfer_with(3, 100, 10))
+        .expect_err("all transfers are complete");
+    assert!(logic.take_evicted_transfer_ids().is_empty());
+}
+
+#[test]
+fn zero_chunk_size_is_rejected() {
+    let mut logic = FrontLogic::new();
+    logic
+        .update(Millis::new(0), &start_transfer_with(1, 100, 0))
+        .expect_err("chunk size must not be zero");
+}
+
+#[test]
+fn cancel_peer_removes_only_its_transfers() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    let first_peer = PeerId(100);
+    let second_peer = PeerId(200);
+    logic
+        .update_for_peer(now, first_peer, &start_transfer_with(1, 100, 10))
+        .unwrap();
+    logic
+        .update_for_peer(now, second_peer, &start_transfer_with(2, 100, 10))
+        .unwrap();
+    logic
+        .update_for_peer(now, first_peer, &start_transfer_with(3, 100, 10))
+        .unwrap();
+
+    assert_eq!(logic.cancel_peer(first_peer), [1, 3]);
+
+    assert!(logic.transfer_status(1).is_none());
+    assert!(logic.transfer_status(3).is_none());
+    assert!(logic.transfer_status(2).is_some());
+    assert!(logic.cancel_peer(first_peer).is_empty());
+}
+
+#[test]
+fn peer_can_not_use_transfer_of_other_peer() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    logic
+        .update_for_peer(now, PeerId(100), &start_transfer_with(1, 100, 10))
+        .unwrap();
+
+    let err = logic
+        .update_for_peer(now, PeerId(200), &start_transfer_with(1, 100, 10))
+        .expect_err("transfer 1 belongs to the first peer");
+    assert!(
+        matches!(
+            err,
+            FrontError::PeerMismatch {
+                transfer_id: 1,
+                peer_id: PeerId(200)
+            }
+        ),
+        "{err:?}"
+    );
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(logic.cancel_peer(PeerId(200)).is_empty());
+    assert_eq!(logic.cancel_peer(PeerId(100)), [1]);
+}
+
+#[test]
+fn kept_chunks_of_other_peer_are_not_applied() {
+    let now = Millis::new(0);
+    let mut logic =
+        FrontLogic::new().with_unknown_transfer_policy(UnknownTransferPolicy::NeedStart);
+    let chunk = |chunk_index, payload: [u8; 2]| {
+        SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+            transfer_id: TransferId(4),
+            data: SetChunkData {
+                chunk_index,
+                codec: ChunkCodec::Stored,
+                payload: payload.into(),
+            },
+        })
+    };
+    let start_transfer = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 4,
+        total_octet_size: 4,
+        chunk_size: 2,
+        schedule: vec![],
+        blob_hash: None,
+    });
+
+    let answer = logic
+        .update_for_peer(now, PeerId(200), &chunk(0, [0xBA, 0xD0]))
+        .expect("chunk for an unknown transfer should be kept");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::NeedStart(4));
+    logic
+        .update_for_peer(now, PeerId(100), &chunk(1, [0x03, 0x04]))
+        .expect("chunk for an unknown transfer should be kept");
+
+    let answer = logic
+        .update_for_peer(now, PeerId(100), &start_transfer)
+        .expect("start transfer should work");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(4));
+    assert_eq!(
+        logic
+            .info()
+            .expect("transfer has been started")
+            .chunk_count_received,
+        1
+    );
+
+    let answer = logic
+        .update_for_peer(now, PeerId(100), &chunk(0, [0x01, 0x02]))
+        .expect("chunk should be accepted");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(4));
+    assert_eq!(
+        logic.blob().expect("blob should be complete"),
+        &[0x01, 0x02, 0x03, 0x04]
+    );
+}
+
+#[test]
+fn cancel_peer_drops_its_kept_chunks() {
+    let now = Millis::new(0);
+    let mut logic =
+        FrontLogic::new().with_unknown_transfer_policy(UnknownTransferPolicy::NeedStart);
+    let chunk = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(4),
+        data: SetChunkData {
+            chunk_index: 0,
+            codec: ChunkCodec::Stored,
+            payload: [0x01, 0x02].into(),
+        },
+    });
+    logic
+        .update_for_peer(now, PeerId(100), &chunk)
+        .expect("chunk for an unknown transfer should be kept");
+
+    assert!(logic.cancel_peer(PeerId(100)).is_empty());
+
+    logic
+        .update_for_peer(now, PeerId(100), &start_transfer_with(4, 4, 2))
+        .expect("start transfer should work");
+    assert_eq!(
+        logic
+            .info()
+            .expect("transfer has been started")
+            .chunk_count_received,
+        0
+    );
+}
+
+fn chunks_of_transfer_5(chunks: &[(u64, &[u8])]) -> SenderToReceiverFrontCommands {
+    SenderToReceiverFrontCommands::SetChunks(SetChunksFrontData {
+        transfer_id: TransferId(5),
+        chunks: chunks
+            .iter()
+            .map(|&(chunk_index, payload)| SetChunkData {
+                chunk_index,
+                codec: ChunkCodec::Stored,
+                payload: payload.to_vec(),
+            })
+            .collect(),
+    })
+}
+
+#[test]
+fn set_chunks_applies_batch_with_single_ack() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    logic.update(now, &start_transfer_of_size(16)).unwrap();
+
+    let answer = logic
+        .update(
+            now,
+            &chunks_of_transfer_5(&[(0, &[1; 4]), (2, &[3; 4]), (1, &[2; 4])]),
+        )
+        .expect("batch should be applied");
+    let ReceiverToSenderFrontCommands::AckChunk(ack) = answer else {
+        panic!("expected a single AckChunk, got {answer:?}");
+    };
+    assert_eq!(ack.data.waiting_for_chunk_index, 3);
+    assert_eq!(logic.transfer_status(5).unwrap().received_chunk_count, 3);
+
+    let answer = logic
+        .update(now, &chunks_of_transfer_5(&[(3, &[4; 4])]))
+        .unwrap();
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(5));
+    assert_eq!(
+        logic.blob(),
+        Some(&[1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4][..])
+    );
+}
+
+#[test]
+fn set_chunks_with_invalid_chunk_applies_nothing() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    logic.update(now, &start_transfer_of_size(16)).unwrap();
+
+    let err = logic
+        .update(
+            now,
+            &chunks_of_transfer_5(&[(0, &[1; 4]), (1, &[2; 4]), (2, &[3; 3])]),
+        )
+        .expect_err("chunk 2 has the wrong size");
+    assert!(
+        matches!(
+            err,
+            FrontError::Blob(BlobError::UnexpectedChunkSize(4, 3, 2))
+        ),
+        "{err:?}"
+    );
+    assert_eq!(logic.transfer_status(5).unwrap().received_chunk_count, 0);
+
+    logic
+        .update(
+            now,
+            &chunks_of_transfer_5(&[(0, &[1; 4]), (1, &[2; 4]), (0, &[1; 4])]),
+        )
+        .expect_err("chunk 0 appears twice");
+    assert_eq!(logic.transfer_status(5).unwrap().received_chunk_count, 0);
+}
+
+#[test]
+fn stats_are_summed_over_transfers() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    logic.update(now, &start_transfer_of_size(8)).unwrap();
+    logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).unwrap();
+    assert!(logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).is_err());
+    assert_eq!(logic.stats().duplicate_chunk_count, 1);
+
+    logic.update(now, &start_transfer_with(9, 3, 3)).unwrap();
+    logic.remove_transfer(5);
+    let answer = logic.update(now, &single_chunk()).unwrap();
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(9));
+
+    let stats = logic.stats();
+    assert_eq!(stats.accepted_chunk_count, 2);
+    assert_eq!(stats.accepted_octet_count, 7);
+    assert_eq!(stats.duplicate_chunk_count, 1);
+}
+
+const CHECKPOINT_BLOB: [u8; 16] = [1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4];
+
+fn checkpoint_of_transfer_5(up_to_chunk: usize) -> SenderToReceiverFrontCommands {
+    OutLogicFront::new(TransferId(5), CHECKPOINT_BLOB.to_vec(), 4)
+        .hash_checkpoint(
+            &SumHasher {
+                digest_octet_size: 4,
+            },
+            up_to_chunk,
+        )
+        .expect("checkpoint is within the blob")
+}
+
+fn checkpoint_receiver() -> FrontLogic {
+    let mut logic = FrontLogic::new().with_hasher(Box::new(SumHasher {
+        digest_octet_size: 4,
+    }));
+    logic
+        .update(Millis::new(0), &start_transfer_of_size(16))
+        .unwrap();
+    logic
+}
+
+#[test]
+fn correct_hash_checkpoints_are_verified() {
+    let now = Millis::new(0);
+    let mut logic = checkpoint_receiver();
+
+    // The checkpoint arrives before the second chunk, so it is verified once that arrives.
+    logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).unwrap();
+    let answer = logic.update(now, &checkpoint_of_transfer_5(2)).unwrap();
+    assert!(matches!(answer, ReceiverToSenderFrontCommands::AckChunk(_)));
+    logic.update(now, &chunk_of_transfer_5(1, &[2; 4])).unwrap();
+
+    logic.update(now, &chunk_of_transfer_5(2, &[3; 4])).unwrap();
+    logic.update(now, &chunk_of_transfer_5(3, &[4; 4])).unwrap();
+    assert_eq!(logic.blob(), Some(&CHECKPOINT_BLOB[..]));
+}
+
+#[test]
+fn corrupted_chunk_fails_its_hash_checkpoint() {
+    let now = Millis::new(0);
+    let mut logic = checkpoint_receiver();
+    logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).unwrap();
+    logic.update(now, &chunk_of_transfer_5(1, &[2; 4])).unwrap();
+    logic.update(now, &checkpoint_of_transfer_5(2)).unwrap();
+    logic
+        .update(now, &chunk_of_transfer_5(2, &[3, 3, 9, 3]))
+        .unwrap();
+
+    let err = logic
+        .update(now, &checkpoint_of_transfer_5(3))
+        .expect_err("chunk 2 is corrupted");
+    assert!(
+        matches!(err, FrontError::Blob(BlobError::CheckpointMismatch(3))),
+        "{err:?}"
+    );
+    assert!(logic.transfer_status(5).is_none());
+}
+
+#[test]
+fn first_chunk_is_reported_to_header_hook_and_acked() {
+    let now = Millis::new(0);
+    let headers = Arc::new(Mutex::new(Vec::new()));
+    let reported = Arc::clone(&headers);
+    let mut logic = FrontLogic::new().with_header_hook(move |transfer_id, header| {
+        reported
+            .lock()
+            .unwrap()
+            .push((transfer_id, header.to_vec()));
+    });
+    logic.update(now, &start_transfer_of_size(16)).unwrap();
+
+    logic.update(now, &chunk_of_transfer_5(2, &[3; 4])).unwrap();
+    assert!(headers.lock().unwrap().is_empty());
+
+    let answer = logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).unwrap();
+    let ReceiverToSenderFrontCommands::AckChunk(ack) = answer else {
+        panic!("expected AckChunk, got {answer:?}");
+    };
+    assert_eq!(ack.data.waiting_for_chunk_index, 1);
+    assert_eq!(*headers.lock().unwrap(), [(TransferId(5), vec![1; 4])]);
+
+    assert!(logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).is_err());
+    logic.update(now, &chunk_of_transfer_5(1, &[2; 4])).unwrap();
+    assert_eq!(headers.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn chunk_size_larger_than_blob_is_capped() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new();
+    let start = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 5,
+        total_octet_size: 3,
+        chunk_size: 10,
+        schedule: vec![],
+        blob_hash: None,
+    });
+    logic.update(now, &start).unwrap();
+
+    let info = logic.info().unwrap();
+    assert_eq!(info.fixed_chunk_size, 3);
+    assert_eq!(info.octet_count, 3);
+    assert_eq!(logic.transfer_status(5).unwrap().chunk_count, 1);
+
+    // A resent start with the same parameters is still the same transfer.
+    let answer = logic.update(now, &start).unwrap();
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(5));
+
+    let answer = logic
+        .update(now, &chunk_of_transfer_5(0, &[7, 8, 9]))
+        .unwrap();
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(5));
+    assert_eq!(logic.transfer_blob(5), Some(&[7, 8, 9][..]));
+}
+
+#[test]
+fn completed_blob_is_handed_to_completion_hook_once() {
+    let now = Millis::new(0);
+    let completed = Arc::new(Mutex::new(Vec::new()));
+    let reported = Arc::clone(&completed);
+    let mut logic = FrontLogic::new().with_completion_hook(move |transfer_id, blob| {
+        reported.lock().unwrap().push((transfer_id, blob));
+    });
+    logic.update(now, &start_transfer_of_size(6)).unwrap();
+
+    logic.update(now, &chunk_of_transfer_5(1, &[2; 2])).unwrap();
+    assert!(completed.lock().unwrap().is_empty());
+
+    let answer = logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).unwrap();
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(5));
+    assert_eq!(
+        *completed.lock().unwrap(),
+        [(TransferId(5), vec![1, 1, 1, 1, 2, 2])]
+    );
+    assert!(logic.transfer_status(5).is_none());
+
+    // A resent chunk and a resent start are answered, but the blob is not handed over again.
+    let answer = logic.update(now, &chunk_of_transfer_5(0, &[1; 4])).unwrap();
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(5));
+    let answer = logic.update(now, &start_transfer_of_size(6)).unwrap();
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(5));
+    assert_eq!(completed.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn repeated_start_of_earlier_delivered_transfer_is_not_delivered_again() {
+    let (sender, receiver) = std::sync::mpsc::sync_channel(2);
+    let mut logic = FrontLogic::new().with_completion_sender(sender);
+    complete_single_chunk_transfer(&mut logic, 1);
+    complete_single_chunk_transfer(&mut logic, 2);
+    assert_eq!(receiver.try_iter().count(), 2);
+
+    // The `Completed` for the first transfer was lost, so the sender starts it again.
+    let answer = logic
+        .update(Millis::new(0), &start_transfer_with(1, 3, 4))
+        .expect("start transfer should work");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(1));
+    assert!(logic.transfer_status(1).is_none());
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn delivered_transfer_id_can_be_reused_with_other_size() {
+    let (sender, receiver) = std::sync::mpsc::sync_channel(2);
+    let mut logic = FrontLogic::new().with_completion_sender(sender);
+    complete_single_chunk_transfer(&mut logic, 1);
+    assert_eq!(receiver.try_iter().count(), 1);
+
+    let answer = logic
+        .update(Millis::new(0), &start_transfer_with(1, 8, 4))
+        .expect("start transfer should work");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::AckStart(1));
+    let status = logic.transfer_status(1).expect("a new transfer is started");
+    assert_eq!(status.octet_count, 8);
+    assert_eq!(status.received_chunk_count, 0);
+}
+
+#[test]
+fn start_transfer_of_zero_octets_is_completed() {
+    let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+    let mut logic = FrontLogic::new().with_completion_sender(sender);
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 4,
+                total_octet_size: 0,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(4));
+    let (transfer_id, blob) = receiver.try_recv().expect("blob should be delivered");
+    assert_eq!(transfer_id, TransferId(4));
+    assert!(blob.is_empty());
+}
+
+#[test]
+fn blobs_of_concurrent_transfers_by_id() {
+    let mut logic = FrontLogic::new();
+    complete_single_chunk_transfer(&mut logic, 1);
+    logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 2,
+                total_octet_size: 6,
+                chunk_size: 4,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .expect("start transfer should work");
+    set_chunk_and_check(&mut logic, 2, 0, &[4, 5, 6, 7], 1, 0);
+    assert_eq!(logic.transfer_blob(2), None);
+
+    let answer = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                transfer_id: TransferId(2),
+                data: SetChunkData {
+                    chunk_index: 1,
+                    codec: ChunkCodec::Stored,
+                    payload: [8, 9].into(),
+                },
+            }),
+        )
+        .expect("chunk should be accepted");
+    assert_eq!(answer, ReceiverToSenderFrontCommands::Completed(2));
+
+    assert_eq!(logic.transfer_blob(1), Some(&[0x01, 0x02, 0x03][..]));
+    assert_eq!(logic.transfer_blob(2), Some(&[4, 5, 6, 7, 8, 9][..]));
+    assert_eq!(logic.transfer_blob(3), None);
+}
+
+#[test]
+fn start_transfer_with_zero_chunk_size_is_rejected() {
+    let mut logic = FrontLogic::new();
+
+    let err = logic
+        .update(
+            Millis::new(0),
+            &SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+                transfer_id: 1,
+                total_octet_size: 8,
+                chunk_size: 0,
+                schedule: vec![],
+                blob_hash: None,
+            }),
+        )
+        .unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(
+        matches!(err, FrontError::Blob(BlobError::InvalidChunkSize(0))),
+        "{err:?}"
+    );
+    assert_eq!(logic.transfer_status(1), None);
+}
+
+#[test]
+fn receive_window_acknowledges_with_credit() {
+    let now = Millis::new(0);
+    let mut logic = FrontLogic::new().with_receive_window(2);
+    logic
+        .update(now, &start_transfer_with(5, 16, 4))
+        .expect("start transfer should work");
+
+    let answer = logic
+        .update(
+            now,
+            &SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+                transfer_id: TransferId(5),
+                data: SetChunkData {
+                    chunk_index: 0,
+                    codec: ChunkCodec::Stored,
+                    payload: [0x01, 0x02, 0x03, 0x04].into(),
+                },
+            }),
+        )
+        .expect("chunk should be acknowledged");
+    let ReceiverToSenderFrontCommands::AckChunkCredit(ack) = answer else {
+        panic!("expected AckChunkCredit, got {answer:?}");
+    };
+    assert_eq!(ack.transfer_id, TransferId(5));
+    // Chunks 1 and 2 fit in the window.
+    assert_eq!(ack.data.credit, 2);
+}
@@ -0,0 +1,74 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use blob_stream::in_range_stream::BlobRangeStreamIn;
+use blob_stream::prelude::*;
+
+#[test]
+fn non_overlapping_tiling() {
+    let mut stream = BlobRangeStreamIn::new(9);
+
+    stream
+        .set_range(4, &[0xff, 0xfe, 0xfd])
+        .expect("Setting 4..7 should work");
+    stream
+        .set_range(0, &[0x31, 0x32, 0x33, 0x34])
+        .expect("Setting 0..4 should work");
+
+    assert!(!stream.is_complete());
+    assert_eq!(stream.received_ranges().len(), 1);
+    assert_eq!(stream.received_ranges()[0], 0..7);
+
+    stream
+        .set_range(7, &[0x42, 0x43])
+        .expect("Setting 7..9 should work");
+
+    assert!(stream.is_complete());
+    assert_eq!(
+        stream.blob().expect("Blob slice should be complete"),
+        &[0x31, 0x32, 0x33, 0x34, 0xff, 0xfe, 0xfd, 0x42, 0x43]
+    );
+}
+
+#[test]
+fn exact_duplicate_is_idempotent() {
+    let mut stream = BlobRangeStreamIn::new(8);
+
+    stream
+        .set_range(2, &[0x10, 0x20, 0x30])
+        .expect("Setting 2..5 should work");
+    stream
+        .set_range(2, &[0x10, 0x20, 0x30])
+        .expect("Setting the same range again should be accepted");
+
+    assert_eq!(stream.received_ranges().len(), 1);
+    assert_eq!(stream.received_ranges()[0], 2..5);
+}
+
+#[test]
+fn conflicting_overlap_is_rejected() {
+    let mut stream = BlobRangeStreamIn::new(8);
+
+    stream
+        .set_range(2, &[0x10, 0x20, 0x30])
+        .expect("Setting 2..5 should work");
+
+    let err = stream
+        .set_range(4, &[0x31, 0x40])
+        .expect_err("overlap with different contents should fail");
+
+    match err {
+        BlobError::OverlappingChunk {
+            existing_range,
+            new_range,
+        } => {
+            assert_eq!(existing_range, 2..5);
+            assert_eq!(new_range, 4..6);
+        }
+        _ => panic!("unexpected error {err}"),
+    }
+
+    assert_eq!(stream.received_ranges().len(), 1);
+    assert_eq!(stream.received_ranges()[0], 2..5);
+}
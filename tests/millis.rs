@@ -0,0 +1,30 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use blob_stream::millis::Millis;
+use std::time::Duration;
+
+#[test]
+fn adding_a_duration_advances_whole_milliseconds() {
+    let mut now = Millis::new(1000);
+    now += Duration::from_micros(2500);
+    assert_eq!(now, Millis::new(1002));
+    assert_eq!(now + Duration::from_secs(1), Millis::new(2002));
+
+    assert_eq!(
+        Millis::new(u64::MAX - 1) + Duration::MAX,
+        Millis::new(u64::MAX)
+    );
+}
+
+#[test]
+fn duration_since_an_earlier_time_saturates_at_zero() {
+    let earlier = Millis::new(100);
+    let later = Millis::from(350);
+    assert_eq!(
+        later.saturating_duration_since(earlier),
+        Duration::from_millis(250)
+    );
+    assert_eq!(earlier.saturating_duration_since(later), Duration::ZERO);
+}
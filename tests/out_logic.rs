@@ -0,0 +1,538 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "std")]
+
+use blob_stream::codec::ChunkCodec;
+use blob_stream::datagram::DatagramBatcher;
+use blob_stream::err::BlobError;
+use blob_stream::in_logic;
+use blob_stream::millis::Millis;
+use blob_stream::out_logic::{Logic, ResendOrder, DEFAULT_RESEND_INTERVAL};
+use blob_stream::protocol::{AckChunkCreditData, AckChunkData, RequestChunksData};
+use blob_stream::protocol_front::SetChunkFrontData;
+use std::time::Duration;
+
+#[test]
+fn send_respects_short_final_chunk() {
+    let mut logic = Logic::new(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10], 4);
+    assert_eq!(logic.chunk_count(), 3);
+
+    let chunks = logic.send(Millis::new(0), 10);
+
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0].payload, [1, 2, 3, 4]);
+    assert_eq!(chunks[1].payload, [5, 6, 7, 8]);
+    assert_eq!(chunks[2].chunk_index, 2);
+    assert_eq!(chunks[2].payload, [9, 10]);
+}
+
+#[test]
+fn ack_mask_skips_acked_chunks() {
+    let mut logic = Logic::new(vec![0; 20], 4);
+
+    logic
+        .receive(&AckChunkData {
+            waiting_for_chunk_index: 1,
+            receive_mask_after_last: 0b101, // chunk 2 and 4
+        })
+        .expect("ack should be valid");
+
+    assert_eq!(logic.acked_chunk_count(), 3);
+    let chunk_indices: Vec<u64> = logic
+        .send(Millis::new(0), 10)
+        .iter()
+        .map(|c| c.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [1, 3]);
+}
+
+#[test]
+fn ack_past_end_is_rejected() {
+    let mut logic = Logic::new(vec![0; 8], 4);
+
+    logic
+        .receive(&AckChunkData {
+            waiting_for_chunk_index: 3,
+            receive_mask_after_last: 0,
+        })
+        .expect_err("chunk index 3 is past the end");
+}
+
+#[test]
+fn full_send_and_ack_cycle() {
+    let blob: Vec<u8> = (0..=254).collect();
+    let mut out_logic = Logic::new(blob.clone(), 16);
+    let mut in_logic = in_logic::Logic::new(blob.len(), 16);
+
+    let now = Millis::new(0);
+    while !out_logic.is_complete() {
+        for chunk in out_logic.send(now, 5) {
+            let ack = in_logic.update(&chunk).expect("chunk should be accepted");
+            out_logic.receive(&ack).expect("ack should be valid");
+        }
+    }
+
+    assert_eq!(out_logic.acked_chunk_count(), 16);
+    assert!(out_logic.send(now, 5).is_empty());
+    assert_eq!(in_logic.blob().expect("blob should be complete"), blob);
+}
+
+#[test]
+fn paused_sender_does_not_send() {
+    let mut logic = Logic::new(vec![0; 8], 4);
+
+    logic.pause();
+    assert!(logic.send(Millis::new(0), 10).is_empty());
+
+    logic.resume();
+    assert_eq!(logic.send(Millis::new(0), 10).len(), 2);
+}
+
+#[test]
+fn round_robin_continues_after_last_sent_chunk() {
+    let mut logic = Logic::new(vec![0; 20], 4).with_resend_order(ResendOrder::RoundRobin);
+
+    let now = Millis::new(0);
+    let chunk_indices: Vec<u64> = logic.send(now, 3).iter().map(|c| c.chunk_index).collect();
+    assert_eq!(chunk_indices, [0, 1, 2]);
+
+    let later = now + DEFAULT_RESEND_INTERVAL;
+    let chunk_indices: Vec<u64> = logic.send(later, 3).iter().map(|c| c.chunk_index).collect();
+    assert_eq!(chunk_indices, [3, 4, 0]);
+}
+
+/// Sends `round_count` rounds over a link that drops about half of the chunks,
+/// and returns how far the receiver got in sequence.
+fn frontier_after_lossy_rounds(resend_order: ResendOrder, round_count: usize) -> usize {
+    const CHUNK_SIZE: usize = 4;
+    const CHUNK_COUNT: usize = 64;
+
+    let mut out_logic = Logic::new(vec![0x42; CHUNK_SIZE * CHUNK_COUNT], CHUNK_SIZE)
+        .with_resend_order(resend_order);
+    let mut in_logic = in_logic::Logic::new(CHUNK_SIZE * CHUNK_COUNT, CHUNK_SIZE);
+
+    // Same deterministic loss pattern for every run
+    let mut random_state: u32 = 0x1234_5678;
+    let mut now = Millis::new(0);
+    for _ in 0..round_count {
+        now += DEFAULT_RESEND_INTERVAL;
+        for chunk in out_logic.send(now, 4) {
+            random_state = random_state
+                .wrapping_mul(1_664_525)
+                .wrapping_add(1_013_904_223);
+            let is_lost = random_state >> 31 == 1;
+            if is_lost {
+                continue;
+            }
+            if let Ok(ack) = in_logic.update(&chunk) {
+                out_logic.receive(&ack).expect("ack should be valid");
+            }
+        }
+    }
+
+    in_logic.info().waiting_for_chunk_index
+}
+
+#[test]
+fn lowest_index_first_advances_frontier_faster_under_loss() {
+    let lowest_index_first = frontier_after_lossy_rounds(ResendOrder::LowestIndexFirst, 20);
+    let round_robin = frontier_after_lossy_rounds(ResendOrder::RoundRobin, 20);
+
+    assert!(
+        lowest_index_first > round_robin,
+        "lowest index first reached {lowest_index_first}, round robin reached {round_robin}"
+    );
+}
+
+#[test]
+fn tick_resends_unacked_chunks_after_interval() {
+    let interval = Duration::from_millis(50);
+    let mut logic = Logic::new(vec![0; 16], 4).with_resend_interval(interval);
+    let start = Millis::new(0);
+
+    assert_eq!(logic.send(start, 10).len(), 4);
+    assert!(logic.tick(start + interval / 2).is_empty());
+
+    logic
+        .receive(&AckChunkData {
+            waiting_for_chunk_index: 1,
+            receive_mask_after_last: 0b1, // chunk 2 is also received
+        })
+        .expect("ack should be valid");
+
+    let resent: Vec<u64> = logic
+        .tick(start + interval)
+        .iter()
+        .map(|c| c.chunk_index)
+        .collect();
+    assert_eq!(resent, [1, 3]);
+
+    // The resent chunks wait for a new interval, and acked chunks are never resent.
+    assert!(logic.tick(start + interval + interval / 2).is_empty());
+    let resent: Vec<u64> = logic
+        .tick(start + interval * 2)
+        .iter()
+        .map(|c| c.chunk_index)
+        .collect();
+    assert_eq!(resent, [1, 3]);
+}
+
+#[test]
+fn send_skips_chunks_waiting_for_ack() {
+    let mut logic = Logic::new(vec![0; 16], 4);
+    let now = Millis::new(0);
+
+    assert_eq!(logic.send(now, 2).len(), 2);
+    let chunk_indices: Vec<u64> = logic.send(now, 10).iter().map(|c| c.chunk_index).collect();
+    assert_eq!(chunk_indices, [2, 3]);
+    assert!(logic.send(now, 10).is_empty());
+}
+
+#[test]
+fn window_caps_chunks_in_flight() {
+    let mut logic = Logic::new(vec![0; 40], 4).with_window_size(3);
+    let now = Millis::new(0);
+
+    let chunk_indices: Vec<u64> = logic.send(now, 10).iter().map(|c| c.chunk_index).collect();
+    assert_eq!(chunk_indices, [0, 1, 2]);
+    assert_eq!(logic.in_flight_count(), 3);
+
+    // Resending the chunks in flight does not let any new chunks through.
+    let later = now + DEFAULT_RESEND_INTERVAL;
+    let chunk_indices: Vec<u64> = logic
+        .send(later, 10)
+        .iter()
+        .map(|c| c.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [0, 1, 2]);
+    assert_eq!(logic.tick(later + DEFAULT_RESEND_INTERVAL).len(), 3);
+    assert_eq!(logic.in_flight_count(), 3);
+}
+
+#[test]
+fn acking_oldest_chunk_frees_a_window_slot() {
+    let mut logic = Logic::new(vec![0; 40], 4).with_window_size(3);
+    let now = Millis::new(0);
+    assert_eq!(logic.send(now, 10).len(), 3);
+
+    logic
+        .receive(&AckChunkData {
+            waiting_for_chunk_index: 1,
+            receive_mask_after_last: 0,
+        })
+        .expect("ack should be valid");
+    assert_eq!(logic.in_flight_count(), 2);
+
+    let chunk_indices: Vec<u64> = logic.send(now, 10).iter().map(|c| c.chunk_index).collect();
+    assert_eq!(chunk_indices, [3]);
+    assert_eq!(logic.in_flight_count(), 3);
+}
+
+#[test]
+fn credit_caps_chunks_in_flight() {
+    let mut out_logic = Logic::new(vec![0; 40], 4);
+    let mut in_logic = in_logic::Logic::new(40, 4).with_receive_window(4);
+    let now = Millis::new(0);
+
+    out_logic
+        .receive_with_credit(&in_logic.ack_with_credit())
+        .expect("ack should be valid");
+    let chunks = out_logic.send(now, 10);
+    assert_eq!(chunks.len(), 4);
+
+    // Chunk 1 is received, which does not move the window since chunk 0 is still missing.
+    in_logic
+        .update(&chunks[1])
+        .expect("chunk should be accepted");
+    let ack_credit = in_logic.ack_with_credit();
+    assert_eq!(ack_credit.credit, 3);
+
+    out_logic
+        .receive_with_credit(&ack_credit)
+        .expect("ack should be valid");
+    assert_eq!(out_logic.in_flight_count(), 3);
+    assert!(out_logic.send(now, 10).is_empty());
+
+    // Chunk 0 moves the window two chunks ahead.
+    in_logic
+        .update(&chunks[0])
+        .expect("chunk should be accepted");
+    let ack_credit = in_logic.ack_with_credit();
+    assert_eq!(ack_credit.credit, 4);
+
+    out_logic
+        .receive_with_credit(&ack_credit)
+        .expect("ack should be valid");
+    let chunk_indices: Vec<u64> = out_logic
+        .send(now, 10)
+        .iter()
+        .map(|c| c.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [4, 5]);
+}
+
+#[test]
+fn wide_ack_mask_covers_chunks_past_64() {
+    const CHUNK_COUNT: usize = 200;
+    const GAP_INDEX: u64 = 5;
+    let blob: Vec<u8> = (0..=199).collect();
+    let mut sender = Logic::new(blob.clone(), 1);
+    let mut receiver = in_logic::Logic::new(blob.len(), 1);
+
+    for chunk in sender.send(Millis::new(0), CHUNK_COUNT) {
+        if chunk.chunk_index != GAP_INDEX {
+            receiver.update(&chunk).expect("chunk should be accepted");
+        }
+    }
+
+    // The 64 bit mask only reaches chunk 69, so the rest would be sent again.
+    let mut narrow_sender = Logic::new(blob, 1);
+    assert_eq!(
+        narrow_sender.send(Millis::new(0), CHUNK_COUNT).len(),
+        CHUNK_COUNT
+    );
+    narrow_sender
+        .receive(&receiver.ack())
+        .expect("ack should be valid");
+    assert_eq!(narrow_sender.acked_chunk_count(), 69);
+
+    let ack_mask = receiver.ack_mask();
+    assert_eq!(ack_mask.waiting_for_chunk_index, GAP_INDEX);
+    assert!(ack_mask.is_received(199));
+    sender
+        .receive_mask(&ack_mask)
+        .expect("ack mask should be valid");
+    assert_eq!(sender.acked_chunk_count(), CHUNK_COUNT - 1);
+
+    let resent: Vec<u64> = sender
+        .send(Millis::new(0) + DEFAULT_RESEND_INTERVAL, CHUNK_COUNT)
+        .iter()
+        .map(|c| c.chunk_index)
+        .collect();
+    assert_eq!(resent, [GAP_INDEX]);
+}
+
+#[test]
+fn planned_datagram_count_accounts_for_batching() {
+    // 10 chunks of 100 octets and a last chunk of 50 octets.
+    let logic = Logic::new(vec![0; 1050], 100);
+    assert_eq!(logic.planned_datagram_count(), 11);
+
+    // Each full command is 1 + 1 + 2 + 8 + 1 + 4 + 100 = 117 octets (121 with the crc32 trailer),
+    // so four fit in a datagram.
+    let datagram_octet_size = 4 * SetChunkFrontData::command_octet_size_for_payload(100);
+    let mut logic = Logic::new(vec![0; 1050], 100)
+        .with_datagram_batcher(DatagramBatcher::new(datagram_octet_size));
+    assert_eq!(logic.planned_datagram_count(), 3);
+
+    logic
+        .receive(&AckChunkData {
+            waiting_for_chunk_index: 8,
+            receive_mask_after_last: 0,
+        })
+        .expect("ack should be valid");
+    assert_eq!(logic.planned_datagram_count(), 1);
+}
+
+#[test]
+fn zero_credit_halts_sender() {
+    let mut logic = Logic::new(vec![0; 40], 4);
+    let now = Millis::new(0);
+    assert_eq!(logic.send(now, 2).len(), 2);
+
+    logic
+        .receive_with_credit(&AckChunkCreditData {
+            ack: AckChunkData {
+                waiting_for_chunk_index: 0,
+                receive_mask_after_last: 0,
+            },
+            credit: 0,
+        })
+        .expect("ack should be valid");
+
+    let later = now + DEFAULT_RESEND_INTERVAL;
+    assert!(logic.send(later, 10).is_empty());
+    assert!(logic.tick(later).is_empty());
+
+    logic
+        .receive_with_credit(&AckChunkCreditData {
+            ack: AckChunkData {
+                waiting_for_chunk_index: 2,
+                receive_mask_after_last: 0,
+            },
+            credit: 3,
+        })
+        .expect("ack should be valid");
+    assert_eq!(logic.credit(), Some(3));
+
+    let chunk_indices: Vec<u64> = logic
+        .send(later, 10)
+        .iter()
+        .map(|c| c.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [2, 3, 4]);
+}
+
+#[test]
+fn try_new_enforces_min_chunk_size() {
+    assert!(matches!(
+        Logic::try_new(vec![0; 1000], 1, 64),
+        Err(BlobError::ChunkSizeTooSmall(1, 64))
+    ));
+
+    let logic = Logic::try_new(vec![0; 1000], 1, 1).expect("one octet is the minimum");
+    assert_eq!(logic.chunk_count(), 1000);
+}
+
+#[test]
+fn requested_chunks_are_sent_first() {
+    let now = Millis::new(0);
+    let mut logic = Logic::new(vec![0; 24], 4);
+    assert_eq!(logic.send(now, 3).len(), 3);
+
+    logic.receive_request(&RequestChunksData {
+        chunk_indices: vec![2, 0],
+    });
+
+    // The requested chunks are not due for a resend yet, but are sent before the new chunks.
+    let chunk_indices: Vec<u64> = logic
+        .send(now, 3)
+        .iter()
+        .map(|chunk| chunk.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [2, 0, 3]);
+
+    let chunk_indices: Vec<u64> = logic
+        .send(now, 3)
+        .iter()
+        .map(|chunk| chunk.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [4, 5]);
+}
+
+#[test]
+fn acked_requested_chunks_are_skipped() {
+    let now = Millis::new(0);
+    let mut logic = Logic::new(vec![0; 12], 4);
+    let _ = logic.send(now, 3);
+
+    logic.receive_request(&RequestChunksData {
+        chunk_indices: vec![0, 1],
+    });
+    logic
+        .receive(&AckChunkData {
+            waiting_for_chunk_index: 1,
+            receive_mask_after_last: 0,
+        })
+        .expect("ack should be valid");
+
+    let chunk_indices: Vec<u64> = logic
+        .send(now, 3)
+        .iter()
+        .map(|chunk| chunk.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [1]);
+}
+
+#[test]
+fn out_of_range_requested_chunks_are_ignored() {
+    let now = Millis::new(0);
+    let mut logic = Logic::new(vec![0; 12], 4);
+    let _ = logic.send(now, 3);
+
+    logic.receive_request(&RequestChunksData {
+        chunk_indices: vec![3, 1, u64::MAX, 2],
+    });
+
+    let chunk_indices: Vec<u64> = logic
+        .send(now, 3)
+        .iter()
+        .map(|chunk| chunk.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [1, 2]);
+}
+
+#[test]
+fn resend_chunks_sends_exactly_the_requested_chunks() {
+    let now = Millis::new(0);
+    let mut logic = Logic::new(vec![0; 32], 4);
+    assert_eq!(logic.send(now, 8).len(), 8);
+    logic
+        .receive(&AckChunkData {
+            waiting_for_chunk_index: 2,
+            receive_mask_after_last: 0,
+        })
+        .expect("ack should be valid");
+
+    logic.resend_chunks(&[2, 1, 5, 99, 2]);
+
+    let chunk_indices: Vec<u64> = logic
+        .send(now, 8)
+        .iter()
+        .map(|chunk| chunk.chunk_index)
+        .collect();
+    assert_eq!(chunk_indices, [2, 5]);
+    assert!(logic.send(now, 8).is_empty());
+}
+
+fn chunk_indices(chunks: &[blob_stream::protocol::SetChunkData]) -> Vec<u64> {
+    chunks.iter().map(|chunk| chunk.chunk_index).collect()
+}
+
+#[test]
+fn byte_budget_limits_chunks_per_tick() {
+    let now = Millis::new(0);
+    // Chunks of 4, 4 and 2 octets.
+    let mut logic = Logic::new(vec![0; 10], 4).with_max_bytes_per_tick(9);
+
+    assert_eq!(chunk_indices(&logic.send(now, 10)), [0, 1]);
+    assert_eq!(logic.bytes_sent_this_tick(), 8);
+    assert!(logic.send(now, 10).is_empty());
+
+    logic.reset_tick();
+    assert_eq!(logic.bytes_sent_this_tick(), 0);
+    assert_eq!(chunk_indices(&logic.send(now, 10)), [2]);
+
+    // Resending is limited by the budget as well.
+    logic.reset_tick();
+    let later = now + DEFAULT_RESEND_INTERVAL;
+    assert_eq!(chunk_indices(&logic.tick(later)), [0, 1]);
+    logic.reset_tick();
+    assert_eq!(chunk_indices(&logic.tick(later)), [2]);
+    assert_eq!(logic.bytes_sent_this_tick(), 2);
+}
+
+#[test]
+fn byte_budget_below_chunk_size_carries_deficit_over() {
+    let now = Millis::new(0);
+    let mut logic = Logic::new(vec![0; 10], 4).with_max_bytes_per_tick(3);
+
+    assert_eq!(chunk_indices(&logic.send(now, 10)), [0]);
+    assert_eq!(logic.bytes_sent_this_tick(), 4);
+    assert!(logic.send(now, 10).is_empty());
+
+    // The octet beyond the budget is carried over, so nothing is sent in the next tick.
+    logic.reset_tick();
+    assert_eq!(logic.bytes_sent_this_tick(), 1);
+    assert!(logic.send(now, 10).is_empty());
+
+    logic.reset_tick();
+    assert_eq!(chunk_indices(&logic.send(now, 10)), [1]);
+    logic.reset_tick();
+    logic.reset_tick();
+    assert_eq!(chunk_indices(&logic.send(now, 10)), [2]);
+}
+
+#[test]
+fn byte_budget_counts_encoded_payload_octets() {
+    let now = Millis::new(0);
+    let mut logic = Logic::new(vec![0; 10], 4)
+        .with_chunk_codec(ChunkCodec::RunLength)
+        .with_max_bytes_per_tick(6);
+
+    // The run length encoded chunks hold 2 octets each.
+    assert_eq!(chunk_indices(&logic.send(now, 10)), [0, 1, 2]);
+    assert_eq!(logic.bytes_sent_this_tick(), 6);
+}
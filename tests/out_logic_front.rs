@@ -0,0 +1,311 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "std")]
+
+use blob_stream::prelude::*;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+const BACKOFF: StartBackoff = StartBackoff {
+    initial_interval: Duration::from_millis(100),
+    max_interval: Duration::from_secs(10),
+    max_attempts: 4,
+};
+
+/// Adds a fixed quarter of the interval, which stands in for a random fraction.
+#[derive(Debug)]
+struct QuarterJitter;
+
+impl JitterSource for QuarterJitter {
+    fn jitter(&mut self, interval: Duration) -> Duration {
+        interval / 4
+    }
+}
+
+fn is_start_transfer(commands: &[SenderToReceiverFrontCommands]) -> bool {
+    matches!(commands, [SenderToReceiverFrontCommands::StartTransfer(_)])
+}
+
+/// Steps the manual clock one millisecond at a time and returns the times at which
+/// `StartTransfer` was sent, until the handshake fails.
+fn start_transfer_times(logic: &mut OutLogicFront, start: Millis) -> (Vec<Duration>, ErrorKind) {
+    let mut times = Vec::new();
+    for elapsed_millis in 0..10_000 {
+        let elapsed = Duration::from_millis(elapsed_millis);
+        match logic.send(start + elapsed, 10) {
+            Ok(commands) if is_start_transfer(&commands) => times.push(elapsed),
+            Ok(commands) => assert!(commands.is_empty()),
+            Err(err) => return (times, err.kind()),
+        }
+    }
+    panic!("handshake should have failed");
+}
+
+#[test]
+fn start_transfer_intervals_grow_exponentially() {
+    let mut logic = OutLogicFront::new(TransferId(3), vec![0; 10], 4).with_start_backoff(BACKOFF);
+
+    let (times, kind) = start_transfer_times(&mut logic, Millis::new(0));
+
+    let millis: Vec<u128> = times.iter().map(Duration::as_millis).collect();
+    assert_eq!(millis, [0, 100, 300, 700]);
+    assert_eq!(kind, ErrorKind::TimedOut);
+    assert_eq!(logic.attempt_count(), 4);
+    assert!(logic.send(Millis::new(0), 10).is_err());
+}
+
+#[test]
+fn jitter_is_added_to_start_transfer_intervals() {
+    let mut logic = OutLogicFront::new(TransferId(3), vec![0; 10], 4)
+        .with_start_backoff(BACKOFF)
+        .with_jitter_source(Box::new(QuarterJitter));
+
+    let (times, _) = start_transfer_times(&mut logic, Millis::new(0));
+
+    let millis: Vec<u128> = times.iter().map(Duration::as_millis).collect();
+    assert_eq!(millis, [0, 125, 375, 875]);
+}
+
+#[test]
+fn start_transfer_interval_is_capped() {
+    let backoff = StartBackoff {
+        max_interval: Duration::from_millis(300),
+        ..BACKOFF
+    };
+
+    assert_eq!(backoff.interval(1), Duration::from_millis(100));
+    assert_eq!(backoff.interval(2), Duration::from_millis(200));
+    assert_eq!(backoff.interval(3), Duration::from_millis(300));
+    assert_eq!(backoff.interval(100), Duration::from_millis(300));
+}
+
+#[test]
+fn ack_start_begins_sending_chunks() {
+    let now = Millis::new(0);
+    let mut logic = OutLogicFront::new(TransferId(3), vec![0; 10], 4).with_start_backoff(BACKOFF);
+
+    assert!(is_start_transfer(&logic.send(now, 10).unwrap()));
+    logic
+        .receive(&ReceiverToSenderFrontCommands::AckStart(3))
+        .expect("ack start should be accepted");
+    assert!(logic.is_started());
+    assert_eq!(logic.attempt_count(), 1);
+
+    let commands = logic.send(now, 10).expect("chunks should be sent");
+    assert_eq!(commands.len(), 3);
+    assert!(commands
+        .iter()
+        .all(|command| matches!(command, SenderToReceiverFrontCommands::SetChunk(_))));
+}
+
+#[test]
+fn ack_start_for_other_transfer_is_ignored() {
+    let now = Millis::new(0);
+    let mut logic = OutLogicFront::new(TransferId(3), vec![0; 10], 4).with_start_backoff(BACKOFF);
+
+    assert!(is_start_transfer(&logic.send(now, 10).unwrap()));
+    logic
+        .receive(&ReceiverToSenderFrontCommands::AckStart(4))
+        .expect("ack start should be ignored");
+
+    assert!(!logic.is_started());
+}
+
+fn started_sender(transfer_id: u16, blob_octet_count: usize) -> OutLogicFront {
+    let mut sender = OutLogicFront::new(TransferId(transfer_id), vec![0; blob_octet_count], 4);
+    sender.send(Millis::new(0), 10).unwrap();
+    sender
+        .receive(&ReceiverToSenderFrontCommands::AckStart(transfer_id))
+        .unwrap();
+    sender
+}
+
+fn ack(sender: &mut OutLogicFront, transfer_id: u16, waiting_for_chunk_index: u64) {
+    sender
+        .receive(&ReceiverToSenderFrontCommands::AckChunk(
+            blob_stream::protocol_front::AckChunkFrontData {
+                transfer_id: TransferId(transfer_id),
+                data: blob_stream::protocol::AckChunkData {
+                    waiting_for_chunk_index,
+                    receive_mask_after_last: 0,
+                },
+            },
+        ))
+        .unwrap();
+}
+
+#[test]
+fn aggregate_stats_sum_over_transfers() {
+    let now = Millis::new(0);
+
+    let not_started = OutLogicFront::new(TransferId(1), vec![0; 10], 4);
+
+    // 10 octets in chunks of 4, 4 and 2. The first chunk is acknowledged, the others in flight.
+    let mut in_progress = started_sender(2, 10);
+    in_progress.send(now, 10).unwrap();
+    ack(&mut in_progress, 2, 1);
+
+    let mut complete = started_sender(3, 6);
+    complete.send(now, 10).unwrap();
+    ack(&mut complete, 3, 2);
+    assert!(complete.is_complete());
+
+    let stats = aggregate_stats([&not_started, &in_progress, &complete]);
+
+    assert_eq!(
+        stats,
+        AggregateStats {
+            acked_octet_count: 4 + 6,
+            in_flight_octet_count: 6,
+            active_transfer_count: 2,
+        }
+    );
+    assert_eq!(aggregate_stats([]), AggregateStats::default());
+}
+
+#[test]
+fn completed_stops_sending() {
+    let now = Millis::new(0);
+    let mut sender = started_sender(3, 10);
+    sender.send(now, 10).unwrap();
+
+    // The receiver completed the transfer, but the acknowledgements were lost.
+    sender
+        .receive(&ReceiverToSenderFrontCommands::Completed(3))
+        .expect("completed should be accepted");
+
+    assert!(sender.is_complete());
+    assert!(sender.is_started());
+    assert_eq!(sender.acked_octet_count(), 10);
+    assert_eq!(sender.in_flight_octet_count(), 0);
+    assert!(sender
+        .send(now + Duration::from_secs(10), 10)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn completed_for_other_transfer_is_ignored() {
+    let now = Millis::new(0);
+    let mut sender = started_sender(3, 10);
+
+    sender
+        .receive(&ReceiverToSenderFrontCommands::Completed(4))
+        .expect("completed should be ignored");
+
+    assert!(!sender.is_complete());
+    assert_eq!(sender.send(now, 10).unwrap().len(), 3);
+}
+
+#[test]
+fn transfer_id_allocator_wraps_around_to_released_ids() {
+    let mut allocator = TransferIdAllocator::new();
+    for expected in 0..=u16::MAX {
+        assert_eq!(allocator.allocate().unwrap(), TransferId(expected));
+    }
+
+    assert!(allocator.release(TransferId(7)));
+    assert!(allocator.release(TransferId(3)));
+    assert!(!allocator.release(TransferId(3)));
+
+    assert_eq!(allocator.allocate().unwrap(), TransferId(3));
+    assert_eq!(allocator.allocate().unwrap(), TransferId(7));
+    assert_eq!(allocator.in_use_count(), usize::from(u16::MAX) + 1);
+}
+
+#[test]
+fn transfer_id_allocator_fails_when_exhausted() {
+    let mut allocator = TransferIdAllocator::new();
+    for _ in 0..=u16::MAX {
+        allocator.allocate().unwrap();
+    }
+
+    assert!(allocator.allocate().is_err());
+
+    allocator.release(TransferId(42));
+    assert_eq!(allocator.allocate().unwrap(), TransferId(42));
+    assert!(allocator.allocate().is_err());
+}
+
+#[test]
+fn multi_sender_completes_transfers_to_front_logic() {
+    let mut now = Millis::new(0);
+    let mut sender = MultiOutLogicFront::new();
+    let mut receiver = FrontLogic::new();
+    let first_blob: Vec<u8> = (0..10).collect();
+    let second_blob: Vec<u8> = (100..107).collect();
+
+    let mut commands = sender
+        .start_transfer(now, TransferId(1), first_blob.clone(), 4)
+        .unwrap();
+    commands.extend(
+        sender
+            .start_transfer(now, TransferId(2), second_blob.clone(), 3)
+            .unwrap(),
+    );
+    assert!(sender
+        .start_transfer(now, TransferId(1), vec![0; 4], 4)
+        .is_err());
+
+    for _ in 0..10 {
+        for command in &commands {
+            let answer = receiver.update(now, command).unwrap();
+            sender.receive(&answer).unwrap();
+        }
+        now += Duration::from_millis(100);
+        commands = sender.send(now, 2);
+        assert!(
+            commands
+                .iter()
+                .filter(|command| matches!(command, SenderToReceiverFrontCommands::SetChunk(_)))
+                .count()
+                <= 2
+        );
+    }
+
+    assert!(sender.transfers().all(OutLogicFront::is_complete));
+    assert!(sender.send(now, 2).is_empty());
+    assert_eq!(receiver.transfer_blob(1), Some(first_blob.as_slice()));
+    assert_eq!(receiver.transfer_blob(2), Some(second_blob.as_slice()));
+    assert!(sender.take_failed_transfer_ids().is_empty());
+}
+
+#[test]
+fn receive_window_credit_throttles_sender() {
+    let now = Millis::new(0);
+    let blob: Vec<u8> = (0..40).collect();
+    let mut sender = OutLogicFront::new(TransferId(1), blob.clone(), 4);
+    let mut receiver = FrontLogic::new().with_receive_window(3);
+    let start = sender.send(now, 1).unwrap();
+    sender
+        .receive(&receiver.update(now, &start[0]).unwrap())
+        .unwrap();
+
+    // The credit arrives with the acknowledgement of the first chunk.
+    let mut commands = sender.send(now, 1).unwrap();
+    let mut sent_chunk_counts = Vec::new();
+    while !commands.is_empty() {
+        sent_chunk_counts.push(commands.len());
+        for command in &commands {
+            let answer = receiver
+                .update(now, command)
+                .expect("every chunk should fit in the receive window");
+            assert!(
+                matches!(
+                    answer,
+                    ReceiverToSenderFrontCommands::AckChunkCredit(_)
+                        | ReceiverToSenderFrontCommands::Completed(1)
+                ),
+                "{answer:?}"
+            );
+            sender.receive(&answer).unwrap();
+        }
+        commands = sender.send(now, 10).unwrap();
+    }
+
+    assert_eq!(sent_chunk_counts, [1, 3, 3, 3]);
+    assert!(sender.is_complete());
+    assert_eq!(receiver.transfer_blob(1), Some(blob.as_slice()));
+}
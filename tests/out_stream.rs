@@ -0,0 +1,48 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use blob_stream::err::BlobError;
+use blob_stream::out_stream::BlobStreamOut;
+
+fn nine_octets() -> BlobStreamOut {
+    BlobStreamOut::new((1..=9).collect(), 4)
+}
+
+#[test]
+fn last_chunk_is_remainder() {
+    let stream = nine_octets();
+
+    assert_eq!(stream.chunk_count(), 3);
+    assert_eq!(stream.chunk(0).unwrap(), [1, 2, 3, 4]);
+    assert_eq!(stream.chunk(1).unwrap(), [5, 6, 7, 8]);
+    assert_eq!(stream.chunk(2).unwrap(), [9]);
+    assert!(matches!(
+        stream.chunk(3),
+        Err(BlobError::InvalidChunkIndex(3, 3))
+    ));
+}
+
+#[test]
+fn iter_chunks_yields_indices_and_payloads() {
+    let stream = nine_octets();
+
+    let sizes: Vec<(usize, usize)> = stream
+        .iter_chunks()
+        .map(|(chunk_index, payload)| (chunk_index, payload.len()))
+        .collect();
+    assert_eq!(sizes, [(0, 4), (1, 4), (2, 1)]);
+
+    assert!(stream
+        .iter_chunks()
+        .all(|(chunk_index, payload)| stream.chunk(chunk_index).unwrap() == payload));
+}
+
+#[test]
+fn empty_blob_has_no_chunks() {
+    let stream = BlobStreamOut::new(Vec::new(), 4);
+
+    assert_eq!(stream.chunk_count(), 0);
+    assert_eq!(stream.iter_chunks().count(), 0);
+    assert!(stream.chunk(0).is_err());
+}
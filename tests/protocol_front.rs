@@ -0,0 +1,662 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "std")]
+
+use blob_stream::prelude::*;
+use blob_stream::protocol::{
+    AbortTransferData, AckBytesData, AckChunkCreditData, AckChunkData, AckChunkMaskData,
+    CompleteTransferData, HashCheckpointData, KeepaliveData, PauseTransferData, RequestChunksData,
+    ResumeTransferData, MAX_HASH_OCTET_SIZE, MAX_PAYLOAD_OCTET_SIZE, MAX_REQUESTED_CHUNK_COUNT,
+    MAX_SCHEDULE_LENGTH,
+};
+use blob_stream::protocol_front::{
+    AckBytesFrontData, AckChunkCreditFrontData, AckChunkFrontData, AckChunkMaskFrontData,
+    RequestChunksFrontData, MAX_SET_CHUNKS_COUNT, MAX_SET_CHUNKS_OCTET_SIZE, PROTOCOL_VERSION,
+};
+use flood_rs::{InOctetStream, OutOctetStream, WriteOctetStream};
+
+fn receiver_round_trip(command: &ReceiverToSenderFrontCommands) -> ReceiverToSenderFrontCommands {
+    let mut out_stream = OutOctetStream::new();
+    command
+        .to_stream(&mut out_stream)
+        .expect("should be able to write command");
+
+    let mut in_stream = InOctetStream::new(&out_stream.octets());
+    ReceiverToSenderFrontCommands::from_stream(&mut in_stream)
+        .expect("should be able to read command")
+}
+
+fn sender_round_trip(command: &SenderToReceiverFrontCommands) -> SenderToReceiverFrontCommands {
+    let mut out_stream = OutOctetStream::new();
+    command
+        .to_stream(&mut out_stream)
+        .expect("should be able to write command");
+
+    let mut in_stream = InOctetStream::new(&out_stream.octets());
+    SenderToReceiverFrontCommands::from_stream(&mut in_stream)
+        .expect("should be able to read command")
+}
+
+#[test]
+fn completed_round_trip() {
+    let completed = ReceiverToSenderFrontCommands::Completed(0x3211);
+
+    assert_eq!(completed.to_octet(), 0x04);
+    assert_eq!(receiver_round_trip(&completed), completed);
+}
+
+#[test]
+fn start_transfer_round_trip() {
+    let start = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 0x0102,
+        total_octet_size: 0x0001_0002,
+        chunk_size: 1024,
+        schedule: vec![],
+        blob_hash: None,
+    });
+
+    assert_eq!(start.to_octet(), 0x02);
+    assert_eq!(sender_round_trip(&start), start);
+}
+
+#[test]
+fn set_chunk_round_trip() {
+    let set_chunk = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(0x0102),
+        data: SetChunkData {
+            chunk_index: 3,
+            codec: ChunkCodec::Stored,
+            payload: vec![1, 2, 3, 4],
+        },
+    });
+
+    assert_eq!(set_chunk.to_octet(), 0x01);
+    assert_eq!(sender_round_trip(&set_chunk), set_chunk);
+}
+
+#[test]
+fn chunk_indices_above_u32_round_trip() {
+    let chunk_index = u64::from(u32::MAX) + 2;
+    let set_chunk = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(1),
+        data: stored_chunk(chunk_index, vec![1, 2]),
+    });
+    assert_eq!(sender_round_trip(&set_chunk), set_chunk);
+
+    let ack_chunk = ReceiverToSenderFrontCommands::AckChunk(AckChunkFrontData {
+        transfer_id: TransferId(1),
+        data: AckChunkData {
+            waiting_for_chunk_index: chunk_index,
+            receive_mask_after_last: 1,
+        },
+    });
+    assert_eq!(receiver_round_trip(&ack_chunk), ack_chunk);
+
+    let ack_mask = ReceiverToSenderFrontCommands::AckChunkMask(AckChunkMaskFrontData {
+        transfer_id: TransferId(1),
+        data: AckChunkMaskData {
+            waiting_for_chunk_index: chunk_index,
+            receive_mask_after_last: vec![0b10],
+        },
+    });
+    assert_eq!(receiver_round_trip(&ack_mask), ack_mask);
+    let ReceiverToSenderFrontCommands::AckChunkMask(ack_mask) = ack_mask else {
+        unreachable!()
+    };
+    assert!(ack_mask.data.is_received(chunk_index + 2));
+    assert!(!ack_mask.data.is_received(chunk_index + 1));
+}
+
+#[test]
+fn ack_start_round_trip() {
+    let ack_start = ReceiverToSenderFrontCommands::AckStart(0x0102);
+
+    assert_eq!(ack_start.to_octet(), 0x03);
+    assert_eq!(receiver_round_trip(&ack_start), ack_start);
+}
+
+#[test]
+fn ack_chunk_round_trip() {
+    let ack_chunk = ReceiverToSenderFrontCommands::AckChunk(AckChunkFrontData {
+        transfer_id: TransferId(0x0102),
+        data: AckChunkData {
+            waiting_for_chunk_index: 7,
+            receive_mask_after_last: 0x8000_0000_0000_0001,
+        },
+    });
+
+    assert_eq!(ack_chunk.to_octet(), 0x02);
+    assert_eq!(receiver_round_trip(&ack_chunk), ack_chunk);
+}
+
+#[test]
+fn ack_bytes_round_trip() {
+    let ack_bytes = ReceiverToSenderFrontCommands::AckBytes(AckBytesFrontData {
+        transfer_id: TransferId(0x0102),
+        data: AckBytesData {
+            received_up_to_byte: 0x1_0000_0001,
+        },
+    });
+
+    assert_eq!(receiver_round_trip(&ack_bytes), ack_bytes);
+}
+
+#[test]
+fn start_transfer_schedule_round_trip() {
+    let start = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 0x0042,
+        total_octet_size: 4000,
+        chunk_size: 1000,
+        schedule: vec![3, 1, 0, 2, u64::from(u32::MAX) + 1],
+        blob_hash: None,
+    });
+
+    assert_eq!(sender_round_trip(&start), start);
+}
+
+#[test]
+fn start_transfer_blob_hash_round_trip() {
+    let mut blob_hash = [0u8; 32];
+    blob_hash[0] = 0xfe;
+    blob_hash[31] = 0x17;
+    let start = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 0x0042,
+        total_octet_size: 4000,
+        chunk_size: 1000,
+        schedule: vec![],
+        blob_hash: Some(blob_hash),
+    });
+
+    assert_eq!(sender_round_trip(&start), start);
+}
+
+#[test]
+fn start_transfer_schedule_too_long() {
+    let start = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 0x0042,
+        total_octet_size: 4000,
+        chunk_size: 1,
+        schedule: vec![0; MAX_SCHEDULE_LENGTH + 1],
+        blob_hash: None,
+    });
+
+    let mut out_stream = OutOctetStream::new();
+    let err = start
+        .to_stream(&mut out_stream)
+        .expect_err("should reject a schedule over the cap");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn abort_transfer_round_trip() {
+    let abort = SenderToReceiverFrontCommands::AbortTransfer(AbortTransferData {
+        transfer_id: 0x1234,
+    });
+
+    assert_eq!(abort.to_octet(), 0x03);
+    assert_eq!(sender_round_trip(&abort), abort);
+}
+
+#[test]
+fn keepalive_round_trip() {
+    let keepalive = SenderToReceiverFrontCommands::Keepalive(KeepaliveData {
+        transfer_id: 0x1234,
+    });
+
+    assert_eq!(keepalive.to_octet(), 0x07);
+    assert_eq!(keepalive.transfer_id(), 0x1234);
+    assert_eq!(sender_round_trip(&keepalive), keepalive);
+}
+
+#[test]
+fn ack_abort_round_trip() {
+    let ack_abort = ReceiverToSenderFrontCommands::AckAbort(0x1234);
+
+    assert_eq!(ack_abort.to_octet(), 0x06);
+    assert_eq!(receiver_round_trip(&ack_abort), ack_abort);
+}
+
+#[test]
+fn pause_and_resume_round_trip() {
+    let pause = ReceiverToSenderFrontCommands::Pause(PauseTransferData {
+        transfer_id: 0x0a0b,
+    });
+    assert_eq!(pause.to_octet(), 0x07);
+    assert_eq!(receiver_round_trip(&pause), pause);
+
+    let resume = ReceiverToSenderFrontCommands::Resume(ResumeTransferData {
+        transfer_id: 0x0a0b,
+    });
+    assert_eq!(resume.to_octet(), 0x08);
+    assert_eq!(receiver_round_trip(&resume), resume);
+}
+
+#[test]
+fn need_start_round_trip() {
+    let need_start = ReceiverToSenderFrontCommands::NeedStart(0x0c0d);
+
+    assert_eq!(need_start.to_octet(), 0x09);
+    assert_eq!(receiver_round_trip(&need_start), need_start);
+}
+
+#[test]
+fn ack_chunk_credit_round_trip() {
+    let ack_credit = ReceiverToSenderFrontCommands::AckChunkCredit(AckChunkCreditFrontData {
+        transfer_id: TransferId(0x0102),
+        data: AckChunkCreditData {
+            ack: AckChunkData {
+                waiting_for_chunk_index: 7,
+                receive_mask_after_last: 0b1011,
+            },
+            credit: 12,
+        },
+    });
+
+    assert_eq!(ack_credit.to_octet(), 0x0A);
+    assert_eq!(receiver_round_trip(&ack_credit), ack_credit);
+}
+
+#[test]
+fn set_chunk_larger_than_u16_round_trip() {
+    let payload: Vec<u8> = (0..70_000u32).map(|i| (i % 251) as u8).collect();
+    let set_chunk = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(0x0007),
+        data: SetChunkData {
+            chunk_index: 3,
+            codec: ChunkCodec::Stored,
+            payload,
+        },
+    });
+
+    assert_eq!(sender_round_trip(&set_chunk), set_chunk);
+}
+
+#[test]
+fn set_chunk_length_prefix_is_not_truncated() {
+    let chunk = SetChunkData {
+        chunk_index: 0,
+        codec: ChunkCodec::Stored,
+        payload: vec![0xab; 70_000],
+    };
+
+    let mut out_stream = OutOctetStream::new();
+    chunk
+        .to_stream(&mut out_stream)
+        .expect("should be able to write chunk");
+    let octets = out_stream.octets();
+
+    assert_eq!(octets.len(), chunk.octet_size());
+    assert_eq!(octets[9..13], 70_000u32.to_be_bytes());
+}
+
+#[test]
+fn ack_chunk_mask_round_trip() {
+    let mut receive_mask_after_last = vec![0xff; 25];
+    receive_mask_after_last[24] = 0x3f;
+    let ack_mask = ReceiverToSenderFrontCommands::AckChunkMask(AckChunkMaskFrontData {
+        transfer_id: TransferId(0x0102),
+        data: AckChunkMaskData {
+            waiting_for_chunk_index: 5,
+            receive_mask_after_last,
+        },
+    });
+
+    assert_eq!(ack_mask.to_octet(), 0x0B);
+    assert_eq!(receiver_round_trip(&ack_mask), ack_mask);
+}
+
+#[test]
+fn request_chunks_round_trip() {
+    let request = ReceiverToSenderFrontCommands::RequestChunks(RequestChunksFrontData {
+        transfer_id: TransferId(0x0102),
+        data: RequestChunksData {
+            chunk_indices: vec![1, 5, 0x0102_0304],
+        },
+    });
+
+    assert_eq!(request.to_octet(), 0x0C);
+    assert_eq!(request.transfer_id(), 0x0102);
+    assert_eq!(receiver_round_trip(&request), request);
+}
+
+#[test]
+fn request_chunks_length_is_capped() {
+    let too_long = RequestChunksData {
+        chunk_indices: vec![0; MAX_REQUESTED_CHUNK_COUNT + 1],
+    };
+    let mut out_stream = OutOctetStream::new();
+    assert!(too_long.to_stream(&mut out_stream).is_err());
+
+    let mut out_stream = OutOctetStream::new();
+    out_stream
+        .write_u16(u16::try_from(MAX_REQUESTED_CHUNK_COUNT + 1).unwrap())
+        .unwrap();
+    let mut in_stream = InOctetStream::new(out_stream.octets_ref());
+    assert!(RequestChunksData::from_stream(&mut in_stream).is_err());
+}
+
+#[test]
+fn complete_transfer_round_trip_for_digest_sizes() {
+    for digest_octet_size in [4, 16, 32, 64] {
+        let complete = SenderToReceiverFrontCommands::CompleteTransfer(CompleteTransferData {
+            transfer_id: 0x0042,
+            hash: (0..digest_octet_size).collect(),
+        });
+
+        let mut out_stream = OutOctetStream::new();
+        complete
+            .to_stream(&mut out_stream)
+            .expect("should be able to write command");
+        // version (u8) + command (u8) + transfer_id (u16) + hash length (u8) + hash
+        assert_eq!(
+            out_stream.octets().len(),
+            1 + 1 + 2 + 1 + digest_octet_size as usize
+        );
+
+        assert_eq!(sender_round_trip(&complete), complete);
+    }
+}
+
+#[test]
+fn complete_transfer_hash_too_long() {
+    let complete = SenderToReceiverFrontCommands::CompleteTransfer(CompleteTransferData {
+        transfer_id: 0x0042,
+        hash: vec![0; MAX_HASH_OCTET_SIZE + 1],
+    });
+
+    let mut out_stream = OutOctetStream::new();
+    let err = complete
+        .to_stream(&mut out_stream)
+        .expect_err("hash should be too long");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+const fn stored_chunk(chunk_index: u64, payload: Vec<u8>) -> SetChunkData {
+    SetChunkData {
+        chunk_index,
+        codec: ChunkCodec::Stored,
+        payload,
+    }
+}
+
+#[test]
+fn set_chunks_round_trip() {
+    let set_chunks = SenderToReceiverFrontCommands::SetChunks(SetChunksFrontData {
+        transfer_id: TransferId(0x0007),
+        chunks: vec![
+            stored_chunk(0, vec![1, 2, 3]),
+            stored_chunk(1, vec![4, 5, 6]),
+            stored_chunk(2, vec![7]),
+        ],
+    });
+
+    assert_eq!(set_chunks.to_octet(), 0x05);
+    assert_eq!(set_chunks.transfer_id(), 0x0007);
+    assert_eq!(sender_round_trip(&set_chunks), set_chunks);
+}
+
+#[test]
+fn set_chunks_with_too_many_chunks_is_rejected() {
+    let chunk_count = MAX_SET_CHUNKS_COUNT + 1;
+    let set_chunks = SetChunksFrontData {
+        transfer_id: TransferId(1),
+        chunks: vec![stored_chunk(0, vec![]); chunk_count],
+    };
+    assert!(set_chunks.to_stream(&mut OutOctetStream::new()).is_err());
+
+    let mut out_stream = OutOctetStream::new();
+    out_stream.write_u8(PROTOCOL_VERSION).unwrap();
+    out_stream.write_u8(0x05).unwrap();
+    out_stream.write_u16(1).unwrap();
+    out_stream
+        .write_u16(u16::try_from(chunk_count).unwrap())
+        .unwrap();
+    let mut in_stream = InOctetStream::new(&out_stream.octets());
+
+    let err = SenderToReceiverFrontCommands::from_stream(&mut in_stream)
+        .expect_err("chunk count is above the maximum");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn set_chunks_with_too_many_octets_is_rejected() {
+    let half = MAX_SET_CHUNKS_OCTET_SIZE / 2;
+    let set_chunks = SetChunksFrontData {
+        transfer_id: TransferId(1),
+        chunks: vec![
+            stored_chunk(0, vec![0; half]),
+            stored_chunk(1, vec![0; half + 1]),
+        ],
+    };
+    assert!(set_chunks.to_stream(&mut OutOctetStream::new()).is_err());
+
+    // The second chunk only declares its length, which must be rejected before its payload is read.
+    let mut out_stream = OutOctetStream::new();
+    out_stream.write_u8(PROTOCOL_VERSION).unwrap();
+    out_stream.write_u8(0x05).unwrap();
+    out_stream.write_u16(1).unwrap();
+    out_stream.write_u16(2).unwrap();
+    stored_chunk(0, vec![0; half])
+        .to_stream(&mut out_stream)
+        .unwrap();
+    out_stream.write_u64(1).unwrap();
+    out_stream.write_u8(0).unwrap();
+    out_stream
+        .write_u32(u32::try_from(half + 1).unwrap())
+        .unwrap();
+    let mut in_stream = InOctetStream::new(&out_stream.octets());
+
+    let err = SenderToReceiverFrontCommands::from_stream(&mut in_stream)
+        .expect_err("payload octets are above the maximum");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+fn serialized_octet_size(command: &SenderToReceiverFrontCommands) -> usize {
+    let mut out_stream = OutOctetStream::new();
+    command.to_stream(&mut out_stream).unwrap();
+    out_stream.octets().len()
+}
+
+#[test]
+fn write_sender_command_to_exact_sized_slice() {
+    let command = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(9),
+        data: stored_chunk(2, vec![1, 2, 3, 4, 5]),
+    });
+    let octet_size = serialized_octet_size(&command);
+    let mut octets = vec![0xff; octet_size];
+
+    let written = command
+        .write_to_slice(&mut octets)
+        .expect("buffer is large enough");
+
+    assert_eq!(written, octet_size);
+    let mut in_stream = InOctetStream::new(&octets);
+    assert_eq!(
+        SenderToReceiverFrontCommands::from_stream(&mut in_stream).unwrap(),
+        command
+    );
+}
+
+#[test]
+fn write_sender_command_to_undersized_slice_fails() {
+    let command = SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+        transfer_id: TransferId(9),
+        data: stored_chunk(2, vec![1, 2, 3, 4, 5]),
+    });
+    let mut octets = vec![0; serialized_octet_size(&command) - 1];
+
+    let err = command
+        .write_to_slice(&mut octets)
+        .expect_err("buffer is one octet too small");
+    assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+}
+
+#[test]
+fn write_receiver_command_to_slice() {
+    let command = ReceiverToSenderFrontCommands::AckChunk(AckChunkFrontData {
+        transfer_id: TransferId(9),
+        data: AckChunkData {
+            waiting_for_chunk_index: 3,
+            receive_mask_after_last: 0b101,
+        },
+    });
+    let mut octets = [0; 64];
+
+    let written = command.write_to_slice(&mut octets).unwrap();
+    assert_eq!(written, 1 + 1 + 2 + 8 + 8);
+    let mut in_stream = InOctetStream::new(&octets[..written]);
+    assert_eq!(
+        ReceiverToSenderFrontCommands::from_stream(&mut in_stream).unwrap(),
+        command
+    );
+
+    let err = command
+        .write_to_slice(&mut octets[..written - 1])
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+}
+
+#[test]
+fn commands_start_with_protocol_version() {
+    let command = ReceiverToSenderFrontCommands::AckStart(4);
+    let mut out_stream = OutOctetStream::new();
+    command.to_stream(&mut out_stream).unwrap();
+
+    assert_eq!(out_stream.octets(), [PROTOCOL_VERSION, 0x03, 0x00, 0x04]);
+    assert_eq!(receiver_round_trip(&command), command);
+}
+
+fn with_other_protocol_version(write: impl FnOnce(&mut OutOctetStream)) -> InOctetStream {
+    let mut out_stream = OutOctetStream::new();
+    write(&mut out_stream);
+    let mut octets = out_stream.octets();
+    octets[0] = PROTOCOL_VERSION.wrapping_add(1);
+    InOctetStream::new(&octets)
+}
+
+#[test]
+fn mismatched_protocol_version_is_rejected() {
+    let abort = SenderToReceiverFrontCommands::AbortTransfer(AbortTransferData { transfer_id: 4 });
+    let mut in_stream = with_other_protocol_version(|stream| abort.to_stream(stream).unwrap());
+    let err = SenderToReceiverFrontCommands::from_stream(&mut in_stream)
+        .expect_err("other protocol version");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let ack_start = ReceiverToSenderFrontCommands::AckStart(4);
+    let mut in_stream = with_other_protocol_version(|stream| ack_start.to_stream(stream).unwrap());
+    let err = ReceiverToSenderFrontCommands::from_stream(&mut in_stream)
+        .expect_err("other protocol version");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn hash_checkpoint_round_trip() {
+    let checkpoint = SenderToReceiverFrontCommands::HashCheckpoint(HashCheckpointData {
+        transfer_id: 3,
+        up_to_chunk: u64::from(u32::MAX) + 70_000,
+        hash: vec![0xde, 0xad, 0xbe, 0xef],
+    });
+
+    assert_eq!(checkpoint.to_octet(), 0x06);
+    assert_eq!(sender_round_trip(&checkpoint), checkpoint);
+}
+
+#[test]
+fn oversized_payload_length_is_rejected_before_allocating() {
+    let mut out_stream = OutOctetStream::new();
+    out_stream.write_u32(0).unwrap(); // chunk_index
+    out_stream.write_u8(ChunkCodec::Stored as u8).unwrap();
+    out_stream.write_u32(u32::MAX).unwrap(); // declared payload length, without a payload
+
+    let mut in_stream = InOctetStream::new(out_stream.octets_ref());
+    let err = SetChunkData::from_stream(&mut in_stream).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let chunk = SetChunkData {
+        chunk_index: 1,
+        codec: ChunkCodec::Stored,
+        payload: vec![0; MAX_PAYLOAD_OCTET_SIZE],
+    };
+    let mut out_stream = OutOctetStream::new();
+    chunk.to_stream(&mut out_stream).unwrap();
+    let mut in_stream = InOctetStream::new(out_stream.octets_ref());
+    assert_eq!(SetChunkData::from_stream(&mut in_stream).unwrap(), chunk);
+}
+
+#[test]
+fn transfer_id_display_conversions_and_map_key() {
+    let transfer_id = TransferId::from(513);
+
+    assert_eq!(format!("{transfer_id}"), "513");
+    assert_eq!(transfer_id, TransferId(513));
+    assert_eq!(u16::from(transfer_id), 513);
+    let raw: u16 = transfer_id.into();
+    assert_eq!(raw, 513);
+
+    let mut names = std::collections::HashMap::new();
+    names.insert(transfer_id, "level");
+    names.insert(TransferId(2), "avatar");
+    assert_eq!(names.get(&TransferId(513)), Some(&"level"));
+
+    let mut sorted: Vec<TransferId> = names.into_keys().collect();
+    sorted.sort();
+    assert_eq!(sorted, [TransferId(2), TransferId(513)]);
+}
+
+#[test]
+fn sender_commands_round_trip_through_bytes() {
+    let commands = [
+        SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+            transfer_id: 0x0102,
+            total_octet_size: 10,
+            chunk_size: 4,
+            schedule: vec![],
+            blob_hash: None,
+        }),
+        SenderToReceiverFrontCommands::SetChunk(SetChunkFrontData {
+            transfer_id: TransferId(0x0102),
+            data: SetChunkData {
+                chunk_index: 2,
+                codec: ChunkCodec::Stored,
+                payload: vec![7, 8],
+            },
+        }),
+    ];
+
+    for command in commands {
+        let octets = command.to_bytes().unwrap();
+        assert_eq!(octets.len(), command.write_to_slice(&mut [0; 64]).unwrap());
+        assert_eq!(
+            SenderToReceiverFrontCommands::from_bytes(&octets).unwrap(),
+            command
+        );
+    }
+}
+
+#[test]
+fn receiver_commands_round_trip_through_bytes() {
+    let commands = [
+        ReceiverToSenderFrontCommands::AckStart(3),
+        ReceiverToSenderFrontCommands::AckChunk(AckChunkFrontData {
+            transfer_id: TransferId(3),
+            data: AckChunkData {
+                waiting_for_chunk_index: 5,
+                receive_mask_after_last: 0b101,
+            },
+        }),
+        ReceiverToSenderFrontCommands::Completed(3),
+    ];
+
+    for command in commands {
+        let octets = command.to_bytes().unwrap();
+        assert_eq!(
+            ReceiverToSenderFrontCommands::from_bytes(&octets).unwrap(),
+            command
+        );
+    }
+
+    assert!(ReceiverToSenderFrontCommands::from_bytes(&[]).is_err());
+}
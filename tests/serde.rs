@@ -0,0 +1,63 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(all(feature = "std", feature = "serde"))]
+
+use blob_stream::prelude::*;
+use blob_stream::protocol::AckChunkData;
+use blob_stream::protocol_front::AckChunkFrontData;
+
+#[test]
+fn set_chunk_payload_is_octet_array() {
+    let set_chunk = SetChunkData {
+        chunk_index: 2,
+        codec: ChunkCodec::Stored,
+        payload: vec![0x01, 0xff],
+    };
+
+    let json = serde_json::to_string(&set_chunk).expect("should serialize");
+    assert_eq!(
+        json,
+        r#"{"chunk_index":2,"codec":"Stored","payload":[1,255]}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<SetChunkData>(&json).expect("should deserialize"),
+        set_chunk
+    );
+    // Chunks serialized before the codec was added are stored.
+    assert_eq!(
+        serde_json::from_str::<SetChunkData>(r#"{"chunk_index":2,"payload":[1,255]}"#)
+            .expect("should deserialize"),
+        set_chunk
+    );
+}
+
+#[test]
+fn front_commands_round_trip() {
+    let start = SenderToReceiverFrontCommands::StartTransfer(StartTransferData {
+        transfer_id: 7,
+        total_octet_size: 100,
+        chunk_size: 10,
+        schedule: vec![1, 0],
+        blob_hash: None,
+    });
+    let json = serde_json::to_string(&start).expect("should serialize");
+    assert_eq!(
+        serde_json::from_str::<SenderToReceiverFrontCommands>(&json).expect("should deserialize"),
+        start
+    );
+
+    let ack = ReceiverToSenderFrontCommands::AckChunk(AckChunkFrontData {
+        transfer_id: TransferId(7),
+        data: AckChunkData {
+            waiting_for_chunk_index: 3,
+            receive_mask_after_last: 0b101,
+        },
+    });
+    let json = serde_json::to_string(&ack).expect("should serialize");
+    assert_eq!(
+        serde_json::from_str::<ReceiverToSenderFrontCommands>(&json).expect("should deserialize"),
+        ack
+    );
+}
@@ -0,0 +1,50 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "memmap")]
+
+use blob_stream::in_stream::BlobStreamIn;
+use blob_stream::storage::MmapStorage;
+use std::fs::{self, OpenOptions};
+
+#[test]
+fn receive_into_memory_mapped_file() {
+    let path = std::env::temp_dir().join(format!("blob-stream-mmap-{}.bin", std::process::id()));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .expect("should be able to create temp file");
+
+    let storage = MmapStorage::new(&file, 9).expect("should be able to map file");
+    let mut stream = BlobStreamIn::with_storage(storage, 4);
+
+    stream
+        .set_chunk(2, &[0x42])
+        .expect("Setting chunk 2 should work");
+    stream
+        .set_chunk(0, &[0x31, 0x32, 0x33, 0x34])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(1, &[0xff, 0xfe, 0xfd, 0xfc])
+        .expect("Setting chunk 1 should work");
+
+    assert_eq!(
+        stream.blob().expect("Blob slice should be complete"),
+        &[0x31, 0x32, 0x33, 0x34, 0xff, 0xfe, 0xfd, 0xfc, 0x42]
+    );
+
+    stream.storage().flush().expect("flush should work");
+    drop(stream);
+
+    let contents = fs::read(&path).expect("should be able to read back file");
+    fs::remove_file(&path).expect("should be able to remove temp file");
+
+    assert_eq!(
+        contents,
+        [0x31, 0x32, 0x33, 0x34, 0xff, 0xfe, 0xfd, 0xfc, 0x42]
+    );
+}
@@ -2,7 +2,10 @@
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
-use blob_stream::in_stream::BlobStreamIn;
+use blob_stream::in_stream::{last_chunk_size_report, BlobStreamIn};
+use blob_stream::prelude::*;
+use blob_stream::storage::BlobStorage;
+use std::cell::Cell;
 
 #[test]
 fn chunks_out_of_order() {
@@ -36,5 +39,623 @@ fn chunks_out_of_order() {
     assert_eq!(
         stream.blob().expect("Blob slice should be complete"),
         &[0x31, 0x32, 0x33, 0x34, 0xff, 0xfe, 0xfd, 0xfc, 0x42]
-    )
+    );
+}
+
+#[test]
+fn last_chunk_of_exact_multiple_is_full_size() {
+    let mut stream = BlobStreamIn::new(8, 4);
+
+    stream
+        .set_chunk(0, &[0x31, 0x32, 0x33, 0x34])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(1, &[0xff, 0xfe, 0xfd, 0xfc])
+        .expect("Setting last chunk with full size should work");
+
+    assert!(stream.is_complete());
+    assert_eq!(
+        stream.blob().expect("Blob slice should be complete"),
+        &[0x31, 0x32, 0x33, 0x34, 0xff, 0xfe, 0xfd, 0xfc]
+    );
+}
+
+#[test]
+fn last_chunk_of_non_multiple_is_remainder() {
+    let mut stream = BlobStreamIn::new(9, 4);
+
+    let err = stream
+        .set_chunk(2, &[0x42, 0x43, 0x44, 0x45])
+        .expect_err("a full size last chunk should be rejected");
+    assert!(matches!(err, BlobError::UnexpectedChunkSize(1, 4, 2)));
+
+    stream
+        .set_chunk(2, &[0x42])
+        .expect("Setting last chunk with remaining size should work");
+    stream
+        .set_chunk(0, &[0x31, 0x32, 0x33, 0x34])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(1, &[0xff, 0xfe, 0xfd, 0xfc])
+        .expect("Setting chunk 1 should work");
+
+    assert!(stream.is_complete());
+}
+
+#[test]
+fn missing_chunks_during_transfer() {
+    let mut stream = BlobStreamIn::new(11, 5);
+
+    stream
+        .set_chunk(1, &[0xff, 0xfe, 0xfd, 0xfc, 0xfb])
+        .expect("Setting chunk 1 should work");
+
+    assert_eq!(stream.missing_chunks().collect::<Vec<_>>(), [0, 2]);
+}
+
+#[test]
+fn progress_during_transfer() {
+    let mut stream = BlobStreamIn::new(9, 4);
+
+    assert_eq!(stream.received_chunk_count(), 0);
+    assert!(stream.progress().abs() < f32::EPSILON);
+
+    stream
+        .set_chunk(0, &[0x31, 0x32, 0x33, 0x34])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(2, &[0x42])
+        .expect("Setting chunk 2 should work");
+
+    assert_eq!(stream.received_chunk_count(), 2);
+    assert!((stream.progress() - 0.666).abs() < 0.001);
+}
+
+#[test]
+fn take_complete_blob() {
+    let mut stream = BlobStreamIn::new(5, 4);
+    stream
+        .set_chunk(0, &[0x31, 0x32, 0x33, 0x34])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(1, &[0x42])
+        .expect("Setting chunk 1 should work");
+
+    assert_eq!(
+        stream.take_blob().expect("blob should be complete"),
+        vec![0x31, 0x32, 0x33, 0x34, 0x42]
+    );
+}
+
+#[test]
+fn take_incomplete_blob() {
+    let mut stream = BlobStreamIn::new(5, 4);
+    stream
+        .set_chunk(1, &[0x42])
+        .expect("Setting chunk 1 should work");
+
+    assert!(stream.take_blob().is_none());
+}
+
+#[test]
+fn get_individual_chunks() {
+    let mut stream = BlobStreamIn::new(9, 4);
+    stream
+        .set_chunk(0, &[0x31, 0x32, 0x33, 0x34])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(2, &[0x42])
+        .expect("Setting chunk 2 should work");
+
+    assert_eq!(
+        stream.get_chunk(0).expect("chunk index 0 is valid"),
+        Some(&[0x31, 0x32, 0x33, 0x34][..])
+    );
+    assert_eq!(
+        stream.get_chunk(2).expect("chunk index 2 is valid"),
+        Some(&[0x42][..])
+    );
+    assert_eq!(stream.get_chunk(1).expect("chunk index 1 is valid"), None);
+    assert!(matches!(
+        stream.get_chunk(3),
+        Err(BlobError::InvalidChunkIndex(3, 3))
+    ));
+}
+
+#[test]
+fn iterate_chunks_of_complete_blob() {
+    let mut stream = BlobStreamIn::new(9, 4);
+    stream
+        .set_chunk(0, &[0x31, 0x32, 0x33, 0x34])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(2, &[0x42])
+        .expect("Setting chunk 2 should work");
+
+    assert_eq!(stream.chunks().count(), 0);
+
+    stream
+        .set_chunk(1, &[0xff, 0xfe, 0xfd, 0xfc])
+        .expect("Setting chunk 1 should work");
+
+    let chunks: Vec<&[u8]> = stream.chunks().collect();
+    assert_eq!(chunks.len(), stream.chunk_count());
+    assert_eq!(
+        chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+        [4, 4, 1]
+    );
+    assert_eq!(chunks[2], &[0x42]);
+}
+
+#[test]
+fn resume_from_existing_blob() {
+    let mut stream = BlobStreamIn::from_existing(vec![0x31, 0x32, 0x33, 0x34, 0x00], 4, &[0])
+        .expect("chunk 0 is in range");
+
+    assert!(!stream.is_complete());
+    assert!(matches!(
+        stream.set_chunk(0, &[0x31, 0x32, 0x33, 0x34]),
+        Err(BlobError::RedundantSameContents(0))
+    ));
+
+    stream
+        .set_chunk(1, &[0x42])
+        .expect("Setting chunk 1 should work");
+
+    assert!(stream.is_complete());
+    assert_eq!(
+        stream.blob().expect("blob should be complete"),
+        &[0x31, 0x32, 0x33, 0x34, 0x42]
+    );
+}
+
+#[test]
+fn resume_from_existing_blob_with_invalid_index() {
+    assert!(matches!(
+        BlobStreamIn::from_existing(vec![0; 5], 4, &[0, 2]),
+        Err(BlobError::InvalidChunkIndex(2, 2))
+    ));
+}
+
+#[test]
+fn complete_blob_is_consistent() {
+    let mut stream = BlobStreamIn::new(8, 4);
+    stream
+        .set_chunk(0, &[1, 2, 3, 4])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(1, &[5, 6, 7, 8])
+        .expect("Setting chunk 1 should work");
+
+    stream
+        .assert_complete_consistency()
+        .expect("exact multiple should be consistent");
+}
+
+/// A storage that can be shrunk after it has been handed to the stream.
+struct ShrinkableStorage {
+    octets: Vec<u8>,
+    len: Cell<usize>,
+}
+
+impl BlobStorage for ShrinkableStorage {
+    fn octets(&self) -> &[u8] {
+        &self.octets[..self.len.get()]
+    }
+
+    fn octets_mut(&mut self) -> &mut [u8] {
+        &mut self.octets[..self.len.get()]
+    }
+}
+
+#[test]
+fn inconsistent_storage_is_detected() {
+    let storage = ShrinkableStorage {
+        octets: vec![0; 9],
+        len: Cell::new(9),
+    };
+    let mut stream = BlobStreamIn::with_storage(storage, 4);
+    stream
+        .set_chunk(0, &[1, 2, 3, 4])
+        .expect("Setting chunk 0 should work");
+    stream
+        .set_chunk(1, &[5, 6, 7, 8])
+        .expect("Setting chunk 1 should work");
+    stream
+        .set_chunk(2, &[9])
+        .expect("Setting chunk 2 should work");
+    stream
+        .assert_complete_consistency()
+        .expect("should be consistent before the storage is shrunk");
+
+    stream.storage().len.set(8);
+
+    assert!(matches!(
+        stream.assert_complete_consistency(),
+        Err(BlobError::InconsistentOctetCount(9, 8))
+    ));
+}
+
+#[test]
+fn chunk_past_the_end_of_shrunk_storage_exceeds_blob() {
+    let storage = ShrinkableStorage {
+        octets: vec![0; 9],
+        len: Cell::new(9),
+    };
+    let mut stream = BlobStreamIn::with_storage(storage, 4);
+    stream.storage().len.set(7);
+
+    let err = stream.set_chunk(1, &[5, 6, 7, 8]).unwrap_err();
+    assert!(matches!(
+        err,
+        BlobError::PayloadExceedsBlob {
+            chunk_index: 1,
+            end: 8,
+            blob_len: 7
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "chunk 1 ends at octet 8, past the end of the blob of 7 octets"
+    );
+    assert_eq!(stream.received_chunk_count(), 0);
+}
+
+#[test]
+fn set_contiguous_chunks_with_partial_last_chunk() {
+    let mut stream = BlobStreamIn::new(10, 4);
+
+    let set_count = stream
+        .set_contiguous(1, &[5, 6, 7, 8, 9, 10])
+        .expect("chunks 1 and 2 should be set");
+    assert_eq!(set_count, 2);
+    assert_eq!(stream.missing_chunks().collect::<Vec<_>>(), [0]);
+
+    let set_count = stream
+        .set_contiguous(0, &[1, 2, 3, 4])
+        .expect("chunk 0 should be set");
+    assert_eq!(set_count, 1);
+
+    assert_eq!(
+        stream.blob().expect("blob should be complete"),
+        &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+    );
+}
+
+#[test]
+fn truncate_can_not_grow_the_blob() {
+    let mut stream = BlobStreamIn::new(10, 4);
+
+    assert!(matches!(
+        stream.truncate(11),
+        Err(BlobError::TruncateBeyondSize(11, 10))
+    ));
+    stream.truncate(8).expect("shrinking should work");
+    assert_eq!(stream.chunk_count(), 2);
+}
+
+#[test]
+fn set_contiguous_rejects_invalid_regions() {
+    let mut stream = BlobStreamIn::new(10, 4);
+
+    assert!(matches!(
+        stream.set_contiguous(0, &[0; 6]),
+        Err(BlobError::UnexpectedChunkSize(4, 2, 1))
+    ));
+    assert!(matches!(
+        stream.set_contiguous(2, &[0; 4]),
+        Err(BlobError::PayloadExceedsBlob {
+            chunk_index: 2,
+            end: 12,
+            blob_len: 10
+        })
+    ));
+    assert!(matches!(
+        stream.set_contiguous(3, &[0; 1]),
+        Err(BlobError::InvalidChunkIndex(3, 3))
+    ));
+
+    stream
+        .set_chunk(1, &[5, 6, 7, 8])
+        .expect("Setting chunk 1 should work");
+    assert!(matches!(
+        stream.set_contiguous(0, &[1, 2, 3, 4, 5, 6, 7, 8]),
+        Err(BlobError::RedundantSameContents(1))
+    ));
+    assert_eq!(stream.get_chunk(0).expect("chunk index 0 is valid"), None);
+}
+
+#[test]
+fn cloned_partial_stream_converges() {
+    let mut stream = BlobStreamIn::new(10, 4);
+    stream.set_chunk(1, &[5, 6, 7, 8]).unwrap();
+
+    let mut cloned = stream.clone();
+    assert_eq!(cloned, stream);
+
+    stream.set_chunk(0, &[1, 2, 3, 4]).unwrap();
+    assert_ne!(cloned, stream);
+
+    // Receiving a redundant chunk on the clone only adds to the wasted bytes.
+    cloned.set_chunk(1, &[5, 6, 7, 8]).unwrap_err();
+    cloned.set_chunk(0, &[1, 2, 3, 4]).unwrap();
+    assert_eq!(cloned, stream);
+    assert_ne!(cloned.wasted_bytes(), stream.wasted_bytes());
+}
+
+#[test]
+fn missing_chunk_queries_match_brute_force_scan() {
+    let mut random_state: u32 = 0x0bad_5eed;
+    for chunk_count in [1, 7, 64, 65, 200] {
+        for fill_percent in [0, 10, 50, 90, 100] {
+            let mut stream = BlobStreamIn::new(chunk_count, 1);
+            let mut received = vec![false; chunk_count];
+            for (chunk_index, is_received) in received.iter_mut().enumerate() {
+                random_state = random_state
+                    .wrapping_mul(1_664_525)
+                    .wrapping_add(1_013_904_223);
+                if random_state % 100 < fill_percent {
+                    stream.set_chunk(chunk_index, &[0x42]).unwrap();
+                    *is_received = true;
+                }
+            }
+
+            let expected_missing: Vec<usize> = (0..chunk_count)
+                .filter(|&chunk_index| !received[chunk_index])
+                .collect();
+            let mut missing = Vec::new();
+            stream.for_each_missing(|chunk_index| missing.push(chunk_index));
+
+            assert_eq!(missing, expected_missing);
+            assert_eq!(stream.first_missing(), expected_missing.first().copied());
+            assert_eq!(
+                stream.received_chunk_count(),
+                chunk_count - expected_missing.len()
+            );
+        }
+    }
+}
+
+#[test]
+fn identical_resent_chunk_is_ignored() {
+    let mut stream = BlobStreamIn::new(8, 4).with_ignore_duplicates(true);
+    stream.set_chunk(0, &[1, 2, 3, 4]).unwrap();
+
+    stream
+        .set_chunk(0, &[1, 2, 3, 4])
+        .expect("identical resent chunk should be accepted");
+    assert_eq!(stream.received_chunk_count(), 1);
+    assert_eq!(stream.wasted_bytes(), 4);
+}
+
+#[test]
+fn differing_resent_chunk_is_rejected_when_ignoring_duplicates() {
+    let mut stream = BlobStreamIn::new(8, 4).with_ignore_duplicates(true);
+    stream.set_chunk(0, &[1, 2, 3, 4]).unwrap();
+
+    let err = stream
+        .set_chunk(0, &[1, 2, 3, 5])
+        .expect_err("different contents should be rejected");
+    assert!(matches!(err, BlobError::RedundantContentDiffers(0)));
+    assert_eq!(stream.get_chunk(0).unwrap(), Some(&[1, 2, 3, 4][..]));
+}
+
+#[test]
+fn identical_resent_chunk_is_rejected_by_default() {
+    let mut stream = BlobStreamIn::new(8, 4);
+    stream.set_chunk(0, &[1, 2, 3, 4]).unwrap();
+
+    let err = stream.set_chunk(0, &[1, 2, 3, 4]).unwrap_err();
+    assert!(matches!(err, BlobError::RedundantSameContents(0)));
+}
+
+#[test]
+fn last_chunk_size_report_differs_only_on_exact_multiples() {
+    assert_eq!(last_chunk_size_report(12, 4), (0, 4));
+    assert_eq!(last_chunk_size_report(4, 4), (0, 4));
+    assert_eq!(last_chunk_size_report(10, 4), (2, 2));
+    assert_eq!(last_chunk_size_report(3, 4), (3, 3));
+    assert_eq!(last_chunk_size_report(0, 4), (0, 0));
+
+    for octet_count in 1..100 {
+        let (naive, correct) = last_chunk_size_report(octet_count, 7);
+        assert_eq!(naive != correct, octet_count % 7 == 0);
+        let stream = BlobStreamIn::new(octet_count, 7);
+        let last_chunk = stream.chunk_count() - 1;
+        assert_eq!(correct, octet_count - last_chunk * 7);
+    }
+}
+
+#[test]
+fn zero_octet_stream_is_complete() {
+    let stream = BlobStreamIn::new(0, 4);
+
+    assert_eq!(stream.chunk_count(), 0);
+    assert!(stream.is_complete());
+    assert_eq!(stream.blob(), Some(&[][..]));
+    assert!(stream.assert_complete_consistency().is_ok());
+}
+
+#[test]
+fn contiguous_prefix_ends_at_first_missing_chunk() {
+    let mut stream = BlobStreamIn::new(11, 5);
+    assert!(stream.contiguous_prefix().is_empty());
+
+    stream.set_chunk(1, &[2; 5]).unwrap();
+    assert!(stream.contiguous_prefix().is_empty());
+
+    stream.set_chunk(0, &[1; 5]).unwrap();
+    assert_eq!(stream.contiguous_prefix(), [1, 1, 1, 1, 1, 2, 2, 2, 2, 2]);
+
+    stream.set_chunk(2, &[3]).unwrap();
+    assert_eq!(stream.contiguous_prefix().len(), 11);
+}
+
+#[test]
+fn reset_to_smaller_size_is_clean() {
+    let mut stream = BlobStreamIn::new(12, 4);
+    for chunk_index in 0..3 {
+        stream.set_chunk(chunk_index, &[0xff; 4]).unwrap();
+    }
+    assert!(stream.set_chunk(0, &[0xff; 4]).is_err());
+    let capacity = stream.storage().capacity();
+
+    stream.reset(5, 2);
+
+    assert_eq!(stream.storage().capacity(), capacity);
+    assert_eq!(stream.chunk_count(), 3);
+    assert_eq!(stream.received_chunk_count(), 0);
+    assert_eq!(stream.wasted_bytes(), 0);
+    assert!(!stream.is_complete());
+    assert_eq!(stream, BlobStreamIn::new(5, 2));
+
+    stream.set_chunk(2, &[7]).unwrap();
+    assert_eq!(stream.storage(), &[0, 0, 0, 0, 7]);
+}
+
+#[test]
+fn try_new_rejects_zero_chunk_size() {
+    assert!(matches!(
+        BlobStreamIn::try_new(10, 0),
+        Err(BlobError::InvalidChunkSize(0))
+    ));
+    assert_eq!(BlobStreamIn::try_new(10, 4).unwrap().chunk_count(), 3);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn reader_reads_complete_blob() {
+    use std::io::Read;
+
+    let mut stream = BlobStreamIn::new(10, 4);
+    stream.set_chunk(2, &[3; 2]).unwrap();
+    stream.set_chunk(0, &[1; 4]).unwrap();
+    assert!(stream.reader().is_none());
+    stream.set_chunk(1, &[2; 4]).unwrap();
+
+    let mut octets = Vec::new();
+    let octet_count = stream.reader().unwrap().read_to_end(&mut octets).unwrap();
+
+    assert_eq!(octet_count, 10);
+    assert_eq!(octets, [1, 1, 1, 1, 2, 2, 2, 2, 3, 3]);
+
+    let mut reader = stream.reader().unwrap();
+    let mut first = [0; 6];
+    reader.read_exact(&mut first).unwrap();
+    assert_eq!(first, [1, 1, 1, 1, 2, 2]);
+    let mut rest = [0; 6];
+    assert_eq!(reader.read(&mut rest).unwrap(), 4);
+    assert_eq!(reader.read(&mut rest).unwrap(), 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn with_writer_writes_chunks_to_sink() {
+    use std::io::Cursor;
+
+    let mut stream = BlobStreamIn::with_writer(9, 4, Cursor::new(Vec::new()));
+    stream.set_chunk(2, &[0x42]).unwrap();
+    stream.set_chunk(0, &[1, 2, 3, 4]).unwrap();
+    assert!(!stream.is_complete());
+    assert_eq!(stream.missing_chunks().collect::<Vec<_>>(), [1]);
+    stream.set_chunk(1, &[5, 6, 7, 8]).unwrap();
+
+    assert!(stream.is_complete());
+    assert!(stream.blob().is_none());
+    assert!(stream.get_chunk(1).unwrap().is_none());
+    assert!(matches!(
+        stream.set_chunk(1, &[5, 6, 7, 8]),
+        Err(BlobError::RedundantSameContents(1))
+    ));
+    assert_eq!(
+        stream.storage().writer().get_ref(),
+        &[1, 2, 3, 4, 5, 6, 7, 8, 0x42]
+    );
+}
+
+#[cfg(feature = "sha256")]
+#[test]
+fn incremental_blob_hash_matches_one_shot_hash() {
+    use sha2::{Digest, Sha256};
+
+    let blob: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let mut stream = BlobStreamIn::new(blob.len(), 64);
+    let chunk_count = stream.chunk_count();
+
+    // Every other chunk first, so the contiguous prefix only grows in the second pass.
+    for chunk_index in (1..chunk_count)
+        .step_by(2)
+        .chain((0..chunk_count).step_by(2))
+    {
+        assert_eq!(stream.blob_hash(), None);
+        let start = chunk_index * 64;
+        let end = (start + 64).min(blob.len());
+        stream.set_chunk(chunk_index, &blob[start..end]).unwrap();
+    }
+
+    let expected: [u8; 32] = Sha256::digest(&blob).into();
+    assert_eq!(stream.blob_hash(), Some(expected));
+}
+
+#[test]
+fn variable_size_chunks_are_placed_at_cumulative_offsets() {
+    let mut stream = BlobStreamIn::with_chunk_sizes(9, &[3, 5, 1]).unwrap();
+    assert_eq!(stream.chunk_count(), 3);
+
+    stream.set_chunk(2, &[9]).unwrap();
+    stream.set_chunk(0, &[1, 2, 3]).unwrap();
+    assert_eq!(stream.contiguous_prefix(), &[1, 2, 3]);
+    assert!(!stream.is_complete());
+
+    assert!(matches!(
+        stream.set_chunk(1, &[4, 5, 6]),
+        Err(BlobError::UnexpectedChunkSize(5, 3, 1))
+    ));
+    stream.set_chunk(1, &[4, 5, 6, 7, 8]).unwrap();
+
+    assert!(stream.is_complete());
+    assert_eq!(stream.blob(), Some(&[1, 2, 3, 4, 5, 6, 7, 8, 9][..]));
+    assert_eq!(stream.get_chunk(1).unwrap(), Some(&[4, 5, 6, 7, 8][..]));
+    let chunks: Vec<&[u8]> = stream.chunks().collect();
+    assert_eq!(chunks, [&[1, 2, 3][..], &[4, 5, 6, 7, 8], &[9]]);
+    stream.assert_complete_consistency().unwrap();
+}
+
+#[test]
+fn variable_size_chunks_set_contiguously() {
+    let mut stream = BlobStreamIn::with_chunk_sizes(9, &[3, 5, 1]).unwrap();
+
+    assert!(matches!(
+        stream.set_contiguous(0, &[1, 2, 3, 4]),
+        Err(BlobError::UnexpectedChunkSize(5, 1, 1))
+    ));
+    assert_eq!(stream.set_contiguous(1, &[4, 5, 6, 7, 8, 9]).unwrap(), 2);
+    assert_eq!(stream.missing_chunks().collect::<Vec<_>>(), [0]);
+}
+
+#[test]
+fn chunk_sizes_must_add_up_to_octet_count() {
+    assert!(matches!(
+        BlobStreamIn::with_chunk_sizes(10, &[3, 5, 1]),
+        Err(BlobError::InconsistentOctetCount(10, 9))
+    ));
+    assert!(matches!(
+        BlobStreamIn::with_chunk_sizes(8, &[3, 0, 5]),
+        Err(BlobError::InvalidChunkSize(0))
+    ));
+}
+
+#[test]
+fn received_ranges_merge_consecutive_chunks() {
+    let mut stream = BlobStreamIn::new(14, 4);
+    assert!(stream.received_ranges().is_empty());
+
+    stream.set_chunk(0, &[0; 4]).unwrap();
+    stream.set_chunk(1, &[1; 4]).unwrap();
+    stream.set_chunk(3, &[3; 2]).unwrap();
+
+    assert_eq!(stream.received_ranges(), [0..8, 12..14]);
+
+    stream.set_chunk(2, &[2; 4]).unwrap();
+    assert_eq!(stream.received_ranges(), vec![0..14]);
 }
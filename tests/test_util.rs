@@ -0,0 +1,45 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+#![cfg(feature = "test-util")]
+
+use blob_stream::test_util::drive_to_completion;
+use blob_stream::{in_logic, out_logic};
+
+#[test]
+fn lossless_transfer_completes_in_one_round() {
+    let blob: Vec<u8> = (0..=255).collect();
+    let mut out_logic = out_logic::Logic::new(blob.clone(), 16).with_window_size(16);
+    let mut in_logic = in_logic::Logic::new(blob.len(), 16);
+
+    let round_count = drive_to_completion(&mut out_logic, &mut in_logic, |_| false).unwrap();
+
+    assert_eq!(round_count, 1);
+    assert_eq!(in_logic.blob(), Some(blob.as_slice()));
+}
+
+#[test]
+fn transfer_with_half_of_the_chunks_lost_converges() {
+    let blob: Vec<u8> = (0..=250).cycle().take(1000).collect();
+    let mut out_logic = out_logic::Logic::new(blob.clone(), 10);
+    let mut in_logic = in_logic::Logic::new(blob.len(), 10);
+
+    // A xorshift generator, so the lost chunks are the same on every run.
+    let mut state: u32 = 0x1234_5678;
+    let mut lost_count = 0;
+    let round_count = drive_to_completion(&mut out_logic, &mut in_logic, |_| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let is_lost = state & 1 == 0;
+        lost_count += usize::from(is_lost);
+        is_lost
+    })
+    .unwrap();
+
+    assert!(lost_count > 0);
+    assert!(round_count > 1);
+    assert!(out_logic.is_complete());
+    assert_eq!(in_logic.blob(), Some(blob.as_slice()));
+}
@@ -0,0 +1,33 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/blob-stream-rs
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use blob_stream::window::window_for_bdp;
+use std::time::Duration;
+
+#[test]
+fn exact_bandwidth_delay_product() {
+    // 100 ms * 1 MB/s = 100 000 octets = 100 chunks of 1000 octets
+    assert_eq!(
+        window_for_bdp(Duration::from_millis(100), 1_000_000.0, 1000),
+        100
+    );
+}
+
+#[test]
+fn partial_chunk_rounds_up() {
+    // 50 ms * 64 000 B/s = 3200 octets = 3.125 chunks of 1024 octets
+    assert_eq!(window_for_bdp(Duration::from_millis(50), 64_000.0, 1024), 4);
+
+    // 250 ms * 10 MB/s = 2 500 000 octets = 2083.33 chunks of 1200 octets
+    assert_eq!(
+        window_for_bdp(Duration::from_millis(250), 10_000_000.0, 1200),
+        2084
+    );
+}
+
+#[test]
+fn window_is_at_least_one_chunk() {
+    assert_eq!(window_for_bdp(Duration::ZERO, 1_000_000.0, 1200), 1);
+    assert_eq!(window_for_bdp(Duration::from_millis(10), 0.0, 1200), 1);
+}